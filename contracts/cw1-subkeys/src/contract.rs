@@ -5,29 +5,37 @@ use std::ops::{AddAssign, Sub};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    ensure, ensure_ne, to_json_binary, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut,
-    DistributionMsg, Empty, Env, MessageInfo, Order, Response, StakingMsg, StdResult,
+    ensure, ensure_ne, from_json, to_json_binary, Addr, Api, BankMsg, Binary, Coin, CosmosMsg,
+    Deps, DepsMut, DistributionMsg, Empty, Env, MessageInfo, Order, Response, StakingMsg, StdError,
+    StdResult, Storage, Uint128, WasmMsg,
 };
+#[cfg(feature = "stargate")]
+use cosmwasm_std::{GovMsg, IbcMsg};
 use cw1::CanExecuteResponse;
 use cw1_whitelist::{
+    audit::{query_audit_config, query_execution_history, record_execution, set_audit_config},
     contract::{
         execute_freeze, execute_update_admins, instantiate as whitelist_instantiate,
         query_admin_list,
     },
     msg::InstantiateMsg,
+    policy::execute_msg_name,
     state::ADMIN_LIST,
 };
 use cw2::{get_contract_version, set_contract_version};
+use cw20::Cw20ExecuteMsg;
 use cw_storage_plus::Bound;
-use cw_utils::Expiration;
+use cw_utils::{Duration, Expiration, NativeBalance};
 use semver::Version;
 
 use crate::error::ContractError;
 use crate::msg::{
-    AllAllowancesResponse, AllPermissionsResponse, AllowanceInfo, ExecuteMsg, PermissionsInfo,
-    QueryMsg,
+    AllAllowancesResponse, AllPermissionsResponse, AllowanceInfo, Cw20AllowanceInfo,
+    Cw20AllowancesResponse, ExecuteMsg, PermissionsInfo, QueryMsg,
+};
+use crate::state::{
+    Allowance, Period, Permissions, ALLOWANCES, CW20_ALLOWANCES, DELEGATIONS, PERMISSIONS,
 };
-use crate::state::{Allowance, Permissions, ALLOWANCES, PERMISSIONS};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:cw1-subkeys";
@@ -58,6 +66,7 @@ pub fn execute(
         ExecuteMsg::Execute { msgs } => execute_execute(deps, env, info, msgs),
         ExecuteMsg::Freeze {} => Ok(execute_freeze(deps, env, info)?),
         ExecuteMsg::UpdateAdmins { admins } => Ok(execute_update_admins(deps, env, info, admins)?),
+        ExecuteMsg::SetAuditConfig { config } => Ok(set_audit_config(deps, info, config)?),
         ExecuteMsg::IncreaseAllowance {
             spender,
             amount,
@@ -68,6 +77,23 @@ pub fn execute(
             amount,
             expires,
         } => execute_decrease_allowance(deps, env, info, spender, amount, expires),
+        ExecuteMsg::SetPeriodicAllowance {
+            spender,
+            cap,
+            period,
+        } => execute_set_periodic_allowance(deps, env, info, spender, cap, period),
+        ExecuteMsg::IncreaseCw20Allowance {
+            spender,
+            token,
+            amount,
+            expires,
+        } => execute_increase_cw20_allowance(deps, env, info, spender, token, amount, expires),
+        ExecuteMsg::DecreaseCw20Allowance {
+            spender,
+            token,
+            amount,
+            expires,
+        } => execute_decrease_cw20_allowance(deps, env, info, spender, token, amount, expires),
         ExecuteMsg::SetPermissions {
             spender,
             permissions,
@@ -93,28 +119,53 @@ where
                 CosmosMsg::Staking(staking_msg) => {
                     let perm = PERMISSIONS.may_load(deps.storage, &info.sender)?;
                     let perm = perm.ok_or(ContractError::NotAllowed {})?;
-                    check_staking_permissions(staking_msg, perm)?;
+                    check_staking_permissions(staking_msg, &perm)?;
+                    track_delegation(deps.storage, &info.sender, staking_msg, &perm)?;
                 }
                 CosmosMsg::Distribution(distribution_msg) => {
                     let perm = PERMISSIONS.may_load(deps.storage, &info.sender)?;
                     let perm = perm.ok_or(ContractError::NotAllowed {})?;
-                    check_distribution_permissions(distribution_msg, perm)?;
+                    check_distribution_permissions(distribution_msg, &perm)?;
+                    #[cfg(feature = "stargate")]
+                    if let DistributionMsg::FundCommunityPool { amount } = distribution_msg {
+                        spend_allowance(deps.storage, &env, &info.sender, amount.clone())?;
+                    }
+                }
+                #[cfg(feature = "stargate")]
+                CosmosMsg::Gov(gov_msg) => {
+                    let perm = PERMISSIONS.may_load(deps.storage, &info.sender)?;
+                    let perm = perm.ok_or(ContractError::NotAllowed {})?;
+                    check_gov_permissions(gov_msg, &perm)?;
+                }
+                #[cfg(feature = "stargate")]
+                CosmosMsg::Ibc(ibc_msg) => {
+                    let perm = PERMISSIONS.may_load(deps.storage, &info.sender)?;
+                    let perm = perm.ok_or(ContractError::NotAllowed {})?;
+                    check_ibc_permissions(ibc_msg, &perm)?;
+                    if let IbcMsg::Transfer { amount, .. } = ibc_msg {
+                        spend_allowance(deps.storage, &env, &info.sender, vec![amount.clone()])?;
+                    }
+                }
+                CosmosMsg::Wasm(wasm_msg) => {
+                    let cw20 = covered_cw20_spend(deps.as_ref(), &info.sender, wasm_msg)?;
+                    if let Some((token, amount)) = cw20 {
+                        spend_cw20_allowance(deps.storage, &env, &info.sender, &token, amount)?;
+                    } else {
+                        let perm = PERMISSIONS.may_load(deps.storage, &info.sender)?;
+                        let perm = perm.ok_or(ContractError::NotAllowed {})?;
+                        check_wasm_permissions(wasm_msg, &perm)?;
+                        if let WasmMsg::Execute { funds, .. } = wasm_msg {
+                            if !funds.is_empty() {
+                                spend_allowance(deps.storage, &env, &info.sender, funds.clone())?;
+                            }
+                        }
+                    }
                 }
                 CosmosMsg::Bank(BankMsg::Send {
                     to_address: _,
                     amount,
                 }) => {
-                    ALLOWANCES.update::<_, ContractError>(deps.storage, &info.sender, |allow| {
-                        let mut allowance = allow.ok_or(ContractError::NoAllowance {})?;
-                        ensure!(
-                            !allowance.expires.is_expired(&env.block),
-                            ContractError::NoAllowance {}
-                        );
-
-                        // Decrease allowance
-                        allowance.balance = allowance.balance.sub(amount.clone())?;
-                        Ok(allowance)
-                    })?;
+                    spend_allowance(deps.storage, &env, &info.sender, amount.clone())?;
                 }
                 _ => {
                     return Err(ContractError::MessageTypeRejected {});
@@ -122,6 +173,8 @@ where
             }
         }
     }
+    record_execution(deps.storage, &env.block, &info.sender, &msgs)?;
+
     // Relay messages
     let res = Response::new()
         .add_messages(msgs)
@@ -130,28 +183,214 @@ where
     Ok(res)
 }
 
+// Decreases the allowance of the spender, failing if it is expired or too low
+fn spend_allowance(
+    storage: &mut dyn Storage,
+    env: &Env,
+    spender: &Addr,
+    amount: Vec<Coin>,
+) -> Result<(), ContractError> {
+    ALLOWANCES.update::<_, ContractError>(storage, spender, |allow| {
+        let mut allowance = allow.ok_or(ContractError::NoAllowance {})?;
+        ensure!(
+            !allowance.expires.is_expired(&env.block),
+            ContractError::NoAllowance {}
+        );
+        allowance.refresh(&env.block);
+
+        // Decrease allowance
+        allowance.balance = allowance.balance.sub(amount)?;
+        Ok(allowance)
+    })?;
+    Ok(())
+}
+
+// Returns the token and amount if the message transfers cw20 tokens held by the proxy
+fn cw20_spend(api: &dyn Api, wasm_msg: &WasmMsg) -> StdResult<Option<(Addr, Uint128)>> {
+    if let WasmMsg::Execute {
+        contract_addr,
+        msg,
+        funds,
+    } = wasm_msg
+    {
+        if funds.is_empty() {
+            match from_json(msg) {
+                Ok(Cw20ExecuteMsg::Transfer { amount, .. })
+                | Ok(Cw20ExecuteMsg::Send { amount, .. }) => {
+                    return Ok(Some((api.addr_validate(contract_addr)?, amount)));
+                }
+                Ok(Cw20ExecuteMsg::BatchTransfer { transfers }) => {
+                    let amount = transfers
+                        .iter()
+                        .try_fold(Uint128::zero(), |sum, t| sum.checked_add(t.amount))?;
+                    return Ok(Some((api.addr_validate(contract_addr)?, amount)));
+                }
+                Ok(Cw20ExecuteMsg::BatchSend { sends }) => {
+                    let amount = sends
+                        .iter()
+                        .try_fold(Uint128::zero(), |sum, s| sum.checked_add(s.amount))?;
+                    return Ok(Some((api.addr_validate(contract_addr)?, amount)));
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Cw20 transfers are covered by cw20 allowances when the spender has one for the token,
+// any other message needs contract permissions
+fn covered_cw20_spend(
+    deps: Deps,
+    spender: &Addr,
+    wasm_msg: &WasmMsg,
+) -> StdResult<Option<(Addr, Uint128)>> {
+    Ok(cw20_spend(deps.api, wasm_msg)?
+        .filter(|(token, _)| CW20_ALLOWANCES.has(deps.storage, (spender, token))))
+}
+
+// Decreases the cw20 allowance of the spender, failing if it is expired or too low
+fn spend_cw20_allowance(
+    storage: &mut dyn Storage,
+    env: &Env,
+    spender: &Addr,
+    token: &Addr,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    CW20_ALLOWANCES.update::<_, ContractError>(storage, (spender, token), |allow| {
+        let mut allowance = allow.ok_or(ContractError::NoAllowance {})?;
+        ensure!(
+            !allowance.expires.is_expired(&env.block),
+            ContractError::NoAllowance {}
+        );
+
+        allowance.amount = allowance
+            .amount
+            .checked_sub(amount)
+            .map_err(StdError::from)?;
+        Ok(allowance)
+    })?;
+    Ok(())
+}
+
 pub fn check_staking_permissions(
     staking_msg: &StakingMsg,
-    permissions: Permissions,
+    permissions: &Permissions,
 ) -> Result<(), ContractError> {
     match staking_msg {
-        StakingMsg::Delegate { .. } => {
+        StakingMsg::Delegate { validator, .. } => {
             ensure!(permissions.delegate, ContractError::DelegatePerm {});
+            check_validator(validator, permissions)?;
         }
-        StakingMsg::Undelegate { .. } => {
+        StakingMsg::Undelegate { validator, .. } => {
             ensure!(permissions.undelegate, ContractError::UnDelegatePerm {});
+            check_validator(validator, permissions)?;
         }
-        StakingMsg::Redelegate { .. } => {
+        StakingMsg::Redelegate {
+            src_validator,
+            dst_validator,
+            ..
+        } => {
             ensure!(permissions.redelegate, ContractError::ReDelegatePerm {});
+            check_validator(src_validator, permissions)?;
+            check_validator(dst_validator, permissions)?;
         }
         _ => return Err(ContractError::UnsupportedMessage {}),
     }
+    if let Some(amount) = delegated_amount(staking_msg) {
+        check_delegation_limit(amount, permissions)?;
+    }
+    Ok(())
+}
+
+// Returns the amount moved to a validator by a delegation or redelegation
+fn delegated_amount(staking_msg: &StakingMsg) -> Option<&Coin> {
+    match staking_msg {
+        StakingMsg::Delegate { amount, .. } | StakingMsg::Redelegate { amount, .. } => Some(amount),
+        _ => None,
+    }
+}
+
+fn check_validator(validator: &str, permissions: &Permissions) -> Result<(), ContractError> {
+    if let Some(validators) = &permissions.validators {
+        ensure!(
+            validators.iter().any(|v| v == validator),
+            ContractError::ValidatorPerm {
+                validator: validator.to_owned()
+            }
+        );
+    }
+    Ok(())
+}
+
+fn check_delegation_limit(amount: &Coin, permissions: &Permissions) -> Result<(), ContractError> {
+    if let Some(max_delegation) = permissions.max_delegation {
+        ensure!(
+            amount.amount <= max_delegation,
+            ContractError::DelegationLimitExceeded {}
+        );
+    }
+    Ok(())
+}
+
+// Returns what the spender would have delegated to the validator after adding the amount,
+// failing if it exceeds the delegation limit
+fn delegation_total(
+    storage: &dyn Storage,
+    spender: &Addr,
+    validator: &str,
+    amount: &Coin,
+    permissions: &Permissions,
+) -> Result<Uint128, ContractError> {
+    let delegated = DELEGATIONS
+        .may_load(storage, (spender, validator, &amount.denom))?
+        .unwrap_or_default();
+    let total = delegated
+        .checked_add(amount.amount)
+        .map_err(StdError::from)?;
+    check_delegation_limit(&Coin::new(total, &amount.denom), permissions)?;
+    Ok(total)
+}
+
+// Keeps the amount the spender delegated to each validator up to date, so the delegation
+// limit holds across executions
+fn track_delegation(
+    storage: &mut dyn Storage,
+    spender: &Addr,
+    staking_msg: &StakingMsg,
+    permissions: &Permissions,
+) -> Result<(), ContractError> {
+    let (src_validator, dst_validator, amount) = match staking_msg {
+        StakingMsg::Delegate { validator, amount } => (None, Some(validator), amount),
+        StakingMsg::Undelegate { validator, amount } => (Some(validator), None, amount),
+        StakingMsg::Redelegate {
+            src_validator,
+            dst_validator,
+            amount,
+        } => (Some(src_validator), Some(dst_validator), amount),
+        _ => return Ok(()),
+    };
+    if let Some(validator) = src_validator {
+        // the spender may also move delegations made by the admins
+        let key = (spender, validator.as_str(), amount.denom.as_str());
+        let delegated = DELEGATIONS.may_load(storage, key)?.unwrap_or_default();
+        let remaining = delegated.saturating_sub(amount.amount);
+        if remaining.is_zero() {
+            DELEGATIONS.remove(storage, key);
+        } else {
+            DELEGATIONS.save(storage, key, &remaining)?;
+        }
+    }
+    if let Some(validator) = dst_validator {
+        let total = delegation_total(storage, spender, validator, amount, permissions)?;
+        DELEGATIONS.save(storage, (spender, validator, &amount.denom), &total)?;
+    }
     Ok(())
 }
 
 pub fn check_distribution_permissions(
     distribution_msg: &DistributionMsg,
-    permissions: Permissions,
+    permissions: &Permissions,
 ) -> Result<(), ContractError> {
     match distribution_msg {
         DistributionMsg::SetWithdrawAddress { .. } => {
@@ -160,6 +399,84 @@ pub fn check_distribution_permissions(
         DistributionMsg::WithdrawDelegatorReward { .. } => {
             ensure!(permissions.withdraw, ContractError::WithdrawPerm {});
         }
+        #[cfg(feature = "stargate")]
+        DistributionMsg::FundCommunityPool { .. } => {
+            ensure!(
+                permissions.fund_community_pool,
+                ContractError::FundCommunityPoolPerm {}
+            );
+        }
+        _ => return Err(ContractError::UnsupportedMessage {}),
+    }
+    Ok(())
+}
+
+#[cfg(feature = "stargate")]
+pub fn check_gov_permissions(
+    gov_msg: &GovMsg,
+    permissions: &Permissions,
+) -> Result<(), ContractError> {
+    match gov_msg {
+        GovMsg::Vote { .. } | GovMsg::VoteWeighted { .. } => {
+            ensure!(permissions.vote, ContractError::VotePerm {});
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "stargate")]
+pub fn check_ibc_permissions(
+    ibc_msg: &IbcMsg,
+    permissions: &Permissions,
+) -> Result<(), ContractError> {
+    match ibc_msg {
+        IbcMsg::Transfer { channel_id, .. } => {
+            ensure!(
+                permissions.ibc_channels.contains(channel_id),
+                ContractError::IbcChannelPerm {
+                    channel: channel_id.clone()
+                }
+            );
+        }
+        _ => return Err(ContractError::UnsupportedMessage {}),
+    }
+    Ok(())
+}
+
+pub fn check_wasm_permissions(
+    wasm_msg: &WasmMsg,
+    permissions: &Permissions,
+) -> Result<(), ContractError> {
+    match wasm_msg {
+        WasmMsg::Execute {
+            contract_addr,
+            msg,
+            funds,
+        } => {
+            let perm =
+                permissions
+                    .contract(contract_addr)
+                    .ok_or_else(|| ContractError::ContractPerm {
+                        contract: contract_addr.clone(),
+                    })?;
+            if let Some(execute_msgs) = &perm.execute_msgs {
+                let name = execute_msg_name(msg).unwrap_or_default();
+                ensure!(
+                    execute_msgs.contains(&name),
+                    ContractError::ExecuteMsgPerm { msg: name }
+                );
+            }
+            if !funds.is_empty() {
+                let limit = perm
+                    .funds_limit
+                    .clone()
+                    .ok_or(ContractError::FundsLimitExceeded {})?;
+                ensure!(
+                    NativeBalance(limit).sub(funds.clone()).is_ok(),
+                    ContractError::FundsLimitExceeded {}
+                );
+            }
+        }
         _ => return Err(ContractError::UnsupportedMessage {}),
     }
     Ok(())
@@ -195,6 +512,7 @@ where
         let mut allowance = allow
             .filter(|allow| !allow.expires.is_expired(&env.block))
             .unwrap_or_default();
+        allowance.refresh(&env.block);
 
         if let Some(exp) = expires {
             if exp.is_expired(&env.block) {
@@ -246,6 +564,7 @@ where
             let mut allowance = allow
                 .filter(|allow| !allow.expires.is_expired(&env.block))
                 .ok_or(ContractError::NoAllowance {})?;
+            allowance.refresh(&env.block);
 
             if let Some(exp) = expires {
                 if exp.is_expired(&env.block) {
@@ -259,7 +578,8 @@ where
             Ok(allowance)
         })?;
 
-    if allowance.balance.is_empty() {
+    // recurring allowances are kept, they will be restored at the next period
+    if allowance.balance.is_empty() && allowance.period.is_none() {
         ALLOWANCES.remove(deps.storage, &spender_addr);
     }
 
@@ -272,12 +592,182 @@ where
     Ok(res)
 }
 
+pub fn execute_set_periodic_allowance<T>(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    spender: String,
+    cap: Vec<Coin>,
+    period: Duration,
+) -> Result<Response<T>, ContractError>
+where
+    T: Clone + fmt::Debug + PartialEq + JsonSchema,
+{
+    let cfg = ADMIN_LIST.load(deps.storage)?;
+    ensure!(cfg.is_admin(&info.sender), ContractError::Unauthorized {});
+
+    let spender_addr = deps.api.addr_validate(&spender)?;
+    ensure_ne!(
+        info.sender,
+        spender_addr,
+        ContractError::CannotSetOwnAccount {}
+    );
+    ensure!(
+        !matches!(period, Duration::Height(0) | Duration::Time(0)),
+        ContractError::ZeroPeriod {}
+    );
+
+    let mut cap = NativeBalance(cap);
+    cap.normalize();
+
+    ALLOWANCES.update::<_, ContractError>(deps.storage, &spender_addr, |allow| {
+        let mut allowance = allow
+            .filter(|allow| !allow.expires.is_expired(&env.block))
+            .unwrap_or_default();
+
+        allowance.balance = cap.clone();
+        allowance.period = Some(Period {
+            duration: period,
+            cap: cap.clone(),
+            next_reset: period.after(&env.block),
+        });
+        Ok(allowance)
+    })?;
+
+    let res = Response::new()
+        .add_attribute("action", "set_periodic_allowance")
+        .add_attribute("owner", info.sender)
+        .add_attribute("spender", spender)
+        .add_attribute("cap", cap.to_string())
+        .add_attribute("period", period.to_string());
+    Ok(res)
+}
+
+pub fn execute_increase_cw20_allowance<T>(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    spender: String,
+    token: String,
+    amount: Uint128,
+    expires: Option<Expiration>,
+) -> Result<Response<T>, ContractError>
+where
+    T: Clone + fmt::Debug + PartialEq + JsonSchema,
+{
+    let cfg = ADMIN_LIST.load(deps.storage)?;
+    ensure!(cfg.is_admin(&info.sender), ContractError::Unauthorized {});
+
+    let spender_addr = deps.api.addr_validate(&spender)?;
+    ensure_ne!(
+        info.sender,
+        spender_addr,
+        ContractError::CannotSetOwnAccount {}
+    );
+    let token_addr = deps.api.addr_validate(&token)?;
+
+    CW20_ALLOWANCES.update::<_, ContractError>(
+        deps.storage,
+        (&spender_addr, &token_addr),
+        |allow| {
+            let prev_expires = allow
+                .as_ref()
+                .map(|allow| allow.expires)
+                .unwrap_or_default();
+
+            let mut allowance = allow
+                .filter(|allow| !allow.expires.is_expired(&env.block))
+                .unwrap_or_default();
+
+            if let Some(exp) = expires {
+                if exp.is_expired(&env.block) {
+                    return Err(ContractError::SettingExpiredAllowance(exp));
+                }
+
+                allowance.expires = exp;
+            } else if prev_expires.is_expired(&env.block) {
+                return Err(ContractError::SettingExpiredAllowance(prev_expires));
+            }
+
+            allowance.amount += amount;
+            Ok(allowance)
+        },
+    )?;
+
+    let res = Response::new()
+        .add_attribute("action", "increase_cw20_allowance")
+        .add_attribute("owner", info.sender)
+        .add_attribute("spender", spender)
+        .add_attribute("token", token_addr)
+        .add_attribute("amount", amount);
+    Ok(res)
+}
+
+pub fn execute_decrease_cw20_allowance<T>(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    spender: String,
+    token: String,
+    amount: Uint128,
+    expires: Option<Expiration>,
+) -> Result<Response<T>, ContractError>
+where
+    T: Clone + fmt::Debug + PartialEq + JsonSchema,
+{
+    let cfg = ADMIN_LIST.load(deps.storage)?;
+    ensure!(cfg.is_admin(&info.sender), ContractError::Unauthorized {});
+
+    let spender_addr = deps.api.addr_validate(&spender)?;
+    ensure_ne!(
+        info.sender,
+        spender_addr,
+        ContractError::CannotSetOwnAccount {}
+    );
+    let token_addr = deps.api.addr_validate(&token)?;
+
+    let allowance = CW20_ALLOWANCES.update::<_, ContractError>(
+        deps.storage,
+        (&spender_addr, &token_addr),
+        |allow| {
+            // Fail fast
+            let mut allowance = allow
+                .filter(|allow| !allow.expires.is_expired(&env.block))
+                .ok_or(ContractError::NoAllowance {})?;
+
+            if let Some(exp) = expires {
+                if exp.is_expired(&env.block) {
+                    return Err(ContractError::SettingExpiredAllowance(exp));
+                }
+
+                allowance.expires = exp;
+            }
+
+            // Tolerates underflows (amount bigger than allowance)
+            allowance.amount = allowance.amount.saturating_sub(amount);
+            Ok(allowance)
+        },
+    )?;
+
+    if allowance.amount.is_zero() {
+        CW20_ALLOWANCES.remove(deps.storage, (&spender_addr, &token_addr));
+    }
+
+    let res = Response::new()
+        .add_attribute("action", "decrease_cw20_allowance")
+        .add_attribute("owner", info.sender)
+        .add_attribute("spender", spender)
+        .add_attribute("token", token_addr)
+        .add_attribute("amount", amount);
+    Ok(res)
+}
+
 pub fn execute_set_permissions<T>(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
     spender: String,
-    perm: Permissions,
+    mut perm: Permissions,
 ) -> Result<Response<T>, ContractError>
 where
     T: Clone + fmt::Debug + PartialEq + JsonSchema,
@@ -291,6 +781,9 @@ where
         spender_addr,
         ContractError::CannotSetOwnAccount {}
     );
+    for contract in perm.contracts.iter_mut() {
+        contract.contract = deps.api.addr_validate(&contract.contract)?.into();
+    }
     PERMISSIONS.save(deps.storage, &spender_addr, &perm)?;
 
     let res = Response::new()
@@ -306,6 +799,17 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::AdminList {} => to_json_binary(&query_admin_list(deps)?),
         QueryMsg::Allowance { spender } => to_json_binary(&query_allowance(deps, env, spender)?),
+        QueryMsg::Cw20Allowances {
+            spender,
+            start_after,
+            limit,
+        } => to_json_binary(&query_cw20_allowances(
+            deps,
+            env,
+            spender,
+            start_after,
+            limit,
+        )?),
         QueryMsg::Permissions { spender } => to_json_binary(&query_permissions(deps, spender)?),
         QueryMsg::CanExecute { sender, msg } => {
             to_json_binary(&query_can_execute(deps, env, sender, msg)?)
@@ -316,6 +820,12 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::AllPermissions { start_after, limit } => {
             to_json_binary(&query_all_permissions(deps, start_after, limit)?)
         }
+        QueryMsg::AuditConfig {} => to_json_binary(&query_audit_config(deps)?),
+        QueryMsg::ExecutionHistory {
+            sender,
+            start_after,
+            limit,
+        } => to_json_binary(&query_execution_history(deps, sender, start_after, limit)?),
     }
 }
 
@@ -323,10 +833,11 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
 pub fn query_allowance(deps: Deps, env: Env, spender: String) -> StdResult<Allowance> {
     // we can use unchecked here as it is a query - bad value means a miss, we never write it
     let spender = deps.api.addr_validate(&spender)?;
-    let allow = ALLOWANCES
+    let mut allow = ALLOWANCES
         .may_load(deps.storage, &spender)?
         .filter(|allow| !allow.expires.is_expired(&env.block))
         .unwrap_or_default();
+    allow.refresh(&env.block);
 
     Ok(allow)
 }
@@ -340,6 +851,19 @@ pub fn query_permissions(deps: Deps, spender: String) -> StdResult<Permissions>
     Ok(permissions)
 }
 
+// returns true if the spender has a non expired allowance covering the amount
+fn allowance_covers(deps: Deps, env: &Env, spender: &Addr, amount: Vec<Coin>) -> StdResult<bool> {
+    let allowance = ALLOWANCES.may_load(deps.storage, spender)?;
+    match allowance {
+        // if there is an allowance, we subtract the requested amount to ensure it is covered (error on underflow)
+        Some(mut allow) => {
+            allow.refresh(&env.block);
+            Ok(!allow.expires.is_expired(&env.block) && allow.balance.sub(amount).is_ok())
+        }
+        None => Ok(false),
+    }
+}
+
 fn query_can_execute(
     deps: Deps,
     env: Env,
@@ -362,27 +886,98 @@ fn can_execute(deps: Deps, env: Env, sender: String, msg: CosmosMsg) -> StdResul
     match msg {
         CosmosMsg::Bank(BankMsg::Send { amount, .. }) => {
             // now we check if there is enough allowance for this message
-            let allowance = ALLOWANCES.may_load(deps.storage, &sender)?;
-            match allowance {
-                // if there is an allowance, we subtract the requested amount to ensure it is covered (error on underflow)
-                Some(allow) => {
-                    Ok(!allow.expires.is_expired(&env.block) && allow.balance.sub(amount).is_ok())
+            allowance_covers(deps, &env, &sender, amount)
+        }
+        CosmosMsg::Staking(staking_msg) => {
+            let perm_opt = PERMISSIONS.may_load(deps.storage, &sender)?;
+            match perm_opt {
+                Some(permission) => {
+                    if check_staking_permissions(&staking_msg, &permission).is_err() {
+                        return Ok(false);
+                    }
+                    let target = match &staking_msg {
+                        StakingMsg::Delegate { validator, amount } => Some((validator, amount)),
+                        StakingMsg::Redelegate {
+                            dst_validator,
+                            amount,
+                            ..
+                        } => Some((dst_validator, amount)),
+                        _ => None,
+                    };
+                    match target {
+                        Some((validator, amount)) => Ok(delegation_total(
+                            deps.storage,
+                            &sender,
+                            validator,
+                            amount,
+                            &permission,
+                        )
+                        .is_ok()),
+                        None => Ok(true),
+                    }
                 }
                 None => Ok(false),
             }
         }
-        CosmosMsg::Staking(staking_msg) => {
+        CosmosMsg::Distribution(distribution_msg) => {
             let perm_opt = PERMISSIONS.may_load(deps.storage, &sender)?;
             match perm_opt {
-                Some(permission) => Ok(check_staking_permissions(&staking_msg, permission).is_ok()),
+                Some(permission) => {
+                    if check_distribution_permissions(&distribution_msg, &permission).is_err() {
+                        return Ok(false);
+                    }
+                    #[cfg(feature = "stargate")]
+                    if let DistributionMsg::FundCommunityPool { amount } = distribution_msg {
+                        return allowance_covers(deps, &env, &sender, amount);
+                    }
+                    Ok(true)
+                }
                 None => Ok(false),
             }
         }
-        CosmosMsg::Distribution(distribution_msg) => {
+        #[cfg(feature = "stargate")]
+        CosmosMsg::Gov(gov_msg) => {
+            let perm_opt = PERMISSIONS.may_load(deps.storage, &sender)?;
+            match perm_opt {
+                Some(permission) => Ok(check_gov_permissions(&gov_msg, &permission).is_ok()),
+                None => Ok(false),
+            }
+        }
+        #[cfg(feature = "stargate")]
+        CosmosMsg::Ibc(ibc_msg) => {
+            let perm_opt = PERMISSIONS.may_load(deps.storage, &sender)?;
+            match perm_opt {
+                Some(permission) => {
+                    if check_ibc_permissions(&ibc_msg, &permission).is_err() {
+                        return Ok(false);
+                    }
+                    match ibc_msg {
+                        IbcMsg::Transfer { amount, .. } => {
+                            allowance_covers(deps, &env, &sender, vec![amount])
+                        }
+                        _ => Ok(true),
+                    }
+                }
+                None => Ok(false),
+            }
+        }
+        CosmosMsg::Wasm(wasm_msg) => {
+            if let Some((token, amount)) = covered_cw20_spend(deps, &sender, &wasm_msg)? {
+                let allow = CW20_ALLOWANCES.load(deps.storage, (&sender, &token))?;
+                return Ok(!allow.expires.is_expired(&env.block) && allow.amount >= amount);
+            }
             let perm_opt = PERMISSIONS.may_load(deps.storage, &sender)?;
             match perm_opt {
                 Some(permission) => {
-                    Ok(check_distribution_permissions(&distribution_msg, permission).is_ok())
+                    if check_wasm_permissions(&wasm_msg, &permission).is_err() {
+                        return Ok(false);
+                    }
+                    match wasm_msg {
+                        WasmMsg::Execute { funds, .. } if !funds.is_empty() => {
+                            allowance_covers(deps, &env, &sender, funds)
+                        }
+                        _ => Ok(true),
+                    }
                 }
                 None => Ok(false),
             }
@@ -420,26 +1015,64 @@ pub fn query_all_allowances(
         })
         .take(limit)
         .map(|item| {
-            item.map(|(addr, allow)| AllowanceInfo {
-                spender: addr.into(),
-                balance: allow.balance,
-                expires: allow.expires,
+            item.map(|(addr, mut allow)| {
+                allow.refresh(&env.block);
+                AllowanceInfo {
+                    spender: addr.into(),
+                    balance: allow.balance,
+                    expires: allow.expires,
+                    period: allow.period,
+                }
             })
         })
         .collect::<StdResult<Vec<_>>>()?;
     Ok(AllAllowancesResponse { allowances })
 }
 
-// return a list of all permissions here
-pub fn query_all_permissions(
+// return the non expired cw20 allowances of the spender
+pub fn query_cw20_allowances(
     deps: Deps,
+    env: Env,
+    spender: String,
     start_after: Option<String>,
     limit: Option<u32>,
-) -> StdResult<AllPermissionsResponse> {
+) -> StdResult<Cw20AllowancesResponse> {
+    let spender = deps.api.addr_validate(&spender)?;
     let limit = calc_limit(limit);
     let start = start_after.map(|s| Bound::ExclusiveRaw(s.into()));
 
-    let permissions = PERMISSIONS
+    let allowances = CW20_ALLOWANCES
+        .prefix(&spender)
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| {
+            if let Ok((_, allow)) = item {
+                !allow.expires.is_expired(&env.block)
+            } else {
+                true
+            }
+        })
+        .take(limit)
+        .map(|item| {
+            item.map(|(token, allow)| Cw20AllowanceInfo {
+                token: token.into(),
+                amount: allow.amount,
+                expires: allow.expires,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(Cw20AllowancesResponse { allowances })
+}
+
+// return a list of all permissions here
+pub fn query_all_permissions(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AllPermissionsResponse> {
+    let limit = calc_limit(limit);
+    let start = start_after.map(|s| Bound::ExclusiveRaw(s.into()));
+
+    let permissions = PERMISSIONS
         .range(deps.storage, start, None, Order::Ascending)
         .take(limit)
         .map(|item| {
@@ -506,12 +1139,24 @@ mod tests {
         redelegate: true,
         undelegate: true,
         withdraw: true,
+        validators: None,
+        max_delegation: None,
+        vote: true,
+        fund_community_pool: true,
+        ibc_channels: Vec::new(),
+        contracts: Vec::new(),
     };
     const NO_PERMS: Permissions = Permissions {
         delegate: false,
         redelegate: false,
         undelegate: false,
         withdraw: false,
+        validators: None,
+        max_delegation: None,
+        vote: false,
+        fund_community_pool: false,
+        ibc_channels: Vec::new(),
+        contracts: Vec::new(),
     };
 
     // Expiration constant working properly with default `mock_env`
@@ -700,6 +1345,7 @@ mod tests {
                 Allowance {
                     balance: NativeBalance(vec![coin(1, TOKEN)]),
                     expires: Expiration::Never {},
+                    period: None,
                 }
             );
             let allowance =
@@ -709,6 +1355,7 @@ mod tests {
                 Allowance {
                     balance: NativeBalance(vec![coin(2, TOKEN)]),
                     expires: Expiration::Never {},
+                    period: None,
                 }
             );
 
@@ -733,6 +1380,7 @@ mod tests {
                 Allowance {
                     balance: NativeBalance(vec![]),
                     expires: Expiration::Never {},
+                    period: None,
                 }
             );
         }
@@ -784,16 +1432,19 @@ mod tests {
                     spender: SPENDER1.to_owned(),
                     balance: NativeBalance(vec![s1_allow]),
                     expires: Expiration::Never {}, // Not set, expected default
+                    period: None,
                 },
                 AllowanceInfo {
                     spender: SPENDER2.to_owned(),
                     balance: NativeBalance(vec![s2_allow]),
                     expires: s2_expire,
+                    period: None,
                 },
                 AllowanceInfo {
                     spender: SPENDER3.to_owned(),
                     balance: NativeBalance(vec![s3_allow]),
                     expires: s3_expire,
+                    period: None,
                 },
             ];
 
@@ -1048,6 +1699,7 @@ mod tests {
                         spender: SPENDER1.to_owned(),
                         balance: NativeBalance(vec![coin(4, TOKEN1)]),
                         expires: Expiration::Never {},
+                        period: None,
                     }]
                 }
                 .canonical()
@@ -1090,6 +1742,7 @@ mod tests {
                         spender: SPENDER1.to_owned(),
                         balance: NativeBalance(vec![coin(4, TOKEN1)]),
                         expires: NON_EXPIRED_HEIGHT,
+                        period: None,
                     }]
                 }
                 .canonical()
@@ -1129,6 +1782,7 @@ mod tests {
                         spender: SPENDER1.to_owned(),
                         balance: NativeBalance(vec![coin(1, TOKEN1), coin(3, TOKEN2)]),
                         expires: Expiration::Never {},
+                        period: None,
                     }]
                 }
                 .canonical()
@@ -1169,11 +1823,13 @@ mod tests {
                             spender: SPENDER1.to_owned(),
                             balance: NativeBalance(vec![coin(1, TOKEN1)]),
                             expires: Expiration::Never {},
+                            period: None,
                         },
                         AllowanceInfo {
                             spender: SPENDER2.to_owned(),
                             balance: NativeBalance(vec![coin(3, TOKEN1)]),
                             expires: Expiration::Never {},
+                            period: None,
                         }
                     ]
                 }
@@ -1215,11 +1871,13 @@ mod tests {
                             spender: SPENDER1.to_owned(),
                             balance: NativeBalance(vec![coin(1, TOKEN1)]),
                             expires: Expiration::Never {},
+                            period: None,
                         },
                         AllowanceInfo {
                             spender: SPENDER2.to_owned(),
                             balance: NativeBalance(vec![coin(3, TOKEN1)]),
                             expires: NON_EXPIRED_HEIGHT,
+                            period: None,
                         }
                     ]
                 }
@@ -1261,6 +1919,7 @@ mod tests {
                         spender: SPENDER1.to_owned(),
                         balance: NativeBalance(vec![coin(2, TOKEN2)]),
                         expires: NON_EXPIRED_TIME,
+                        period: None,
                     }]
                 }
                 .canonical(),
@@ -1300,6 +1959,7 @@ mod tests {
                         spender: SPENDER1.to_owned(),
                         balance: NativeBalance(vec![coin(1, TOKEN1)]),
                         expires: NON_EXPIRED_HEIGHT,
+                        period: None,
                     }]
                 }
                 .canonical(),
@@ -1376,6 +2036,7 @@ mod tests {
                         spender: SPENDER1.to_owned(),
                         balance: NativeBalance(vec![coin(6, TOKEN1)]),
                         expires: NON_EXPIRED_HEIGHT,
+                        period: None,
                     }]
                 }
                 .canonical()
@@ -1417,6 +2078,7 @@ mod tests {
                         spender: SPENDER1.to_owned(),
                         balance: NativeBalance(vec![coin(20, TOKEN2)]),
                         expires: NON_EXPIRED_HEIGHT,
+                        period: None,
                     }]
                 }
                 .canonical()
@@ -1458,6 +2120,7 @@ mod tests {
                         spender: SPENDER1.to_owned(),
                         balance: NativeBalance(vec![coin(20, TOKEN2)]),
                         expires: NON_EXPIRED_HEIGHT,
+                        period: None,
                     }]
                 }
                 .canonical()
@@ -1531,6 +2194,7 @@ mod tests {
                         spender: SPENDER1.to_owned(),
                         balance: NativeBalance(vec![coin(6, TOKEN1)]),
                         expires: NON_EXPIRED_TIME,
+                        period: None,
                     }]
                 }
                 .canonical()
@@ -1567,6 +2231,7 @@ mod tests {
                         spender: SPENDER1.to_owned(),
                         balance: NativeBalance(vec![coin(10, TOKEN1)]),
                         expires: NON_EXPIRED_HEIGHT,
+                        period: None,
                     }]
                 }
                 .canonical()
@@ -1602,6 +2267,7 @@ mod tests {
                         spender: SPENDER1.to_owned(),
                         balance: NativeBalance(vec![coin(10, TOKEN1)]),
                         expires: Expiration::Never {},
+                        period: None,
                     }]
                 }
                 .canonical()
@@ -1667,6 +2333,7 @@ mod tests {
                         spender: SPENDER1.to_owned(),
                         balance: NativeBalance(vec![coin(3, TOKEN1)]),
                         expires: NON_EXPIRED_HEIGHT,
+                        period: None,
                     }]
                 }
                 .canonical(),
@@ -1715,6 +2382,7 @@ mod tests {
                         spender: SPENDER1.to_owned(),
                         balance: NativeBalance(vec![coin(4, TOKEN1)]),
                         expires: Expiration::Never {},
+                        period: None,
                     }]
                 }
                 .canonical()
@@ -1779,7 +2447,8 @@ mod tests {
                     allowances: vec![AllowanceInfo {
                         spender: SPENDER1.to_owned(),
                         balance: NativeBalance(coins(10, TOKEN1)),
-                        expires: Expiration::Never {}
+                        expires: Expiration::Never {},
+                        period: None,
                     }]
                 }
                 .canonical()
@@ -2062,6 +2731,934 @@ mod tests {
                 .unwrap_err();
             }
         }
+
+        #[test]
+        fn validator_allowlist() {
+            let perm = Permissions {
+                validators: Some(vec!["validator1".to_owned(), "validator2".to_owned()]),
+                max_delegation: Some(Uint128::new(20)),
+                ..ALL_PERMS
+            };
+            let Suite { mut deps, .. } = SuiteConfig::new().with_permissions(SPENDER1, perm).init();
+
+            let allowed: Vec<CosmosMsg> = vec![
+                StakingMsg::Delegate {
+                    validator: "validator1".to_owned(),
+                    amount: coin(20, TOKEN1),
+                }
+                .into(),
+                StakingMsg::Redelegate {
+                    src_validator: "validator1".to_owned(),
+                    dst_validator: "validator2".to_owned(),
+                    amount: coin(15, TOKEN1),
+                }
+                .into(),
+                StakingMsg::Undelegate {
+                    validator: "validator2".to_owned(),
+                    amount: coin(50, TOKEN1),
+                }
+                .into(),
+            ];
+            for msg in allowed {
+                let resp =
+                    query_can_execute(deps.as_ref(), mock_env(), SPENDER1.to_owned(), msg.clone())
+                        .unwrap();
+                assert_eq!(resp, CanExecuteResponse { can_execute: true });
+
+                execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info(SPENDER1, &[]),
+                    ExecuteMsg::Execute { msgs: vec![msg] },
+                )
+                .unwrap();
+            }
+
+            let validator3 = || ContractError::ValidatorPerm {
+                validator: "validator3".to_owned(),
+            };
+            let rejected: Vec<(CosmosMsg, _)> = vec![
+                (
+                    StakingMsg::Delegate {
+                        validator: "validator3".to_owned(),
+                        amount: coin(10, TOKEN1),
+                    }
+                    .into(),
+                    validator3(),
+                ),
+                (
+                    StakingMsg::Delegate {
+                        validator: "validator1".to_owned(),
+                        amount: coin(21, TOKEN1),
+                    }
+                    .into(),
+                    ContractError::DelegationLimitExceeded {},
+                ),
+                (
+                    StakingMsg::Redelegate {
+                        src_validator: "validator3".to_owned(),
+                        dst_validator: "validator1".to_owned(),
+                        amount: coin(10, TOKEN1),
+                    }
+                    .into(),
+                    validator3(),
+                ),
+                (
+                    StakingMsg::Redelegate {
+                        src_validator: "validator1".to_owned(),
+                        dst_validator: "validator3".to_owned(),
+                        amount: coin(10, TOKEN1),
+                    }
+                    .into(),
+                    validator3(),
+                ),
+                (
+                    StakingMsg::Undelegate {
+                        validator: "validator3".to_owned(),
+                        amount: coin(10, TOKEN1),
+                    }
+                    .into(),
+                    validator3(),
+                ),
+            ];
+            for (msg, expected) in rejected {
+                let resp =
+                    query_can_execute(deps.as_ref(), mock_env(), SPENDER1.to_owned(), msg.clone())
+                        .unwrap();
+                assert_eq!(resp, CanExecuteResponse { can_execute: false });
+
+                let err = execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info(SPENDER1, &[]),
+                    ExecuteMsg::Execute { msgs: vec![msg] },
+                )
+                .unwrap_err();
+                assert_eq!(err, expected);
+            }
+
+            // the limit applies to the total of each denom
+            let delegate = |validator: &str, amount| -> CosmosMsg {
+                StakingMsg::Delegate {
+                    validator: validator.to_owned(),
+                    amount,
+                }
+                .into()
+            };
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER1, &[]),
+                ExecuteMsg::Execute {
+                    msgs: vec![
+                        delegate("validator1", coin(15, TOKEN1)),
+                        delegate("validator1", coin(15, TOKEN2)),
+                    ],
+                },
+            )
+            .unwrap();
+
+            // which holds across executions until delegations are moved away
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER1, &[]),
+                ExecuteMsg::Execute {
+                    msgs: vec![delegate("validator1", coin(1, TOKEN1))],
+                },
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::DelegationLimitExceeded {});
+            let resp = query_can_execute(
+                deps.as_ref(),
+                mock_env(),
+                SPENDER1.to_owned(),
+                delegate("validator1", coin(1, TOKEN1)),
+            )
+            .unwrap();
+            assert_eq!(resp, CanExecuteResponse { can_execute: false });
+            let undelegate: CosmosMsg = StakingMsg::Undelegate {
+                validator: "validator1".to_owned(),
+                amount: coin(10, TOKEN1),
+            }
+            .into();
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER1, &[]),
+                ExecuteMsg::Execute {
+                    msgs: vec![undelegate, delegate("validator1", coin(10, TOKEN1))],
+                },
+            )
+            .unwrap();
+
+            // and to the messages of a single execution
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER1, &[]),
+                ExecuteMsg::Execute {
+                    msgs: vec![
+                        delegate("validator2", coin(15, TOKEN1)),
+                        delegate("validator2", coin(15, TOKEN1)),
+                    ],
+                },
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::DelegationLimitExceeded {});
+        }
+    }
+
+    mod wasm_permission {
+        use super::*;
+
+        use crate::state::ContractPermission;
+
+        const CONTRACT: &str = addr!("contract");
+        const OTHER_CONTRACT: &str = addr!("other_contract");
+
+        fn contract_perms() -> Permissions {
+            Permissions {
+                contracts: vec![ContractPermission {
+                    contract: CONTRACT.to_owned(),
+                    execute_msgs: Some(vec!["claim".to_owned(), "stake".to_owned()]),
+                    funds_limit: Some(coins(5, TOKEN1)),
+                }],
+                ..NO_PERMS
+            }
+        }
+
+        fn wasm_execute(contract: &str, msg: &str, funds: Vec<Coin>) -> CosmosMsg {
+            WasmMsg::Execute {
+                contract_addr: contract.to_owned(),
+                msg: Binary::from(msg.as_bytes()),
+                funds,
+            }
+            .into()
+        }
+
+        #[test]
+        fn allowed() {
+            let Suite { mut deps, .. } = SuiteConfig::new()
+                .with_permissions(SPENDER1, contract_perms())
+                .with_allowance(SPENDER1, coin(10, TOKEN1))
+                .init();
+
+            let msgs = vec![
+                wasm_execute(CONTRACT, r#"{"claim":{}}"#, vec![]),
+                wasm_execute(CONTRACT, r#"{"stake":{}}"#, coins(4, TOKEN1)),
+            ];
+            for msg in &msgs {
+                let resp =
+                    query_can_execute(deps.as_ref(), mock_env(), SPENDER1.to_owned(), msg.clone())
+                        .unwrap();
+                assert_eq!(resp, CanExecuteResponse { can_execute: true });
+            }
+
+            let rsp = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER1, &[]),
+                ExecuteMsg::Execute { msgs: msgs.clone() },
+            )
+            .unwrap();
+            assert_eq!(
+                rsp.messages,
+                msgs.into_iter().map(SubMsg::new).collect::<Vec<_>>()
+            );
+
+            // attached funds are charged against the allowance
+            let allowance =
+                query_allowance(deps.as_ref(), mock_env(), SPENDER1.to_owned()).unwrap();
+            assert_eq!(allowance.balance, NativeBalance(vec![coin(6, TOKEN1)]));
+        }
+
+        #[test]
+        fn reject() {
+            let Suite { mut deps, .. } = SuiteConfig::new()
+                .with_permissions(SPENDER1, contract_perms())
+                .with_allowance(SPENDER1, coin(10, TOKEN1))
+                .with_allowance(SPENDER2, coin(10, TOKEN1))
+                .init();
+
+            let cases = vec![
+                (
+                    SPENDER1,
+                    wasm_execute(OTHER_CONTRACT, r#"{"claim":{}}"#, vec![]),
+                    ContractError::ContractPerm {
+                        contract: OTHER_CONTRACT.to_owned(),
+                    },
+                ),
+                (
+                    SPENDER1,
+                    wasm_execute(CONTRACT, r#"{"withdraw":{}}"#, vec![]),
+                    ContractError::ExecuteMsgPerm {
+                        msg: "withdraw".to_owned(),
+                    },
+                ),
+                (
+                    SPENDER1,
+                    wasm_execute(CONTRACT, r#"{"stake":{}}"#, coins(6, TOKEN1)),
+                    ContractError::FundsLimitExceeded {},
+                ),
+                (
+                    SPENDER2,
+                    wasm_execute(CONTRACT, r#"{"claim":{}}"#, vec![]),
+                    ContractError::NotAllowed {},
+                ),
+            ];
+
+            for (spender, msg, expected) in cases {
+                let resp =
+                    query_can_execute(deps.as_ref(), mock_env(), spender.to_owned(), msg.clone())
+                        .unwrap();
+                assert_eq!(resp, CanExecuteResponse { can_execute: false });
+
+                let err = execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info(spender, &[]),
+                    ExecuteMsg::Execute { msgs: vec![msg] },
+                )
+                .unwrap_err();
+                assert_eq!(err, expected);
+            }
+        }
+
+        #[test]
+        fn funds_need_allowance() {
+            let Suite { mut deps, .. } = SuiteConfig::new()
+                .with_permissions(SPENDER1, contract_perms())
+                .with_allowance(SPENDER1, coin(3, TOKEN1))
+                .init();
+
+            let msg = wasm_execute(CONTRACT, r#"{"stake":{}}"#, coins(4, TOKEN1));
+            let resp =
+                query_can_execute(deps.as_ref(), mock_env(), SPENDER1.to_owned(), msg.clone())
+                    .unwrap();
+            assert_eq!(resp, CanExecuteResponse { can_execute: false });
+
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER1, &[]),
+                ExecuteMsg::Execute { msgs: vec![msg] },
+            )
+            .unwrap_err();
+        }
+    }
+
+    #[cfg(feature = "stargate")]
+    mod gov_ibc_permission {
+        use super::*;
+
+        use cosmwasm_std::{Decimal, IbcTimeout, Timestamp, VoteOption, WeightedVoteOption};
+
+        const CHANNEL: &str = "channel-0";
+
+        fn gov_ibc_perms() -> Permissions {
+            Permissions {
+                vote: true,
+                fund_community_pool: true,
+                ibc_channels: vec![CHANNEL.to_owned()],
+                ..NO_PERMS
+            }
+        }
+
+        fn ibc_transfer(channel: &str, amount: Coin) -> CosmosMsg {
+            IbcMsg::Transfer {
+                channel_id: channel.to_owned(),
+                to_address: "receiver".to_owned(),
+                amount,
+                timeout: IbcTimeout::with_timestamp(Timestamp::from_seconds(2_000_000_000)),
+                memo: None,
+            }
+            .into()
+        }
+
+        #[test]
+        fn allowed() {
+            let Suite { mut deps, .. } = SuiteConfig::new()
+                .with_permissions(SPENDER1, gov_ibc_perms())
+                .with_allowance(SPENDER1, coin(10, TOKEN1))
+                .init();
+
+            let msgs = vec![
+                GovMsg::Vote {
+                    proposal_id: 1,
+                    option: VoteOption::Yes,
+                }
+                .into(),
+                GovMsg::VoteWeighted {
+                    proposal_id: 2,
+                    options: vec![WeightedVoteOption {
+                        option: VoteOption::No,
+                        weight: Decimal::one(),
+                    }],
+                }
+                .into(),
+                DistributionMsg::FundCommunityPool {
+                    amount: coins(3, TOKEN1),
+                }
+                .into(),
+                ibc_transfer(CHANNEL, coin(4, TOKEN1)),
+            ];
+            for msg in &msgs {
+                let resp =
+                    query_can_execute(deps.as_ref(), mock_env(), SPENDER1.to_owned(), msg.clone())
+                        .unwrap();
+                assert_eq!(resp, CanExecuteResponse { can_execute: true });
+            }
+
+            let rsp = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER1, &[]),
+                ExecuteMsg::Execute { msgs: msgs.clone() },
+            )
+            .unwrap();
+            assert_eq!(
+                rsp.messages,
+                msgs.into_iter().map(SubMsg::new).collect::<Vec<_>>()
+            );
+
+            // funded and transferred amounts are charged against the allowance
+            let allowance =
+                query_allowance(deps.as_ref(), mock_env(), SPENDER1.to_owned()).unwrap();
+            assert_eq!(allowance.balance, NativeBalance(vec![coin(3, TOKEN1)]));
+        }
+
+        #[test]
+        fn reject() {
+            let Suite { mut deps, .. } = SuiteConfig::new()
+                .with_permissions(SPENDER1, gov_ibc_perms())
+                .with_permissions(SPENDER2, NO_PERMS)
+                .with_allowance(SPENDER1, coin(10, TOKEN1))
+                .with_allowance(SPENDER2, coin(10, TOKEN1))
+                .init();
+
+            let vote: CosmosMsg = GovMsg::Vote {
+                proposal_id: 1,
+                option: VoteOption::Yes,
+            }
+            .into();
+            let fund: CosmosMsg = DistributionMsg::FundCommunityPool {
+                amount: coins(3, TOKEN1),
+            }
+            .into();
+            let cases = vec![
+                (
+                    SPENDER1,
+                    ibc_transfer("channel-1", coin(1, TOKEN1)),
+                    ContractError::IbcChannelPerm {
+                        channel: "channel-1".to_owned(),
+                    },
+                ),
+                (SPENDER2, vote, ContractError::VotePerm {}),
+                (SPENDER2, fund, ContractError::FundCommunityPoolPerm {}),
+                (
+                    SPENDER2,
+                    ibc_transfer(CHANNEL, coin(1, TOKEN1)),
+                    ContractError::IbcChannelPerm {
+                        channel: CHANNEL.to_owned(),
+                    },
+                ),
+                (
+                    SPENDER3,
+                    ibc_transfer(CHANNEL, coin(1, TOKEN1)),
+                    ContractError::NotAllowed {},
+                ),
+            ];
+
+            for (spender, msg, expected) in cases {
+                let resp =
+                    query_can_execute(deps.as_ref(), mock_env(), spender.to_owned(), msg.clone())
+                        .unwrap();
+                assert_eq!(resp, CanExecuteResponse { can_execute: false });
+
+                let err = execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info(spender, &[]),
+                    ExecuteMsg::Execute { msgs: vec![msg] },
+                )
+                .unwrap_err();
+                assert_eq!(err, expected);
+            }
+        }
+
+        #[test]
+        fn amounts_need_allowance() {
+            let Suite { mut deps, .. } = SuiteConfig::new()
+                .with_permissions(SPENDER1, gov_ibc_perms())
+                .with_allowance(SPENDER1, coin(3, TOKEN1))
+                .init();
+
+            let msgs = vec![
+                ibc_transfer(CHANNEL, coin(4, TOKEN1)),
+                DistributionMsg::FundCommunityPool {
+                    amount: coins(4, TOKEN1),
+                }
+                .into(),
+            ];
+            for msg in msgs {
+                let resp =
+                    query_can_execute(deps.as_ref(), mock_env(), SPENDER1.to_owned(), msg.clone())
+                        .unwrap();
+                assert_eq!(resp, CanExecuteResponse { can_execute: false });
+
+                execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info(SPENDER1, &[]),
+                    ExecuteMsg::Execute { msgs: vec![msg] },
+                )
+                .unwrap_err();
+            }
+        }
+    }
+
+    mod audit_log {
+        use super::*;
+
+        use cw1_whitelist::audit::{AuditConfig, ExecutionRecord};
+        use cw1_whitelist::policy::MsgType;
+
+        #[test]
+        fn records_admin_and_subkey_executions() {
+            let Suite { mut deps, owner } = SuiteConfig::new()
+                .with_allowance(SPENDER1, coin(10, TOKEN1))
+                .init();
+
+            let msg = ExecuteMsg::SetAuditConfig {
+                config: Some(AuditConfig { retention: 0 }),
+            };
+            let err = execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap_err();
+            assert_eq!(err, ContractError::ZeroRetention {});
+
+            let msg = ExecuteMsg::SetAuditConfig {
+                config: Some(AuditConfig { retention: 10 }),
+            };
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER1, &[]),
+                msg.clone(),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+            execute(deps.as_mut(), mock_env(), owner.clone(), msg).unwrap();
+
+            let send: CosmosMsg = BankMsg::Send {
+                to_address: SPENDER2.to_owned(),
+                amount: coins(4, TOKEN1),
+            }
+            .into();
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER1, &[]),
+                ExecuteMsg::Execute {
+                    msgs: vec![send.clone()],
+                },
+            )
+            .unwrap();
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                owner.clone(),
+                ExecuteMsg::Execute {
+                    msgs: vec![CosmosMsg::Custom(Empty {}), send],
+                },
+            )
+            .unwrap();
+
+            // rejected executions are not recorded
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER2, &[]),
+                ExecuteMsg::Execute {
+                    msgs: vec![CosmosMsg::Custom(Empty {})],
+                },
+            )
+            .unwrap_err();
+
+            let history: cw1_whitelist::msg::ExecutionHistoryResponse = from_json(
+                query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::ExecutionHistory {
+                        sender: Some(SPENDER1.to_owned()),
+                        start_after: None,
+                        limit: None,
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap();
+            assert_eq!(
+                history.executions,
+                vec![ExecutionRecord {
+                    id: 1,
+                    sender: Addr::unchecked(SPENDER1),
+                    height: mock_env().block.height,
+                    msg_types: vec![MsgType::Bank],
+                    spent: coins(4, TOKEN1),
+                }]
+            );
+
+            let history: cw1_whitelist::msg::ExecutionHistoryResponse = from_json(
+                query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::ExecutionHistory {
+                        sender: None,
+                        start_after: Some(1),
+                        limit: None,
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap();
+            assert_eq!(history.executions.len(), 1);
+            assert_eq!(history.executions[0].sender, owner.sender);
+            assert_eq!(
+                history.executions[0].msg_types,
+                vec![MsgType::Custom, MsgType::Bank]
+            );
+        }
+    }
+
+    mod periodic_allowance {
+        use super::*;
+
+        fn send(amount: u128) -> ExecuteMsg {
+            ExecuteMsg::Execute {
+                msgs: vec![BankMsg::Send {
+                    to_address: SPENDER2.to_owned(),
+                    amount: coins(amount, TOKEN1),
+                }
+                .into()],
+            }
+        }
+
+        #[test]
+        fn resets_every_period() {
+            let Suite {
+                mut deps, owner, ..
+            } = Suite::init();
+            let env = mock_env();
+            let start = env.block.height;
+
+            let msg = ExecuteMsg::SetPeriodicAllowance {
+                spender: SPENDER1.to_owned(),
+                cap: coins(10, TOKEN1),
+                period: Duration::Height(100),
+            };
+            execute(deps.as_mut(), env.clone(), owner, msg).unwrap();
+
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(SPENDER1, &[]),
+                send(6),
+            )
+            .unwrap();
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(SPENDER1, &[]),
+                send(5),
+            )
+            .unwrap_err();
+
+            let period = Period {
+                duration: Duration::Height(100),
+                cap: NativeBalance(coins(10, TOKEN1)),
+                next_reset: Expiration::AtHeight(start + 100),
+            };
+            assert_eq!(
+                query_all_allowances(deps.as_ref(), env.clone(), None, None)
+                    .unwrap()
+                    .canonical(),
+                AllAllowancesResponse {
+                    allowances: vec![AllowanceInfo {
+                        spender: SPENDER1.to_owned(),
+                        balance: NativeBalance(coins(4, TOKEN1)),
+                        expires: Expiration::Never {},
+                        period: Some(period.clone()),
+                    }]
+                }
+                .canonical()
+            );
+
+            // a few periods later the cap is available again, and resets stay aligned
+            let mut env = env;
+            env.block.height = start + 350;
+            let resp = query_can_execute(
+                deps.as_ref(),
+                env.clone(),
+                SPENDER1.to_owned(),
+                BankMsg::Send {
+                    to_address: SPENDER2.to_owned(),
+                    amount: coins(10, TOKEN1),
+                }
+                .into(),
+            )
+            .unwrap();
+            assert_eq!(resp, CanExecuteResponse { can_execute: true });
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(SPENDER1, &[]),
+                send(10),
+            )
+            .unwrap();
+
+            let allowance =
+                query_allowance(deps.as_ref(), env.clone(), SPENDER1.to_owned()).unwrap();
+            assert_eq!(allowance.balance, NativeBalance(vec![]));
+            assert_eq!(
+                allowance.period,
+                Some(Period {
+                    next_reset: Expiration::AtHeight(start + 400),
+                    ..period
+                })
+            );
+
+            // emptied periodic allowances are kept
+            execute(deps.as_mut(), env, mock_info(SPENDER1, &[]), send(1)).unwrap_err();
+        }
+
+        #[test]
+        fn zero_period() {
+            let Suite {
+                mut deps, owner, ..
+            } = Suite::init();
+
+            let msg = ExecuteMsg::SetPeriodicAllowance {
+                spender: SPENDER1.to_owned(),
+                cap: coins(10, TOKEN1),
+                period: Duration::Time(0),
+            };
+            let err = execute(deps.as_mut(), mock_env(), owner, msg).unwrap_err();
+            assert_eq!(err, ContractError::ZeroPeriod {});
+        }
+    }
+
+    mod cw20_allowance {
+        use super::*;
+        use cw20::{Cw20Coin, Cw20Send};
+
+        const CW20: &str = addr!("cw20");
+
+        fn cw20_msg(msg: &Cw20ExecuteMsg) -> CosmosMsg {
+            WasmMsg::Execute {
+                contract_addr: CW20.to_owned(),
+                msg: to_json_binary(msg).unwrap(),
+                funds: vec![],
+            }
+            .into()
+        }
+
+        fn increase(deps: DepsMut, owner: MessageInfo, amount: u128) {
+            let msg = ExecuteMsg::IncreaseCw20Allowance {
+                spender: SPENDER1.to_owned(),
+                token: CW20.to_owned(),
+                amount: Uint128::new(amount),
+                expires: None,
+            };
+            execute(deps, mock_env(), owner, msg).unwrap();
+        }
+
+        fn query_amount(deps: Deps) -> Vec<Cw20AllowanceInfo> {
+            query_cw20_allowances(deps, mock_env(), SPENDER1.to_owned(), None, None)
+                .unwrap()
+                .allowances
+        }
+
+        #[test]
+        fn transfer_and_send_are_charged() {
+            let Suite {
+                mut deps, owner, ..
+            } = Suite::init();
+            increase(deps.as_mut(), owner, 100);
+            assert_eq!(
+                query_amount(deps.as_ref()),
+                vec![Cw20AllowanceInfo {
+                    token: CW20.to_owned(),
+                    amount: Uint128::new(100),
+                    expires: Expiration::Never {},
+                }]
+            );
+
+            let msgs = vec![
+                cw20_msg(&Cw20ExecuteMsg::Transfer {
+                    recipient: SPENDER2.to_owned(),
+                    amount: Uint128::new(30),
+                }),
+                cw20_msg(&Cw20ExecuteMsg::Send {
+                    contract: SPENDER2.to_owned(),
+                    amount: Uint128::new(50),
+                    msg: Binary::default(),
+                }),
+            ];
+            for msg in &msgs {
+                let resp =
+                    query_can_execute(deps.as_ref(), mock_env(), SPENDER1.to_owned(), msg.clone())
+                        .unwrap();
+                assert_eq!(resp, CanExecuteResponse { can_execute: true });
+            }
+
+            let rsp = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER1, &[]),
+                ExecuteMsg::Execute { msgs: msgs.clone() },
+            )
+            .unwrap();
+            assert_eq!(
+                rsp.messages,
+                msgs.into_iter().map(SubMsg::new).collect::<Vec<_>>()
+            );
+            assert_eq!(query_amount(deps.as_ref())[0].amount, Uint128::new(20));
+
+            // cannot spend more than what is left
+            let msg = cw20_msg(&Cw20ExecuteMsg::Transfer {
+                recipient: SPENDER2.to_owned(),
+                amount: Uint128::new(21),
+            });
+            let resp =
+                query_can_execute(deps.as_ref(), mock_env(), SPENDER1.to_owned(), msg.clone())
+                    .unwrap();
+            assert_eq!(resp, CanExecuteResponse { can_execute: false });
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER1, &[]),
+                ExecuteMsg::Execute { msgs: vec![msg] },
+            )
+            .unwrap_err();
+        }
+
+        #[test]
+        fn batches_are_charged() {
+            let Suite {
+                mut deps, owner, ..
+            } = Suite::init();
+            increase(deps.as_mut(), owner, 100);
+
+            let batch_transfer = cw20_msg(&Cw20ExecuteMsg::BatchTransfer {
+                transfers: vec![
+                    Cw20Coin {
+                        address: SPENDER2.to_owned(),
+                        amount: Uint128::new(20),
+                    },
+                    Cw20Coin {
+                        address: SPENDER3.to_owned(),
+                        amount: Uint128::new(30),
+                    },
+                ],
+            });
+            let batch_send = cw20_msg(&Cw20ExecuteMsg::BatchSend {
+                sends: vec![
+                    Cw20Send {
+                        contract: SPENDER2.to_owned(),
+                        amount: Uint128::new(15),
+                        msg: Binary::default(),
+                    },
+                    Cw20Send {
+                        contract: SPENDER3.to_owned(),
+                        amount: Uint128::new(25),
+                        msg: Binary::default(),
+                    },
+                ],
+            });
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER1, &[]),
+                ExecuteMsg::Execute {
+                    msgs: vec![batch_transfer.clone(), batch_send],
+                },
+            )
+            .unwrap();
+            assert_eq!(query_amount(deps.as_ref())[0].amount, Uint128::new(10));
+
+            // the whole batch counts against what is left
+            let resp = query_can_execute(
+                deps.as_ref(),
+                mock_env(),
+                SPENDER1.to_owned(),
+                batch_transfer.clone(),
+            )
+            .unwrap();
+            assert_eq!(resp, CanExecuteResponse { can_execute: false });
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER1, &[]),
+                ExecuteMsg::Execute {
+                    msgs: vec![batch_transfer],
+                },
+            )
+            .unwrap_err();
+        }
+
+        #[test]
+        fn only_transfers_are_covered() {
+            let Suite {
+                mut deps, owner, ..
+            } = Suite::init();
+            increase(deps.as_mut(), owner, 100);
+
+            // burning is not a transfer, so contract permissions are needed
+            let msg = cw20_msg(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::new(10),
+            });
+            let resp =
+                query_can_execute(deps.as_ref(), mock_env(), SPENDER1.to_owned(), msg.clone())
+                    .unwrap();
+            assert_eq!(resp, CanExecuteResponse { can_execute: false });
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(SPENDER1, &[]),
+                ExecuteMsg::Execute { msgs: vec![msg] },
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::NotAllowed {});
+        }
+
+        #[test]
+        fn decrease() {
+            let Suite {
+                mut deps, owner, ..
+            } = Suite::init();
+            increase(deps.as_mut(), owner.clone(), 100);
+
+            let msg = ExecuteMsg::DecreaseCw20Allowance {
+                spender: SPENDER1.to_owned(),
+                token: CW20.to_owned(),
+                amount: Uint128::new(40),
+                expires: None,
+            };
+            execute(deps.as_mut(), mock_env(), owner.clone(), msg.clone()).unwrap();
+            assert_eq!(query_amount(deps.as_ref())[0].amount, Uint128::new(60));
+
+            // underflow removes the allowance
+            let msg = ExecuteMsg::DecreaseCw20Allowance {
+                spender: SPENDER1.to_owned(),
+                token: CW20.to_owned(),
+                amount: Uint128::new(100),
+                expires: None,
+            };
+            execute(deps.as_mut(), mock_env(), owner.clone(), msg.clone()).unwrap();
+            assert_eq!(query_amount(deps.as_ref()), vec![]);
+            let err = execute(deps.as_mut(), mock_env(), owner, msg).unwrap_err();
+            assert_eq!(err, ContractError::NoAllowance {});
+        }
     }
 
     mod can_execute {
@@ -2240,12 +3837,14 @@ mod tests {
         let allow = Allowance {
             balance: NativeBalance(vec![coin.clone()]),
             expires: Expiration::Never {},
+            period: None,
         };
         let perm = Permissions {
             delegate: true,
             redelegate: false,
             undelegate: false,
             withdraw: true,
+            ..NO_PERMS
         };
 
         let info = mock_info(owner, &[]);
@@ -2259,7 +3858,7 @@ mod tests {
         // setup permission and then allowance and check if changed
         let setup_perm_msg = ExecuteMsg::SetPermissions {
             spender: spender1.to_string(),
-            permissions: perm,
+            permissions: perm.clone(),
         };
         execute(deps.as_mut(), mock_env(), info.clone(), setup_perm_msg).unwrap();
 
@@ -2285,7 +3884,7 @@ mod tests {
 
         let setup_perm_msg = ExecuteMsg::SetPermissions {
             spender: spender2.to_string(),
-            permissions: perm,
+            permissions: perm.clone(),
         };
         execute(deps.as_mut(), mock_env(), info, setup_perm_msg).unwrap();
 
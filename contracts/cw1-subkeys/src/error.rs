@@ -31,20 +31,53 @@ pub enum ContractError {
     #[error("Un-delegate is not allowed")]
     UnDelegatePerm {},
 
+    #[error("Staking with validator {validator} is not allowed")]
+    ValidatorPerm { validator: String },
+
+    #[error("Delegated amount exceeds the limit")]
+    DelegationLimitExceeded {},
+
     #[error("Withdraw is not allowed")]
     WithdrawPerm {},
 
     #[error("Set withdraw address is not allowed")]
     WithdrawAddrPerm {},
 
+    #[error("Funding the community pool is not allowed")]
+    FundCommunityPoolPerm {},
+
+    #[error("Voting is not allowed")]
+    VotePerm {},
+
+    #[error("Ibc transfer on channel {channel} is not allowed")]
+    IbcChannelPerm { channel: String },
+
+    #[error("Executing contract {contract} is not allowed")]
+    ContractPerm { contract: String },
+
+    #[error("Execute message {msg} is not allowed")]
+    ExecuteMsgPerm { msg: String },
+
+    #[error("Attached funds exceed the limit")]
+    FundsLimitExceeded {},
+
     #[error("Unsupported message")]
     UnsupportedMessage {},
 
+    #[error("Allowance period cannot be zero")]
+    ZeroPeriod {},
+
+    #[error("Audit log retention cannot be zero")]
+    ZeroRetention {},
+
     #[error("Allowance already expired while setting: {0}")]
     SettingExpiredAllowance(Expiration),
 
     #[error("Semver parsing error: {0}")]
     SemVer(String),
+
+    #[error("{0}")]
+    Whitelist(cw1_whitelist::ContractError),
 }
 
 impl From<cw1_whitelist::ContractError> for ContractError {
@@ -52,6 +85,33 @@ impl From<cw1_whitelist::ContractError> for ContractError {
         match err {
             cw1_whitelist::ContractError::Std(error) => ContractError::Std(error),
             cw1_whitelist::ContractError::Unauthorized {} => ContractError::Unauthorized {},
+            cw1_whitelist::ContractError::ZeroRetention {} => ContractError::ZeroRetention {},
+            cw1_whitelist::ContractError::SemVer(msg) => ContractError::SemVer(msg),
+            // never produced by the whitelist code paths we reuse, kept as they are
+            err @ (cw1_whitelist::ContractError::Hook(_)
+            | cw1_whitelist::ContractError::MessageNotAllowed {}
+            | cw1_whitelist::ContractError::InvalidThreshold {}
+            | cw1_whitelist::ContractError::ExecutionExpired {}
+            | cw1_whitelist::ContractError::AlreadyApproved {}
+            | cw1_whitelist::ContractError::CannotMigrate { .. }
+            | cw1_whitelist::ContractError::CannotMigrateVersion { .. }
+            | cw1_whitelist::ContractError::InsufficientResources { .. }
+            | cw1_whitelist::ContractError::OverRelease { .. }
+            | cw1_whitelist::ContractError::ResourceTypeNotFound
+            | cw1_whitelist::ContractError::OtherError
+            | cw1_whitelist::ContractError::ResourceOverflow
+            | cw1_whitelist::ContractError::NotFound
+            | cw1_whitelist::ContractError::AlreadyExists
+            | cw1_whitelist::ContractError::EmptyOrderGroup
+            | cw1_whitelist::ContractError::NoActiveOrders { .. }
+            | cw1_whitelist::ContractError::DuplicateResource { .. }
+            | cw1_whitelist::ContractError::MarketplaceFeeTooHigh { .. }
+            | cw1_whitelist::ContractError::KeeperFeeTooHigh { .. }
+            | cw1_whitelist::ContractError::PubkeyNotRegistered
+            | cw1_whitelist::ContractError::CredentialsDeadlinePassed { .. }
+            | cw1_whitelist::ContractError::CredentialsTooLarge { .. }
+            | cw1_whitelist::ContractError::InvalidCredentialsConfig {}
+            | cw1_whitelist::ContractError::OrderExpired { .. }) => ContractError::Whitelist(err),
         }
     }
 }
@@ -3,10 +3,12 @@ use schemars::JsonSchema;
 use std::fmt;
 
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Coin, CosmosMsg, Empty};
-use cw_utils::{Expiration, NativeBalance};
+use cosmwasm_std::{Coin, CosmosMsg, Empty, Uint128};
+use cw_utils::{Duration, Expiration, NativeBalance};
 
-use crate::state::Permissions;
+use cw1_whitelist::audit::AuditConfig;
+
+use crate::state::{Period, Permissions};
 
 #[cw_serde]
 pub enum ExecuteMsg<T = Empty>
@@ -22,6 +24,10 @@ where
     /// UpdateAdmins will change the admin set of the contract, must be called by an existing admin,
     /// and only works if the contract is mutable
     UpdateAdmins { admins: Vec<String> },
+    /// Records every relayed `Execute`, keeping the latest `retention` records, or stops
+    /// recording when `config` is None. Must be called by an admin and only works if the
+    /// contract is mutable
+    SetAuditConfig { config: Option<AuditConfig> },
 
     /// Add an allowance to a given subkey (subkey must not be admin)
     IncreaseAllowance {
@@ -36,6 +42,30 @@ where
         expires: Option<Expiration>,
     },
 
+    /// Turns the allowance of a given subkey into a recurring one (subkey must not be admin).
+    /// Its balance is set to `cap` right away and restored to it every `period`
+    SetPeriodicAllowance {
+        spender: String,
+        cap: Vec<Coin>,
+        period: Duration,
+    },
+
+    /// Add a cw20 allowance to a given subkey (subkey must not be admin). The subkey can then
+    /// relay `Transfer` and `Send` messages to the token contract
+    IncreaseCw20Allowance {
+        spender: String,
+        token: String,
+        amount: Uint128,
+        expires: Option<Expiration>,
+    },
+    /// Decreases a cw20 allowance for a given subkey (subkey must not be admin)
+    DecreaseCw20Allowance {
+        spender: String,
+        token: String,
+        amount: Uint128,
+        expires: Option<Expiration>,
+    },
+
     // Setups up permissions for a given subkey.
     SetPermissions {
         spender: String,
@@ -55,6 +85,13 @@ where
     /// Get the current allowance for the given subkey (how much it can spend)
     #[returns(crate::state::Allowance)]
     Allowance { spender: String },
+    /// Gets all cw20 allowances of the given subkey
+    #[returns(Cw20AllowancesResponse)]
+    Cw20Allowances {
+        spender: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     /// Get the current permissions for the given subkey (how much it can spend)
     #[returns(PermissionsInfo)]
     Permissions { spender: String },
@@ -75,6 +112,16 @@ where
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    /// Shows whether relayed messages are recorded and how many records are kept
+    #[returns(cw1_whitelist::msg::AuditConfigResponse)]
+    AuditConfig {},
+    /// Gets the recorded executions, oldest first, optionally only those of `sender`
+    #[returns(cw1_whitelist::msg::ExecutionHistoryResponse)]
+    ExecutionHistory {
+        sender: Option<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 #[cw_serde]
@@ -98,8 +145,10 @@ impl AllAllowancesResponse {
 #[cw_serde]
 pub struct AllowanceInfo {
     pub spender: String,
+    /// for recurring allowances, the balance left in the current period
     pub balance: NativeBalance,
     pub expires: Expiration,
+    pub period: Option<Period>,
 }
 
 #[cfg(test)]
@@ -121,10 +170,12 @@ impl AllowanceInfo {
     ///   spender: "spender2".to_owned(),
     ///   balance: NativeBalance(vec![coin(1, "token1")]),
     ///   expires: Expiration::Never {},
+    ///   period: None,
     /// }, AllowanceInfo {
     ///   spender: "spender1".to_owned(),
     ///   balance: NativeBalance(vec![coin(2, "token2")]),
     ///   expires: Expiration::Never {},
+    ///   period: None,
     /// }];
     ///
     /// allows.sort_by(AllowanceInfo::cmp_by_spender);
@@ -144,6 +195,18 @@ impl AllowanceInfo {
     }
 }
 
+#[cw_serde]
+pub struct Cw20AllowanceInfo {
+    pub token: String,
+    pub amount: Uint128,
+    pub expires: Expiration,
+}
+
+#[cw_serde]
+pub struct Cw20AllowancesResponse {
+    pub allowances: Vec<Cw20AllowanceInfo>,
+}
+
 #[cw_serde]
 pub struct PermissionsInfo {
     pub spender: String,
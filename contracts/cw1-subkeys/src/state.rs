@@ -2,20 +2,50 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, BlockInfo, Coin, Timestamp, Uint128};
 use cw_storage_plus::Map;
-use cw_utils::{Expiration, NativeBalance};
+use cw_utils::{Duration, Expiration, NativeBalance};
 
 // Permissions struct defines users message execution permissions.
 // Could have implemented permissions for each cosmos module(StakingPermissions, GovPermissions etc...)
 // But that meant a lot of code for each module. Keeping the permissions inside one struct is more
 // optimal. Define other modules permissions here.
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema, Default, Copy)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema, Default)]
 pub struct Permissions {
     pub delegate: bool,
     pub redelegate: bool,
     pub undelegate: bool,
     pub withdraw: bool,
+    /// validators the subkey may delegate to, redelegate from and to, and undelegate from.
+    /// Any validator is allowed if not set
+    #[serde(default)]
+    pub validators: Option<Vec<String>>,
+    /// maximum amount the subkey may have delegated to a single validator, per denom.
+    /// Undelegating or redelegating away frees it up again. Unlimited if not set
+    #[serde(default)]
+    pub max_delegation: Option<Uint128>,
+    /// allows voting on governance proposals, including weighted votes.
+    /// Only with the "stargate" feature
+    #[serde(default)]
+    pub vote: bool,
+    /// allows funding the community pool, the amount is charged against the subkey's allowance.
+    /// Only with the "stargate" feature
+    #[serde(default)]
+    pub fund_community_pool: bool,
+    /// channels the subkey may send ibc transfers on, none by default. Transferred amounts
+    /// are charged against the subkey's allowance. Only with the "stargate" feature
+    #[serde(default)]
+    pub ibc_channels: Vec<String>,
+    /// contracts the subkey may execute, none by default
+    #[serde(default)]
+    pub contracts: Vec<ContractPermission>,
+}
+
+impl Permissions {
+    /// returns the permission for the given contract if it is allowlisted
+    pub fn contract(&self, contract: &str) -> Option<&ContractPermission> {
+        self.contracts.iter().find(|perm| perm.contract == contract)
+    }
 }
 
 impl fmt::Display for Permissions {
@@ -24,14 +54,88 @@ impl fmt::Display for Permissions {
             f,
             "staking: {{ delegate: {}, redelegate: {}, undelegate: {}, withdraw: {} }}",
             self.delegate, self.redelegate, self.undelegate, self.withdraw
-        )
+        )?;
+        if let Some(validators) = &self.validators {
+            write!(f, ", validators: [{}]", validators.join(", "))?;
+        }
+        if let Some(max_delegation) = self.max_delegation {
+            write!(f, ", max_delegation: {}", max_delegation)?;
+        }
+        if self.vote {
+            write!(f, ", gov: {{ vote: true }}")?;
+        }
+        if self.fund_community_pool {
+            write!(f, ", distribution: {{ fund_community_pool: true }}")?;
+        }
+        if !self.ibc_channels.is_empty() {
+            write!(
+                f,
+                ", ibc: {{ channels: [{}] }}",
+                self.ibc_channels.join(", ")
+            )?;
+        }
+        if !self.contracts.is_empty() {
+            let contracts: Vec<_> = self.contracts.iter().map(|c| c.contract.as_str()).collect();
+            write!(f, ", wasm: {{ contracts: [{}] }}", contracts.join(", "))?;
+        }
+        Ok(())
     }
 }
 
+/// Allows executing a contract through the proxy
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ContractPermission {
+    pub contract: String,
+    /// top-level names of the execute messages that may be sent, e.g. "claim" for
+    /// `{"claim":{}}`. Any message can be sent if not set
+    pub execute_msgs: Option<Vec<String>>,
+    /// maximum funds attached to a single message, no funds can be attached if not set.
+    /// Attached funds are also charged against the subkey's allowance
+    pub funds_limit: Option<Vec<Coin>>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
 pub struct Allowance {
     pub balance: NativeBalance,
     pub expires: Expiration,
+    /// set for recurring allowances, whose balance is restored at every period
+    #[serde(default)]
+    pub period: Option<Period>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Period {
+    pub duration: Duration,
+    /// balance available in every period
+    pub cap: NativeBalance,
+    pub next_reset: Expiration,
+}
+
+impl Allowance {
+    /// Restores the balance to the cap once the period elapsed. Periods stay aligned on the
+    /// first one, so a reset happening late does not shift the following ones.
+    pub fn refresh(&mut self, block: &BlockInfo) {
+        let Some(period) = self.period.as_mut() else {
+            return;
+        };
+        if !period.next_reset.is_expired(block) {
+            return;
+        }
+
+        self.balance = period.cap.clone();
+        period.next_reset = match (period.next_reset, period.duration) {
+            (Expiration::AtHeight(reset), Duration::Height(blocks)) => {
+                Expiration::AtHeight(reset + ((block.height - reset) / blocks + 1) * blocks)
+            }
+            (Expiration::AtTime(reset), Duration::Time(secs)) => {
+                let elapsed = block.time.seconds() - reset.seconds();
+                Expiration::AtTime(Timestamp::from_seconds(
+                    reset.seconds() + (elapsed / secs + 1) * secs,
+                ))
+            }
+            _ => period.duration.after(block),
+        };
+    }
 }
 
 #[cfg(test)]
@@ -53,11 +157,13 @@ impl Allowance {
     /// let allow1 = Allowance {
     ///   balance: NativeBalance(vec![coin(1, "token1"), coin(0, "token2"), coin(2, "token1"), coin(3, "token3")]),
     ///   expires: Expiration::Never {},
+    ///   period: None,
     /// };
     ///
     /// let allow2 = Allowance {
     ///   balance: NativeBalance(vec![coin(3, "token3"), coin(3, "token1")]),
     ///   expires: Expiration::Never {},
+    ///   period: None,
     /// };
     ///
     /// assert_eq!(allow1.canonical(), allow2.canonical());
@@ -68,5 +174,16 @@ impl Allowance {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct Cw20Allowance {
+    pub amount: Uint128,
+    pub expires: Expiration,
+}
+
 pub const PERMISSIONS: Map<&Addr, Permissions> = Map::new("permissions");
 pub const ALLOWANCES: Map<&Addr, Allowance> = Map::new("allowances");
+/// (spender, validator, denom) -> amount the spender delegated and redelegated to the
+/// validator, minus what it undelegated or redelegated away
+pub const DELEGATIONS: Map<(&Addr, &str, &str), Uint128> = Map::new("delegations");
+/// (spender, token) -> amount of the cw20 token the spender may transfer
+pub const CW20_ALLOWANCES: Map<(&Addr, &Addr), Cw20Allowance> = Map::new("cw20_allowances");
@@ -0,0 +1,220 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    Addr, BlockInfo, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult,
+    Storage,
+};
+use cw_storage_plus::{Bound, Item, Map};
+use cw_utils::{Duration, Expiration};
+
+use crate::audit::record_execution;
+use crate::msg::{ApprovalConfigResponse, PendingExecutionsResponse};
+use crate::policy::assert_policy;
+use crate::roles::{has_role, Role};
+use crate::state::{AdminList, ADMIN_LIST};
+use crate::ContractError;
+
+/// When set, `Execute` only proposes the messages, which are relayed once
+/// `threshold` admins holding the `ProxyExecutor` role approved them before the proposal expires
+#[cw_serde]
+pub struct ApprovalConfig {
+    pub threshold: u32,
+    pub expiry: Duration,
+}
+
+#[cw_serde]
+pub struct PendingExecution {
+    pub id: u64,
+    pub proposer: Addr,
+    pub msgs: Vec<CosmosMsg>,
+    /// approvers who approved so far
+    pub approvals: Vec<Addr>,
+    pub expires: Expiration,
+    /// approvals needed, from the config at the time of the proposal
+    pub threshold: u32,
+}
+
+impl PendingExecution {
+    /// only counts approvals of current approvers, so removed admins lose their say
+    fn approval_count(&self, storage: &dyn Storage, cfg: &AdminList) -> u32 {
+        self.approvals
+            .iter()
+            .filter(|a| is_approver(storage, cfg, a))
+            .count() as u32
+    }
+}
+
+// 只有拥有执行者角色的管理员才能提议和批准执行
+fn is_approver(storage: &dyn Storage, cfg: &AdminList, addr: &Addr) -> bool {
+    cfg.is_admin(addr) && has_role(storage, Role::ProxyExecutor, addr)
+}
+
+pub const APPROVAL_CONFIG: Item<ApprovalConfig> = Item::new("approval_config");
+pub const PENDING_EXECUTIONS: Map<u64, PendingExecution> = Map::new("pending_executions");
+pub const PENDING_EXECUTION_COUNT: Item<u64> = Item::new("pending_execution_count");
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+fn next_id(store: &mut dyn Storage) -> StdResult<u64> {
+    let id: u64 = PENDING_EXECUTION_COUNT.may_load(store)?.unwrap_or_default() + 1;
+    PENDING_EXECUTION_COUNT.save(store, &id)?;
+    Ok(id)
+}
+
+/// returns an error if the threshold cannot be reached with the given admins
+pub fn validate_threshold(threshold: u32, admins: usize) -> Result<(), ContractError> {
+    if threshold == 0 || threshold as usize > admins {
+        return Err(ContractError::InvalidThreshold {});
+    }
+    Ok(())
+}
+
+// 达到阈值后转发消息，否则保存待批准的执行
+fn execute_if_approved(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    cfg: &AdminList,
+    pending: PendingExecution,
+) -> StdResult<Response> {
+    let approvals = pending.approval_count(storage, cfg);
+    if approvals >= pending.threshold {
+        PENDING_EXECUTIONS.remove(storage, pending.id);
+        record_execution(storage, block, &pending.proposer, &pending.msgs)?;
+        Ok(Response::new()
+            .add_messages(pending.msgs)
+            .add_attribute("action", "execute")
+            .add_attribute("execution_id", pending.id.to_string()))
+    } else {
+        PENDING_EXECUTIONS.save(storage, pending.id, &pending)?;
+        Ok(Response::new()
+            .add_attribute("action", "propose_execution")
+            .add_attribute("execution_id", pending.id.to_string())
+            .add_attribute("approvals", approvals.to_string()))
+    }
+}
+
+pub fn propose_execution(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    config: ApprovalConfig,
+    msgs: Vec<CosmosMsg>,
+) -> Result<Response, ContractError> {
+    let cfg = ADMIN_LIST.load(deps.storage)?;
+    if !is_approver(deps.storage, &cfg, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    assert_policy(deps.storage, &info.sender, &msgs)?;
+
+    let pending = PendingExecution {
+        id: next_id(deps.storage)?,
+        proposer: info.sender.clone(),
+        msgs,
+        // proposing the messages approves them
+        approvals: vec![info.sender],
+        expires: config.expiry.after(&env.block),
+        threshold: config.threshold,
+    };
+
+    Ok(execute_if_approved(deps.storage, &env.block, &cfg, pending)?)
+}
+
+pub fn approve_execution(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let cfg = ADMIN_LIST.load(deps.storage)?;
+    if !is_approver(deps.storage, &cfg, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut pending = PENDING_EXECUTIONS.load(deps.storage, id)?;
+    if pending.expires.is_expired(&env.block) {
+        return Err(ContractError::ExecutionExpired {});
+    }
+    if pending.approvals.contains(&info.sender) {
+        return Err(ContractError::AlreadyApproved {});
+    }
+    pending.approvals.push(info.sender);
+
+    // the threshold of the proposal still applies if the config changed since
+    Ok(execute_if_approved(deps.storage, &env.block, &cfg, pending)?)
+}
+
+/// removes the expired ones among the next `limit` pending executions after `start_after`,
+/// oldest first
+pub fn prune_expired_executions(
+    deps: DepsMut,
+    env: Env,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let scanned = PENDING_EXECUTIONS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    let expired: Vec<_> = scanned
+        .into_iter()
+        .filter(|(_, pending)| pending.expires.is_expired(&env.block))
+        .map(|(id, _)| id)
+        .collect();
+
+    for id in expired.iter() {
+        PENDING_EXECUTIONS.remove(deps.storage, *id);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "prune_expired_executions")
+        .add_attribute("pruned", expired.len().to_string()))
+}
+
+pub fn set_approval_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    config: Option<ApprovalConfig>,
+) -> Result<Response, ContractError> {
+    let cfg = ADMIN_LIST.load(deps.storage)?;
+    if !cfg.can_modify(info.sender.as_ref()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match config {
+        Some(config) => {
+            validate_threshold(config.threshold, cfg.admins.len())?;
+            APPROVAL_CONFIG.save(deps.storage, &config)?;
+        }
+        None => APPROVAL_CONFIG.remove(deps.storage),
+    }
+
+    Ok(Response::new().add_attribute("action", "set_approval_config"))
+}
+
+pub fn query_approval_config(deps: Deps) -> StdResult<ApprovalConfigResponse> {
+    let config = APPROVAL_CONFIG.may_load(deps.storage)?;
+    Ok(ApprovalConfigResponse { config })
+}
+
+pub fn query_pending_execution(deps: Deps, id: u64) -> StdResult<PendingExecution> {
+    PENDING_EXECUTIONS.load(deps.storage, id)
+}
+
+pub fn query_pending_executions(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PendingExecutionsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let executions = PENDING_EXECUTIONS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, pending)| pending))
+        .collect::<StdResult<_>>()?;
+
+    Ok(PendingExecutionsResponse { executions })
+}
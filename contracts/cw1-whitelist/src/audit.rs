@@ -0,0 +1,177 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    Addr, BankMsg, BlockInfo, Coin, CosmosMsg, Deps, DepsMut, MessageInfo, Order, Response,
+    StdResult, Storage, WasmMsg,
+};
+use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, MultiIndex};
+use cw_utils::NativeBalance;
+
+use crate::msg::{AuditConfigResponse, ExecutionHistoryResponse};
+use crate::policy::{msg_type, MsgType};
+use crate::state::ADMIN_LIST;
+use crate::ContractError;
+
+/// When set, every relayed `Execute` is recorded and the latest `retention` records are kept
+#[cw_serde]
+pub struct AuditConfig {
+    pub retention: u32,
+}
+
+#[cw_serde]
+pub struct ExecutionRecord {
+    pub id: u64,
+    pub sender: Addr,
+    pub height: u64,
+    /// kind of each relayed message, in order
+    pub msg_types: Vec<MsgType>,
+    /// native funds sent out of the proxy, see `sent_funds`
+    pub spent: Vec<Coin>,
+}
+
+pub struct ExecutionIndexes<'a> {
+    pub sender: MultiIndex<'a, Addr, ExecutionRecord, u64>,
+}
+
+impl<'a> IndexList<ExecutionRecord> for ExecutionIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<ExecutionRecord>> + '_> {
+        let v: Vec<&dyn Index<ExecutionRecord>> = vec![&self.sender];
+        Box::new(v.into_iter())
+    }
+}
+
+// 执行编号 -> 执行记录，按发送者索引
+pub const AUDIT_LOG: IndexedMap<u64, ExecutionRecord, ExecutionIndexes> = IndexedMap::new(
+    "audit_log",
+    ExecutionIndexes {
+        sender: MultiIndex::new(
+            |_pk, record| record.sender.clone(),
+            "audit_log",
+            "audit_log__sender",
+        ),
+    },
+);
+pub const AUDIT_LOG_COUNT: Item<u64> = Item::new("audit_log_count");
+pub const AUDIT_CONFIG: Item<AuditConfig> = Item::new("audit_config");
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+/// records dropped by a single execution at most, so lowering the retention cannot make
+/// `Execute` run out of gas
+pub const MAX_PRUNED_RECORDS: usize = 10;
+
+/// sums the native funds sent out of the proxy by bank sends and wasm messages
+pub fn sent_funds<T>(msgs: &[CosmosMsg<T>]) -> Vec<Coin> {
+    let mut spent = NativeBalance::default();
+    for msg in msgs {
+        let sent = match msg {
+            CosmosMsg::Bank(BankMsg::Send { amount, .. }) => amount.clone(),
+            CosmosMsg::Wasm(WasmMsg::Execute { funds, .. })
+            | CosmosMsg::Wasm(WasmMsg::Instantiate { funds, .. }) => funds.clone(),
+            _ => continue,
+        };
+        spent += NativeBalance(sent);
+    }
+    spent.normalize();
+    spent.into_vec()
+}
+
+/// Records the messages relayed for the sender, dropping up to `MAX_PRUNED_RECORDS` records
+/// beyond the retention. Does nothing unless the audit log is enabled
+pub fn record_execution<T>(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    sender: &Addr,
+    msgs: &[CosmosMsg<T>],
+) -> StdResult<()> {
+    let Some(config) = AUDIT_CONFIG.may_load(storage)? else {
+        return Ok(());
+    };
+
+    let id = AUDIT_LOG_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    AUDIT_LOG_COUNT.save(storage, &id)?;
+    let record = ExecutionRecord {
+        id,
+        sender: sender.clone(),
+        height: block.height,
+        msg_types: msgs.iter().map(msg_type).collect(),
+        spent: sent_funds(msgs),
+    };
+    AUDIT_LOG.save(storage, id, &record)?;
+
+    // usually a single record, more if the retention was lowered, in which case the
+    // following executions catch up
+    if let Some(oldest_kept) = id.checked_sub(config.retention as u64) {
+        let expired = AUDIT_LOG
+            .keys(
+                storage,
+                None,
+                Some(Bound::inclusive(oldest_kept)),
+                Order::Ascending,
+            )
+            .take(MAX_PRUNED_RECORDS)
+            .collect::<StdResult<Vec<_>>>()?;
+        for id in expired {
+            AUDIT_LOG.remove(storage, id)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn set_audit_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    config: Option<AuditConfig>,
+) -> Result<Response, ContractError> {
+    let cfg = ADMIN_LIST.load(deps.storage)?;
+    if !cfg.can_modify(info.sender.as_ref()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match config {
+        Some(config) => {
+            if config.retention == 0 {
+                return Err(ContractError::ZeroRetention {});
+            }
+            AUDIT_CONFIG.save(deps.storage, &config)?;
+        }
+        None => AUDIT_CONFIG.remove(deps.storage),
+    }
+
+    Ok(Response::new().add_attribute("action", "set_audit_config"))
+}
+
+pub fn query_audit_config(deps: Deps) -> StdResult<AuditConfigResponse> {
+    let config = AUDIT_CONFIG.may_load(deps.storage)?;
+    Ok(AuditConfigResponse { config })
+}
+
+pub fn query_execution_history(
+    deps: Deps,
+    sender: Option<String>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ExecutionHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let records = match sender {
+        Some(sender) => {
+            let sender = deps.api.addr_validate(&sender)?;
+            AUDIT_LOG
+                .idx
+                .sender
+                .prefix(sender)
+                .range(deps.storage, start, None, Order::Ascending)
+                .take(limit)
+                .collect::<StdResult<Vec<_>>>()?
+        }
+        None => AUDIT_LOG
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?,
+    };
+
+    Ok(ExecutionHistoryResponse {
+        executions: records.into_iter().map(|(_, record)| record).collect(),
+    })
+}
@@ -9,13 +9,40 @@ use cosmwasm_std::{
 };
 
 use cw1::CanExecuteResponse;
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
+use cw_controllers::Hooks;
+use semver::Version;
 
 use crate::error::ContractError;
-use crate::msg::{AdminListResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::state::{AdminList, ADMIN_LIST};
+use crate::migrations::{v1, V2_LAYOUT};
+use crate::msg::{AdminListResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::state::{AdminList, ADMIN_LIST, ORDER_HOOKS, RESOURCE_HOOKS};
+use crate::policy::{assert_policy, policy_allows, query_execute_policy, set_execute_policy};
+use crate::approvals::{
+    approve_execution, propose_execution, prune_expired_executions, query_approval_config,
+    query_pending_execution, query_pending_executions, set_approval_config, validate_threshold,
+    APPROVAL_CONFIG,
+};
+use crate::audit::{
+    query_audit_config, query_execution_history, record_execution, set_audit_config,
+};
+use crate::roles::{
+    grant_all_roles, grant_role, has_role, query_roles, revoke_role, sync_admin_roles, Role,
+};
 use crate::resource::{query_resources, update_resources, add_resources, delete_resources};
-use crate::order::{create_order, end_order, handle_exception};
+use crate::order::{
+    create_order, end_order, handle_exception, query_keeper_fee, query_marketplace_fee,
+    set_keeper_fee, set_marketplace_fee, settle_expired,
+};
+use crate::stats::{query_market_stats, query_provider_revenue};
+use crate::order_group::{
+    cancel_order_group, create_order_batch, end_order_group, extend_order_group,
+    query_order_group,
+};
+use crate::credentials::{
+    deliver_credentials, flag_undelivered_credentials, query_credentials,
+    query_credentials_config, register_pubkey, set_credentials_config,
+};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:cw1-whitelist";
@@ -34,6 +61,9 @@ pub fn instantiate(
         mutable: msg.mutable,
     };
     ADMIN_LIST.save(deps.storage, &cfg)?;
+    // initial admins start with every role, they can hand them over later
+    grant_all_roles(deps.storage, &cfg.admins)?;
+    V2_LAYOUT.save(deps.storage, &true)?;
     Ok(Response::default())
 }
 
@@ -51,9 +81,23 @@ pub fn execute(
     msg: ExecuteMsg<Empty>,
 ) -> Result<Response<Empty>, ContractError> {
     match msg {
-        ExecuteMsg::Execute { msgs } => execute_execute(deps, env, info, msgs),
+        ExecuteMsg::Execute { msgs } => match APPROVAL_CONFIG.may_load(deps.storage)? {
+            Some(config) => propose_execution(deps, env, info, config, msgs),
+            None => execute_execute(deps, env, info, msgs),
+        },
+        ExecuteMsg::ApproveExecution { id } => approve_execution(deps, env, info, id),
+        ExecuteMsg::PruneExpiredExecutions { start_after, limit } => {
+            prune_expired_executions(deps, env, start_after, limit)
+        }
+        ExecuteMsg::SetApprovalConfig { config } => set_approval_config(deps, info, config),
         ExecuteMsg::Freeze {} => execute_freeze(deps, env, info),
         ExecuteMsg::UpdateAdmins { admins } => execute_update_admins(deps, env, info, admins),
+        ExecuteMsg::GrantRole { role, addr } => grant_role(deps, info, role, addr),
+        ExecuteMsg::RevokeRole { role, addr } => revoke_role(deps, info, role, addr),
+        ExecuteMsg::SetExecutePolicy { addr, policy } => {
+            set_execute_policy(deps, info, addr, policy)
+        }
+        ExecuteMsg::SetAuditConfig { config } => set_audit_config(deps, info, config),
         //ExecuteMsg::UseResources { ids } => {}
         ExecuteMsg::UpdateResources { ids, resource_types } => {update_resources(deps, env, info, ids, resource_types)}
         ExecuteMsg::AddResources { resources } => {add_resources(deps, env, info, resources)}
@@ -62,21 +106,56 @@ pub fn execute(
         ExecuteMsg::CreateOrder { resource_id, duration } => {create_order(deps, env,info, resource_id, duration)}
         ExecuteMsg::EndOrder { order_id } => {end_order(deps,env,info, order_id)}
         ExecuteMsg::HandleException { order_id, action } => {handle_exception(deps,env, info, order_id, action)}
+        ExecuteMsg::CreateOrderBatch {
+            resource_ids,
+            duration,
+        } => create_order_batch(deps, env, info, resource_ids, duration),
+        ExecuteMsg::ExtendOrderGroup { group_id, duration } => {
+            extend_order_group(deps, env, info, group_id, duration)
+        }
+        ExecuteMsg::CancelOrderGroup { group_id } => cancel_order_group(deps, env, info, group_id),
+        ExecuteMsg::EndOrderGroup { group_id } => end_order_group(deps, env, info, group_id),
+        ExecuteMsg::SettleExpired { limit } => settle_expired(deps, env, info, limit),
+        ExecuteMsg::SetMarketplaceFee { fee } => set_marketplace_fee(deps, env, info, fee),
+        ExecuteMsg::SetKeeperFee { fee } => set_keeper_fee(deps, env, info, fee),
+        ExecuteMsg::RegisterPubkey { order_id, pubkey } => {
+            register_pubkey(deps, env, info, order_id, pubkey)
+        }
+        ExecuteMsg::DeliverCredentials {
+            order_id,
+            credentials,
+        } => deliver_credentials(deps, env, info, order_id, credentials),
+        ExecuteMsg::FlagUndeliveredCredentials { order_id } => {
+            flag_undelivered_credentials(deps, env, info, order_id)
+        }
+        ExecuteMsg::SetCredentialsConfig { config } => set_credentials_config(deps, info, config),
+        ExecuteMsg::AddOrderHook { addr } => execute_add_hook(deps, info, &ORDER_HOOKS, addr),
+        ExecuteMsg::RemoveOrderHook { addr } => {
+            execute_remove_hook(deps, info, &ORDER_HOOKS, addr)
+        }
+        ExecuteMsg::AddResourceHook { addr } => {
+            execute_add_hook(deps, info, &RESOURCE_HOOKS, addr)
+        }
+        ExecuteMsg::RemoveResourceHook { addr } => {
+            execute_remove_hook(deps, info, &RESOURCE_HOOKS, addr)
+        }
     }
 }
 
 pub fn execute_execute<T>(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msgs: Vec<CosmosMsg<T>>,
 ) -> Result<Response<T>, ContractError>
 where
     T: Clone + fmt::Debug + PartialEq + JsonSchema,
 {
-    if !can_execute(deps.as_ref(), info.sender.as_ref())? {
+    if !has_role(deps.storage, Role::ProxyExecutor, &info.sender) {
         Err(ContractError::Unauthorized {})
     } else {
+        assert_policy(deps.storage, &info.sender, &msgs)?;
+        record_execution(deps.storage, &env.block, &info.sender, &msgs)?;
         let res = Response::new()
             .add_messages(msgs)
             .add_attribute("action", "execute");
@@ -111,7 +190,12 @@ pub fn execute_update_admins(
     if !cfg.can_modify(info.sender.as_ref()) {
         Err(ContractError::Unauthorized {})
     } else {
-        cfg.admins = map_validate(deps.api, &admins)?;
+        let admins = map_validate(deps.api, &admins)?;
+        if let Some(config) = APPROVAL_CONFIG.may_load(deps.storage)? {
+            validate_threshold(config.threshold, admins.len())?;
+        }
+        sync_admin_roles(deps.storage, &cfg.admins, &admins)?;
+        cfg.admins = admins;
         ADMIN_LIST.save(deps.storage, &cfg)?;
 
         let res = Response::new().add_attribute("action", "update_admins");
@@ -119,10 +203,63 @@ pub fn execute_update_admins(
     }
 }
 
-fn can_execute(deps: Deps, sender: &str) -> StdResult<bool> {
+pub fn execute_add_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    hooks: &Hooks,
+    addr: String,
+) -> Result<Response, ContractError> {
     let cfg = ADMIN_LIST.load(deps.storage)?;
-    let can = cfg.is_admin(sender);
-    Ok(can)
+    if !cfg.can_modify(info.sender.as_ref()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let addr = deps.api.addr_validate(&addr)?;
+    hooks.add_hook(deps.storage, addr.clone())?;
+
+    let res = Response::new()
+        .add_attribute("action", "add_hook")
+        .add_attribute("hook", addr)
+        .add_attribute("sender", info.sender);
+    Ok(res)
+}
+
+pub fn execute_remove_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    hooks: &Hooks,
+    addr: String,
+) -> Result<Response, ContractError> {
+    let cfg = ADMIN_LIST.load(deps.storage)?;
+    if !cfg.can_modify(info.sender.as_ref()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let addr = deps.api.addr_validate(&addr)?;
+    hooks.remove_hook(deps.storage, addr.clone())?;
+
+    let res = Response::new()
+        .add_attribute("action", "remove_hook")
+        .add_attribute("hook", addr)
+        .add_attribute("sender", info.sender);
+    Ok(res)
+}
+
+fn can_execute<T>(deps: Deps, sender: &str, msg: &CosmosMsg<T>) -> StdResult<bool> {
+    let sender = Addr::unchecked(sender);
+    if !has_role(deps.storage, Role::ProxyExecutor, &sender)
+        || !policy_allows(deps.storage, &sender, msg)?
+    {
+        return Ok(false);
+    }
+
+    // with approvals required, messages are only relayed right away if the
+    // sender's own approval reaches the threshold
+    match APPROVAL_CONFIG.may_load(deps.storage)? {
+        Some(config) if config.threshold > 1 => Ok(false),
+        Some(_) => Ok(ADMIN_LIST.load(deps.storage)?.is_admin(&sender)),
+        None => Ok(true),
+    }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -132,9 +269,42 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::CanExecute { sender, msg } => {
             to_json_binary(&query_can_execute(deps, sender, msg)?)
         }
+        QueryMsg::Roles {} => to_json_binary(&query_roles(deps)?),
+        QueryMsg::ApprovalConfig {} => to_json_binary(&query_approval_config(deps)?),
+        QueryMsg::PendingExecution { id } => to_json_binary(&query_pending_execution(deps, id)?),
+        QueryMsg::ListPendingExecutions { start_after, limit } => {
+            to_json_binary(&query_pending_executions(deps, start_after, limit)?)
+        }
+        QueryMsg::ExecutePolicy { addr } => to_json_binary(&query_execute_policy(deps, addr)?),
+        QueryMsg::AuditConfig {} => to_json_binary(&query_audit_config(deps)?),
+        QueryMsg::ExecutionHistory {
+            sender,
+            start_after,
+            limit,
+        } => to_json_binary(&query_execution_history(deps, sender, start_after, limit)?),
         QueryMsg::QueryResources {ids} => {
             to_json_binary(&query_resources(deps, ids)?)
         }
+        QueryMsg::MarketStats {} => to_json_binary(&query_market_stats(deps)?),
+        QueryMsg::ProviderRevenue {
+            provider,
+            start_height,
+            end_height,
+        } => to_json_binary(&query_provider_revenue(
+            deps,
+            provider,
+            start_height,
+            end_height,
+        )?),
+        QueryMsg::MarketplaceFee {} => to_json_binary(&query_marketplace_fee(deps)?),
+        QueryMsg::KeeperFee {} => to_json_binary(&query_keeper_fee(deps)?),
+        QueryMsg::OrderGroup { group_id } => to_json_binary(&query_order_group(deps, group_id)?),
+        QueryMsg::OrderCredentials { order_id } => {
+            to_json_binary(&query_credentials(deps, order_id)?)
+        }
+        QueryMsg::CredentialsConfig {} => to_json_binary(&query_credentials_config(deps)?),
+        QueryMsg::OrderHooks {} => to_json_binary(&ORDER_HOOKS.query_hooks(deps)?),
+        QueryMsg::ResourceHooks {} => to_json_binary(&RESOURCE_HOOKS.query_hooks(deps)?),
     }
 }
 
@@ -149,18 +319,79 @@ pub fn query_admin_list(deps: Deps) -> StdResult<AdminListResponse> {
 pub fn query_can_execute(
     deps: Deps,
     sender: String,
-    _msg: CosmosMsg,
+    msg: CosmosMsg,
 ) -> StdResult<CanExecuteResponse> {
     Ok(CanExecuteResponse {
-        can_execute: can_execute(deps, &sender)?,
+        can_execute: can_execute(deps, &sender, &msg)?,
     })
 }
 
+// the expiry index and marketplace statistics were added after 2.0.0,
+// this is the last release that needs to be migrated to v2
+const MIGRATE_VERSION_2: &str = "2.0.0";
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let version: Version = CONTRACT_VERSION.parse()?;
+    let stored = get_contract_version(deps.storage)?;
+    let storage_version: Version = stored.version.parse()?;
+
+    // First, ensure we are working from an equal or older version of this contract
+    // wrong type
+    if CONTRACT_NAME != stored.contract {
+        return Err(ContractError::CannotMigrate {
+            previous_contract: stored.contract,
+        });
+    }
+    // existing one is newer
+    if storage_version > version {
+        return Err(ContractError::CannotMigrateVersion {
+            previous_version: stored.version,
+        });
+    }
+
+    // run the v1->v2 conversion if we are v1 style, 2.0.0 is both the last v1 release and
+    // the current one so the storage marker tells them apart
+    let v2_layout = V2_LAYOUT.may_load(deps.storage)?.unwrap_or_default();
+    if storage_version <= MIGRATE_VERSION_2.parse()? && !v2_layout {
+        v1::rebuild_indexes_and_stats(deps.storage)?;
+        v1::seed_roles(deps.storage)?;
+        V2_LAYOUT.save(deps.storage, &true)?;
+    }
+    // otherwise no migration (yet) - add them here
+
+    // we don't need to save anything if migrating from the same version
+    if storage_version < version {
+        set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    }
+
+    Ok(Response::new())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coin, coins, BankMsg, StakingMsg, SubMsg, WasmMsg};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi};
+    use cosmwasm_std::{
+        coin, coins, BankMsg, Decimal, StakingMsg, StdError, SubMsg, Uint128, WasmMsg,
+    };
+    use cw_controllers::HookError;
+
+    use crate::hook::{OrderChangedHookMsg, OrderDiff, ResourceChangedHookMsg, ResourceDiff};
+    use crate::msg::{CapacityResponse, StatCount};
+    use crate::order::{query_keeper_fee, query_marketplace_fee, ORDER_MAP};
+    use cw_utils::Duration;
+
+    use crate::approvals::ApprovalConfig;
+    use crate::audit::{AuditConfig, ExecutionRecord, MAX_PRUNED_RECORDS};
+    use crate::policy::{query_execute_policy, ExecutePolicy, MsgType};
+    use crate::stats::ACTIVE_ORDERS;
+    use crate::resource::RESOURCE_MAP;
+    use crate::roles::ROLES;
+    use crate::order_group::query_order_group;
+    use crate::credentials::{query_credentials_config, CredentialsConfig};
+    use crate::order_type::{HandleAction, OrderStatus, DEFAULT_DENOM};
+    use crate::resource_type::{Region, Resource, ResourceAttr, ResourceDetails, Status, NAT};
 
     #[test]
     fn instantiate_and_modify_config() {
@@ -209,6 +440,19 @@ mod tests {
         };
         assert_eq!(query_admin_list(deps.as_ref()).unwrap(), expected);
 
+        // carl lost his roles with his seat, so he cannot relay messages anymore
+        let send: CosmosMsg = BankMsg::Send {
+            to_address: carl.to_string(),
+            amount: coins(10, "atom"),
+        }
+        .into();
+        let msg = ExecuteMsg::Execute { msgs: vec![send] };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(&carl, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        assert!(Role::ALL
+            .into_iter()
+            .all(|role| !has_role(&deps.storage, role, &Addr::unchecked(&carl))));
+
         // carl cannot freeze it
         let info = mock_info(&carl, &[]);
         let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Freeze {}).unwrap_err();
@@ -324,4 +568,1437 @@ mod tests {
         let res = query_can_execute(deps.as_ref(), anyone.to_string(), staking_msg).unwrap();
         assert!(!res.can_execute);
     }
+
+    #[test]
+    fn roles_restrict_permissions() {
+        let mut deps = mock_dependencies();
+
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+        let carl = deps.api.addr_make("carl");
+
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![alice.to_string()],
+            mutable: true,
+        };
+        let info = mock_info(alice.as_str(), &[]);
+        instantiate(deps.as_mut(), mock_env(), info, instantiate_msg).unwrap();
+
+        // initial admins hold every role
+        let roles = query_roles(deps.as_ref()).unwrap();
+        assert_eq!(roles.roles.len(), Role::ALL.len());
+        for role in roles.roles {
+            assert_eq!(role.members, vec![alice.to_string()]);
+        }
+
+        // only admins manage roles
+        let grant = ExecuteMsg::GrantRole {
+            role: Role::ProxyExecutor,
+            addr: bob.to_string(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(carl.as_str(), &[]), grant.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), grant).unwrap();
+
+        // bob, who is not an admin, can now relay messages but not moderate resources
+        let send_msg = CosmosMsg::Bank(BankMsg::Send {
+            to_address: carl.to_string(),
+            amount: coins(100, "ushell"),
+        });
+        let res = query_can_execute(deps.as_ref(), bob.to_string(), send_msg.clone()).unwrap();
+        assert!(res.can_execute);
+        let msg = ExecuteMsg::AddResources {
+            resources: vec![mock_resource("m1", bob.clone())],
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &[]), msg)
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // an admin without the fee manager role cannot set fees
+        let revoke = ExecuteMsg::RevokeRole {
+            role: Role::FeeManager,
+            addr: alice.to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), revoke.clone())
+            .unwrap();
+        let msg = ExecuteMsg::SetKeeperFee {
+            fee: Decimal::percent(1),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg)
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // revoking a missing role fails
+        let err = execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), revoke)
+            .unwrap_err();
+        assert_eq!(err, ContractError::NotFound);
+
+        // frozen contracts cannot change roles anymore
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(alice.as_str(), &[]),
+            ExecuteMsg::Freeze {},
+        )
+        .unwrap();
+        let revoke = ExecuteMsg::RevokeRole {
+            role: Role::ProxyExecutor,
+            addr: bob.to_string(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), revoke)
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let res = query_can_execute(deps.as_ref(), bob.to_string(), send_msg).unwrap();
+        assert!(res.can_execute);
+    }
+
+    #[test]
+    fn execute_policies_filter_messages() {
+        let mut deps = mock_dependencies();
+
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+        let token = deps.api.addr_make("token");
+        let other = deps.api.addr_make("other");
+
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![alice.to_string(), bob.to_string()],
+            mutable: true,
+        };
+        let info = mock_info(alice.as_str(), &[]);
+        instantiate(deps.as_mut(), mock_env(), info, instantiate_msg).unwrap();
+
+        // bob may only call transfer on the token contract
+        let policy = ExecutePolicy {
+            msg_types: Some(vec![MsgType::Wasm]),
+            contracts: Some(vec![token.to_string()]),
+            execute_msgs: Some(vec!["transfer".to_string()]),
+        };
+        let msg = ExecuteMsg::SetExecutePolicy {
+            addr: bob.to_string(),
+            policy: Some(policy.clone()),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(other.as_str(), &[]), msg.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+        let res = query_execute_policy(deps.as_ref(), bob.to_string()).unwrap();
+        assert_eq!(res.policy, Some(policy));
+
+        let wasm_msg = |contract: &Addr, msg: &str| -> CosmosMsg {
+            WasmMsg::Execute {
+                contract_addr: contract.to_string(),
+                msg: Binary::from(msg.as_bytes()),
+                funds: vec![],
+            }
+            .into()
+        };
+        let transfer = wasm_msg(&token, r#"{"transfer":{"recipient":"x","amount":"1"}}"#);
+        let burn = wasm_msg(&token, r#"{"burn":{"amount":"1"}}"#);
+        let elsewhere = wasm_msg(&other, r#"{"transfer":{"recipient":"x","amount":"1"}}"#);
+        let send: CosmosMsg = BankMsg::Send {
+            to_address: other.to_string(),
+            amount: coins(100, "ushell"),
+        }
+        .into();
+
+        let can = |deps: Deps, msg: &CosmosMsg| {
+            query_can_execute(deps, bob.to_string(), msg.clone())
+                .unwrap()
+                .can_execute
+        };
+        assert!(can(deps.as_ref(), &transfer));
+        assert!(!can(deps.as_ref(), &burn));
+        assert!(!can(deps.as_ref(), &elsewhere));
+        assert!(!can(deps.as_ref(), &send));
+
+        // nor migrate the token or change its admin
+        let migrate: CosmosMsg = WasmMsg::Migrate {
+            contract_addr: token.to_string(),
+            new_code_id: 2,
+            msg: Binary::from(b"{}".as_slice()),
+        }
+        .into();
+        let update_admin: CosmosMsg = WasmMsg::UpdateAdmin {
+            contract_addr: token.to_string(),
+            admin: bob.to_string(),
+        }
+        .into();
+        let clear_admin: CosmosMsg = WasmMsg::ClearAdmin {
+            contract_addr: token.to_string(),
+        }
+        .into();
+        assert!(!can(deps.as_ref(), &migrate));
+        assert!(!can(deps.as_ref(), &update_admin));
+        assert!(!can(deps.as_ref(), &clear_admin));
+
+        // alice has no policy and can relay anything
+        let res = query_can_execute(deps.as_ref(), alice.to_string(), send.clone()).unwrap();
+        assert!(res.can_execute);
+
+        // execute applies the same checks to every message
+        let msg = ExecuteMsg::Execute {
+            msgs: vec![transfer.clone(), send.clone()],
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &[]), msg)
+            .unwrap_err();
+        assert_eq!(err, ContractError::MessageNotAllowed {});
+        let msg = ExecuteMsg::Execute {
+            msgs: vec![transfer.clone()],
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &[]), msg).unwrap();
+        assert_eq!(res.messages, vec![SubMsg::new(transfer)]);
+
+        // lifting the policy allows everything again
+        let msg = ExecuteMsg::SetExecutePolicy {
+            addr: bob.to_string(),
+            policy: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+        assert!(can(deps.as_ref(), &send));
+    }
+
+    #[test]
+    fn threshold_approvals() {
+        let mut deps = mock_dependencies();
+
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+        let carl = deps.api.addr_make("carl");
+        let dave = deps.api.addr_make("dave");
+
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![alice.to_string(), bob.to_string(), carl.to_string()],
+            mutable: true,
+        };
+        let info = mock_info(alice.as_str(), &[]);
+        instantiate(deps.as_mut(), mock_env(), info, instantiate_msg).unwrap();
+
+        // threshold cannot exceed the number of admins
+        let msg = ExecuteMsg::SetApprovalConfig {
+            config: Some(ApprovalConfig {
+                threshold: 4,
+                expiry: Duration::Height(10),
+            }),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg)
+            .unwrap_err();
+        assert_eq!(err, ContractError::InvalidThreshold {});
+        let config = ApprovalConfig {
+            threshold: 2,
+            expiry: Duration::Height(10),
+        };
+        let msg = ExecuteMsg::SetApprovalConfig {
+            config: Some(config.clone()),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+        assert_eq!(query_approval_config(deps.as_ref()).unwrap().config, Some(config));
+
+        // nor can the admin set shrink below it
+        let msg = ExecuteMsg::UpdateAdmins {
+            admins: vec![alice.to_string()],
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg)
+            .unwrap_err();
+        assert_eq!(err, ContractError::InvalidThreshold {});
+
+        let send: CosmosMsg = BankMsg::Send {
+            to_address: dave.to_string(),
+            amount: coins(10000, "DAI"),
+        }
+        .into();
+        let res = query_can_execute(deps.as_ref(), alice.to_string(), send.clone()).unwrap();
+        assert!(!res.can_execute);
+
+        // execute only proposes the messages
+        let msg = ExecuteMsg::Execute {
+            msgs: vec![send.clone()],
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg.clone())
+            .unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(res.attributes[1], ("execution_id", "1"));
+        let pending = query_pending_execution(deps.as_ref(), 1).unwrap();
+        assert_eq!(pending.approvals, vec![alice.clone()]);
+
+        // executors outside the admin set can neither propose nor approve
+        let grant = ExecuteMsg::GrantRole {
+            role: Role::ProxyExecutor,
+            addr: dave.to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), grant).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), mock_info(dave.as_str(), &[]), msg.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // only admins approve, once
+        let approve = ExecuteMsg::ApproveExecution { id: 1 };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(dave.as_str(), &[]), approve.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), approve.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::AlreadyApproved {});
+
+        // the second approval relays the messages
+        let res = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &[]), approve)
+            .unwrap();
+        assert_eq!(res.messages, vec![SubMsg::new(send)]);
+        assert!(query_pending_execution(deps.as_ref(), 1).is_err());
+
+        // approvals must come before the expiry
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg.clone())
+            .unwrap();
+        let list = query_pending_executions(deps.as_ref(), None, None).unwrap();
+        assert_eq!(list.executions.len(), 1);
+        let mut env = mock_env();
+        env.block.height += 10;
+        let approve = ExecuteMsg::ApproveExecution { id: 2 };
+        let err = execute(deps.as_mut(), env.clone(), mock_info(carl.as_str(), &[]), approve)
+            .unwrap_err();
+        assert_eq!(err, ContractError::ExecutionExpired {});
+
+        // and anyone can prune the expired ones, leaving the open ones
+        let config = ApprovalConfig {
+            threshold: 3,
+            expiry: Duration::Height(10),
+        };
+        let set_config = ExecuteMsg::SetApprovalConfig {
+            config: Some(config),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(alice.as_str(), &[]), set_config).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info(alice.as_str(), &[]), msg).unwrap();
+        let prune = |start_after, limit| ExecuteMsg::PruneExpiredExecutions { start_after, limit };
+        let info = mock_info(dave.as_str(), &[]);
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), prune(Some(2), Some(1))).unwrap();
+        assert_eq!(res.attributes[1], ("pruned", "0"));
+        let res = execute(deps.as_mut(), env.clone(), info, prune(None, None)).unwrap();
+        assert_eq!(res.attributes[1], ("pruned", "1"));
+        let list = query_pending_executions(deps.as_ref(), None, None).unwrap();
+        let ids: Vec<_> = list.executions.iter().map(|pending| pending.id).collect();
+        assert_eq!(ids, vec![3]);
+
+        // proposals keep their threshold when the approval mode is turned off
+        assert_eq!(query_pending_execution(deps.as_ref(), 3).unwrap().threshold, 3);
+        let approve_3 = || ExecuteMsg::ApproveExecution { id: 3 };
+        let msg = ExecuteMsg::SetApprovalConfig { config: None };
+        execute(deps.as_mut(), env.clone(), mock_info(alice.as_str(), &[]), msg).unwrap();
+        let res = execute(deps.as_mut(), env.clone(), mock_info(bob.as_str(), &[]), approve_3())
+            .unwrap();
+        assert!(res.messages.is_empty());
+        let res = execute(deps.as_mut(), env, mock_info(carl.as_str(), &[]), approve_3()).unwrap();
+        assert_eq!(res.messages.len(), 1);
+    }
+
+    #[test]
+    fn audit_log_records_executions() {
+        let mut deps = mock_dependencies();
+
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+        let other = deps.api.addr_make("other");
+
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![alice.to_string(), bob.to_string()],
+            mutable: true,
+        };
+        let info = mock_info(alice.as_str(), &[]);
+        instantiate(deps.as_mut(), mock_env(), info, instantiate_msg).unwrap();
+
+        let send: CosmosMsg = BankMsg::Send {
+            to_address: other.to_string(),
+            amount: coins(100, "ushell"),
+        }
+        .into();
+        let delegate: CosmosMsg = StakingMsg::Delegate {
+            validator: "validator".to_string(),
+            amount: coin(50, "ushell"),
+        }
+        .into();
+        let execute_msg = ExecuteMsg::Execute {
+            msgs: vec![send.clone(), delegate, send],
+        };
+
+        // nothing is recorded until enabled
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), execute_msg.clone())
+            .unwrap();
+        let res = query_execution_history(deps.as_ref(), None, None, None).unwrap();
+        assert!(res.executions.is_empty());
+
+        let msg = ExecuteMsg::SetAuditConfig {
+            config: Some(AuditConfig { retention: 0 }),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg)
+            .unwrap_err();
+        assert_eq!(err, ContractError::ZeroRetention {});
+        let msg = ExecuteMsg::SetAuditConfig {
+            config: Some(AuditConfig { retention: 2 }),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(other.as_str(), &[]), msg.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+
+        for sender in [&alice, &bob, &alice] {
+            execute(deps.as_mut(), mock_env(), mock_info(sender.as_str(), &[]), execute_msg.clone())
+                .unwrap();
+        }
+
+        // only the latest two records are kept
+        let res = query_execution_history(deps.as_ref(), None, None, None).unwrap();
+        assert_eq!(
+            res.executions,
+            vec![
+                ExecutionRecord {
+                    id: 2,
+                    sender: bob.clone(),
+                    height: mock_env().block.height,
+                    msg_types: vec![MsgType::Bank, MsgType::Staking, MsgType::Bank],
+                    spent: coins(200, "ushell"),
+                },
+                ExecutionRecord {
+                    id: 3,
+                    sender: alice.clone(),
+                    height: mock_env().block.height,
+                    msg_types: vec![MsgType::Bank, MsgType::Staking, MsgType::Bank],
+                    spent: coins(200, "ushell"),
+                },
+            ]
+        );
+
+        // filtered by sender and paginated
+        let res = query_execution_history(deps.as_ref(), Some(alice.to_string()), None, None)
+            .unwrap();
+        assert_eq!(res.executions.iter().map(|r| r.id).collect::<Vec<_>>(), vec![3]);
+        let res = query_execution_history(deps.as_ref(), None, Some(2), Some(1)).unwrap();
+        assert_eq!(res.executions.iter().map(|r| r.id).collect::<Vec<_>>(), vec![3]);
+        let res = query_audit_config(deps.as_ref()).unwrap();
+        assert_eq!(res.config, Some(AuditConfig { retention: 2 }));
+
+        // lowering the retention a lot drops the old records over several executions
+        let msg = ExecuteMsg::SetAuditConfig {
+            config: Some(AuditConfig { retention: 20 }),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+        for _ in 0..15 {
+            execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &[]), execute_msg.clone())
+                .unwrap();
+        }
+        let msg = ExecuteMsg::SetAuditConfig {
+            config: Some(AuditConfig { retention: 1 }),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+        let ids = |deps: Deps| {
+            query_execution_history(deps, None, None, Some(30))
+                .unwrap()
+                .executions
+                .iter()
+                .map(|r| r.id)
+                .collect::<Vec<_>>()
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &[]), execute_msg.clone())
+            .unwrap();
+        assert_eq!(ids(deps.as_ref()), (2 + MAX_PRUNED_RECORDS as u64..=19).collect::<Vec<_>>());
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &[]), execute_msg).unwrap();
+        assert_eq!(ids(deps.as_ref()), vec![20]);
+    }
+
+    fn mock_resource(id: &str, owner: Addr) -> Resource {
+        let attr = ResourceAttr::new(
+            ResourceDetails::new(4, 2),
+            ResourceDetails::new(8, 1),
+            ResourceDetails::new(100, 0),
+            Region::Singapore,
+            NAT::FullCone,
+        );
+        Resource::new(id.to_string(), owner, 10, attr, Status::Unused)
+    }
+
+    // instantiates the contract with alice as admin and adds one resource per id, owned by alice
+    fn setup_marketplace(deps: DepsMut, ids: &[&str]) -> Vec<Resource> {
+        let alice = MockApi::default().addr_make("alice");
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![alice.to_string()],
+            mutable: false,
+        };
+        let info = mock_info(alice.as_str(), &[]);
+        instantiate(deps, mock_env(), info, instantiate_msg).unwrap();
+
+        ids.iter().map(|id| mock_resource(id, alice.clone())).collect()
+    }
+
+    #[test]
+    fn batch_orders_are_atomic() {
+        let mut deps = mock_dependencies();
+        let resources = setup_marketplace(deps.as_mut(), &["m1", "m2", "m3"]);
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+
+        let msg = ExecuteMsg::AddResources {
+            resources: resources.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+
+        // m3 is rented on its own first
+        let price = resources[0].get_resource_price();
+        let msg = ExecuteMsg::CreateOrder {
+            resource_id: "m3".to_string(),
+            duration: 10,
+        };
+        let funds = coins(price * 10, DEFAULT_DENOM);
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap();
+
+        // empty or duplicated groups are rejected
+        let msg = ExecuteMsg::CreateOrderBatch {
+            resource_ids: vec![],
+            duration: 10,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap_err();
+        assert_eq!(err, ContractError::EmptyOrderGroup);
+        let msg = ExecuteMsg::CreateOrderBatch {
+            resource_ids: vec!["m1".to_string(), "m1".to_string()],
+            duration: 10,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::DuplicateResource {
+                resource_id: "m1".to_string()
+            }
+        );
+
+        // so a batch including it fails as a whole
+        let funds = coins(price * 30, DEFAULT_DENOM);
+        let msg = ExecuteMsg::CreateOrderBatch {
+            resource_ids: vec!["m1".to_string(), "m2".to_string(), "m3".to_string()],
+            duration: 10,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap_err();
+        assert_eq!(err, ContractError::OtherError);
+        let free = query_resources(deps.as_ref(), vec!["m1".to_string(), "m2".to_string()]).unwrap();
+        assert!(free.iter().all(|r| r.check_status(Status::Unused)));
+
+        // paying for one machine is not enough for two
+        let msg = ExecuteMsg::CreateOrderBatch {
+            resource_ids: vec!["m1".to_string(), "m2".to_string()],
+            duration: 10,
+        };
+        let short = coins(price * 10, DEFAULT_DENOM);
+        let err = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &short), msg.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::OtherError);
+
+        // a single payment reserves both
+        let res = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap();
+        assert_eq!(res.attributes[1], ("group_id", "1"));
+        let group = query_order_group(deps.as_ref(), 1).unwrap();
+        assert_eq!(group.initiator, bob.to_string());
+        assert_eq!(group.orders.len(), 2);
+        assert!(group.orders.iter().all(|o| o.group_id == Some(1)));
+        let used = query_resources(deps.as_ref(), vec!["m1".to_string(), "m2".to_string()]).unwrap();
+        assert!(used.iter().all(|r| r.check_status(Status::Used)));
+
+        // costs and heights beyond the integer range fail instead of panicking
+        let attr = resources[0].get_resource_attr();
+        let price_max = u128::MAX / 2;
+        let pricey = Resource::new("m4".to_string(), alice.clone(), price_max, attr, Status::Unused);
+        let msg = ExecuteMsg::AddResources {
+            resources: vec![pricey, mock_resource("m5", alice.clone())],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+        let msg = ExecuteMsg::CreateOrderBatch {
+            resource_ids: vec!["m4".to_string()],
+            duration: 10,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::Overflow { .. })));
+        let msg = ExecuteMsg::CreateOrderBatch {
+            resource_ids: vec!["m5".to_string()],
+            duration: u64::MAX,
+        };
+        let funds = coins(price * u64::MAX as u128, DEFAULT_DENOM);
+        let err = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::Overflow { .. })));
+
+        // single orders created in the same block get their own ids
+        let msg = ExecuteMsg::CreateOrder {
+            resource_id: "m5".to_string(),
+            duration: 10,
+        };
+        let funds = coins(price * 10, DEFAULT_DENOM);
+        let res = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap();
+        assert_eq!(res.attributes[1], ("order_id", "2"));
+        assert_eq!(ORDER_MAP.load(&deps.storage, "1".to_string()).unwrap().resource_id, "m3");
+    }
+
+    #[test]
+    fn order_groups_extend_cancel_and_settle_together() {
+        let mut deps = mock_dependencies();
+        let resources = setup_marketplace(deps.as_mut(), &["m1", "m2"]);
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+
+        let msg = ExecuteMsg::AddResources {
+            resources: resources.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+
+        let price = resources[0].get_resource_price();
+        let ids = vec!["m1".to_string(), "m2".to_string()];
+        let msg = ExecuteMsg::CreateOrderBatch {
+            resource_ids: ids.clone(),
+            duration: 10,
+        };
+        let funds = coins(price * 20, DEFAULT_DENOM);
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap();
+
+        // only the initiator can extend, paying for every order
+        let msg = ExecuteMsg::ExtendOrderGroup {
+            group_id: 1,
+            duration: 10,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &funds), msg.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap();
+        let group = query_order_group(deps.as_ref(), 1).unwrap();
+        for order in group.orders {
+            assert_eq!(order.end_height, mock_env().block.height + 20);
+            assert_eq!(order.locked_funds, price * 20);
+        }
+
+        // the group cannot be settled before every order expired
+        let mut env = mock_env();
+        env.block.height += 10;
+        let msg = ExecuteMsg::EndOrderGroup { group_id: 1 };
+        let err = execute(deps.as_mut(), env.clone(), mock_info(bob.as_str(), &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::OtherError);
+
+        // cancelling half way pays the provider for the used half and refunds the rest
+        let msg = ExecuteMsg::CancelOrderGroup { group_id: 1 };
+        let res = execute(deps.as_mut(), env, mock_info(bob.as_str(), &[]), msg).unwrap();
+        assert_eq!(
+            res.messages[..3],
+            [
+                SubMsg::new(BankMsg::Send {
+                    to_address: alice.to_string(),
+                    amount: coins(price * 10, DEFAULT_DENOM),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: alice.to_string(),
+                    amount: coins(price * 10, DEFAULT_DENOM),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: bob.to_string(),
+                    amount: coins(price * 20, DEFAULT_DENOM),
+                }),
+            ]
+        );
+        let group = query_order_group(deps.as_ref(), 1).unwrap();
+        assert!(group.orders.iter().all(|o| o.status == OrderStatus::Terminated));
+        let free = query_resources(deps.as_ref(), ids.clone()).unwrap();
+        assert!(free.iter().all(|r| r.check_status(Status::Unused)));
+
+        // a second group settles together once expired
+        let msg = ExecuteMsg::CreateOrderBatch {
+            resource_ids: ids,
+            duration: 10,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap();
+        let mut env = mock_env();
+        env.block.height += 10;
+        let msg = ExecuteMsg::EndOrderGroup { group_id: 2 };
+        let res = execute(deps.as_mut(), env, mock_info(alice.as_str(), &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 2);
+        let group = query_order_group(deps.as_ref(), 2).unwrap();
+        assert!(group.orders.iter().all(|o| o.status == OrderStatus::Expired));
+    }
+
+    #[test]
+    fn order_groups_skip_orders_handled_alone() {
+        let mut deps = mock_dependencies();
+        let resources = setup_marketplace(deps.as_mut(), &["m1", "m2", "m3"]);
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+        let keeper = deps.api.addr_make("keeper");
+
+        let msg = ExecuteMsg::AddResources {
+            resources: resources.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+
+        let price = resources[0].get_resource_price();
+        let msg = ExecuteMsg::CreateOrderBatch {
+            resource_ids: vec!["m1".to_string(), "m2".to_string()],
+            duration: 10,
+        };
+        let funds = coins(price * 20, DEFAULT_DENOM);
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap();
+
+        // one order of the group is disputed on its own
+        let msg = ExecuteMsg::HandleException {
+            order_id: "1-0".to_string(),
+            action: HandleAction::MarkAsException,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+
+        // the rest of the group is still extended and cancelled together
+        let msg = ExecuteMsg::ExtendOrderGroup {
+            group_id: 1,
+            duration: 10,
+        };
+        let funds = coins(price * 10, DEFAULT_DENOM);
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap();
+        let mut env = mock_env();
+        env.block.height += 10;
+        let msg = ExecuteMsg::CancelOrderGroup { group_id: 1 };
+        let res = execute(deps.as_mut(), env.clone(), mock_info(bob.as_str(), &[]), msg.clone())
+            .unwrap();
+        assert_eq!(
+            res.messages[..2],
+            [
+                SubMsg::new(BankMsg::Send {
+                    to_address: alice.to_string(),
+                    amount: coins(price * 10, DEFAULT_DENOM),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: bob.to_string(),
+                    amount: coins(price * 10, DEFAULT_DENOM),
+                }),
+            ]
+        );
+        let group = query_order_group(deps.as_ref(), 1).unwrap();
+        assert_eq!(group.orders[0].status, OrderStatus::Exception);
+        assert_eq!(group.orders[1].status, OrderStatus::Terminated);
+        let err = execute(deps.as_mut(), env, mock_info(bob.as_str(), &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::NoActiveOrders { group_id: 1 });
+
+        // an order settled by a keeper is left out when the group ends
+        let msg = ExecuteMsg::CreateOrderBatch {
+            resource_ids: vec!["m2".to_string(), "m3".to_string()],
+            duration: 10,
+        };
+        let funds = coins(price * 20, DEFAULT_DENOM);
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap();
+        let mut env = mock_env();
+        env.block.height += 10;
+        let msg = ExecuteMsg::SettleExpired { limit: Some(1) };
+        execute(deps.as_mut(), env.clone(), mock_info(keeper.as_str(), &[]), msg).unwrap();
+        let msg = ExecuteMsg::EndOrderGroup { group_id: 2 };
+        let res = execute(deps.as_mut(), env, mock_info(bob.as_str(), &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        let group = query_order_group(deps.as_ref(), 2).unwrap();
+        assert!(group.orders.iter().all(|o| o.status == OrderStatus::Expired));
+    }
+
+    #[test]
+    fn keepers_settle_expired_orders() {
+        let mut deps = mock_dependencies();
+        let resources = setup_marketplace(deps.as_mut(), &["m1", "m2", "m3"]);
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+        let keeper = deps.api.addr_make("keeper");
+
+        let msg = ExecuteMsg::AddResources {
+            resources: resources.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+
+        // only fee managers can set the fees, and they must stay small
+        let msg = ExecuteMsg::SetMarketplaceFee {
+            fee: Decimal::percent(20),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &[]), msg.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::MarketplaceFeeTooHigh {
+                max: Decimal::percent(10)
+            }
+        );
+        let msg = ExecuteMsg::SetKeeperFee {
+            fee: Decimal::percent(60),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &[]), msg.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::KeeperFeeTooHigh {
+                max: Decimal::percent(50)
+            }
+        );
+
+        // the marketplace keeps 10% of provider payments and hands 10% of that to keepers
+        let msg = ExecuteMsg::SetMarketplaceFee {
+            fee: Decimal::percent(10),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+        let msg = ExecuteMsg::SetKeeperFee {
+            fee: Decimal::percent(10),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+        assert_eq!(
+            query_marketplace_fee(deps.as_ref()).unwrap().fee,
+            Decimal::percent(10)
+        );
+        assert_eq!(
+            query_keeper_fee(deps.as_ref()).unwrap().fee,
+            Decimal::percent(10)
+        );
+
+        // m3 expires later than m1 and m2, which are rented in different blocks
+        let price = resources[0].get_resource_price();
+        for (id, delay, duration) in [("m3", 0, 100), ("m1", 1, 10), ("m2", 2, 10)] {
+            let mut env = mock_env();
+            env.block.height += delay;
+            let msg = ExecuteMsg::CreateOrder {
+                resource_id: id.to_string(),
+                duration,
+            };
+            let funds = coins(price * duration as u128, DEFAULT_DENOM);
+            execute(deps.as_mut(), env, mock_info(bob.as_str(), &funds), msg).unwrap();
+        }
+
+        // nothing to settle yet
+        let msg = ExecuteMsg::SettleExpired { limit: Some(1) };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(keeper.as_str(), &[]), msg.clone())
+            .unwrap();
+        assert_eq!(res.messages, vec![]);
+        assert_eq!(res.attributes[1], ("settled", "0"));
+
+        // the first expired order is settled first, minus the marketplace fee, part of which
+        // goes to the keeper
+        let mut env = mock_env();
+        env.block.height += 20;
+        let res = execute(deps.as_mut(), env.clone(), mock_info(keeper.as_str(), &[]), msg).unwrap();
+        let fee = price * 10 / 10;
+        let reward = fee / 10;
+        assert_eq!(
+            res.messages[..2],
+            [
+                SubMsg::new(BankMsg::Send {
+                    to_address: alice.to_string(),
+                    amount: coins(price * 10 - fee, DEFAULT_DENOM),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: keeper.to_string(),
+                    amount: coins(reward, DEFAULT_DENOM),
+                }),
+            ]
+        );
+        // orders are numbered in creation order
+        let order_id = "2".to_string();
+        let order = ORDER_MAP.load(&deps.storage, order_id).unwrap();
+        assert_eq!(order.status, OrderStatus::Expired);
+
+        // then the remaining expired one, leaving m3 active
+        let msg = ExecuteMsg::SettleExpired { limit: None };
+        let res = execute(deps.as_mut(), env, mock_info(keeper.as_str(), &[]), msg).unwrap();
+        assert_eq!(res.attributes[1], ("settled", "1"));
+        let rented = query_resources(deps.as_ref(), vec!["m1".to_string(), "m2".to_string(), "m3".to_string()]).unwrap();
+        let statuses: Vec<_> = rented.iter().map(|r| r.get_status()).collect();
+        assert_eq!(statuses, vec![Status::Unused, Status::Unused, Status::Used]);
+    }
+
+    #[test]
+    fn market_stats_track_resources_and_orders() {
+        let mut deps = mock_dependencies();
+        let mut resources = setup_marketplace(deps.as_mut(), &["m1", "m2"]);
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+
+        // m2 is in another region
+        let attr = ResourceAttr::new(
+            ResourceDetails::new(4, 2),
+            ResourceDetails::new(8, 1),
+            ResourceDetails::new(100, 0),
+            Region::Other("Tokyo".to_string()),
+            NAT::FullCone,
+        );
+        resources[1] = Resource::new("m2".to_string(), alice.clone(), 10, attr, Status::Unused);
+        let msg = ExecuteMsg::AddResources {
+            resources: resources.clone(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+
+        let price = resources[0].get_resource_price();
+        let msg = ExecuteMsg::CreateOrder {
+            resource_id: "m1".to_string(),
+            duration: 10,
+        };
+        let funds = coins(price * 10, DEFAULT_DENOM);
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap();
+
+        let stats = query_market_stats(deps.as_ref()).unwrap();
+        assert_eq!(
+            stats.resources_by_status,
+            vec![
+                StatCount {
+                    key: "Unused".to_string(),
+                    count: 1
+                },
+                StatCount {
+                    key: "Used".to_string(),
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(
+            stats.resources_by_region,
+            vec![
+                StatCount {
+                    key: "Singapore".to_string(),
+                    count: 1
+                },
+                StatCount {
+                    key: "Tokyo".to_string(),
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(
+            stats.cpu,
+            CapacityResponse {
+                total: Uint128::new(8),
+                rented: Uint128::new(4),
+                utilization: Decimal::percent(50),
+            }
+        );
+        assert_eq!(stats.active_orders, 1);
+        assert_eq!(stats.escrowed, Uint128::new(price * 10));
+
+        // funds of orders in dispute stay escrowed
+        let order_id = "1".to_string();
+        for action in [HandleAction::MarkAsException, HandleAction::MarkAsNormal] {
+            let msg = ExecuteMsg::HandleException {
+                order_id: order_id.clone(),
+                action: action.clone(),
+            };
+            execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+
+            let stats = query_market_stats(deps.as_ref()).unwrap();
+            let active = (action == HandleAction::MarkAsNormal) as u64;
+            assert_eq!(stats.active_orders, active);
+            assert_eq!(stats.escrowed, Uint128::new(price * 10));
+        }
+
+        // settling releases the escrow and records the revenue at the settlement height
+        let mut env = mock_env();
+        env.block.height += 10;
+        let msg = ExecuteMsg::EndOrder {
+            order_id: "1".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(bob.as_str(), &[]), msg).unwrap();
+
+        let stats = query_market_stats(deps.as_ref()).unwrap();
+        assert_eq!(stats.active_orders, 0);
+        assert_eq!(stats.escrowed, Uint128::zero());
+        assert_eq!(stats.cpu.rented, Uint128::zero());
+
+        let revenue = |start, end| {
+            query_provider_revenue(deps.as_ref(), alice.to_string(), start, end)
+                .unwrap()
+                .revenue
+        };
+        assert_eq!(revenue(None, None), Uint128::new(price * 10));
+        assert_eq!(
+            revenue(Some(env.block.height), Some(env.block.height)),
+            Uint128::new(price * 10)
+        );
+        assert_eq!(revenue(None, Some(env.block.height - 1)), Uint128::zero());
+        assert_eq!(revenue(Some(env.block.height + 1), None), Uint128::zero());
+
+        // deleting resources removes them from the stats
+        let msg = ExecuteMsg::DeleteResources {
+            ids: vec!["m1".to_string(), "m2".to_string()],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+        let stats = query_market_stats(deps.as_ref()).unwrap();
+        assert_eq!(stats.resources_by_status, vec![]);
+        assert_eq!(stats.resources_by_region, vec![]);
+        assert_eq!(stats.cpu.total, Uint128::zero());
+    }
+
+    #[test]
+    fn migrate_checks_contract_and_version() {
+        let mut deps = mock_dependencies();
+        setup_marketplace(deps.as_mut(), &[]);
+
+        // same version is fine
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+        // cannot migrate another contract
+        set_contract_version(deps.as_mut().storage, "crates.io:cw1-subkeys", "1.0.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CannotMigrate {
+                previous_contract: "crates.io:cw1-subkeys".to_string()
+            }
+        );
+
+        // nor downgrade
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "99.0.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CannotMigrateVersion {
+                previous_version: "99.0.0".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn migrate_v1_orders_and_resources() {
+        let mut deps = mock_dependencies();
+        let resources = setup_marketplace(deps.as_mut(), &["m1", "m2"]);
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+        let keeper = deps.api.addr_make("keeper");
+
+        // old contracts stored resources and orders without index nor statistics
+        let price = resources[0].get_resource_price();
+        let mut rented = resources[0].clone();
+        rented.set_status(Status::Used);
+        RESOURCE_MAP
+            .save(deps.as_mut().storage, "m1".to_string(), &rented)
+            .unwrap();
+        RESOURCE_MAP
+            .save(deps.as_mut().storage, "m2".to_string(), &resources[1])
+            .unwrap();
+        let order = v1::Order {
+            id: "100".to_string(),
+            resource_id: "m1".to_string(),
+            initiator: bob,
+            start_height: 100,
+            end_height: 110,
+            locked_funds: price * 10,
+            status: OrderStatus::Active,
+        };
+        v1::ORDERS
+            .save(deps.as_mut().storage, order.id.clone(), &order)
+            .unwrap();
+        ROLES.clear(deps.as_mut().storage);
+        V2_LAYOUT.remove(deps.as_mut().storage);
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "1.1.0").unwrap();
+
+        // so keepers cannot find the expired order
+        let mut env = mock_env();
+        env.block.height = 200;
+        let msg = ExecuteMsg::SettleExpired { limit: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info(keeper.as_str(), &[]), msg.clone())
+            .unwrap();
+        assert_eq!(res.attributes[1], ("settled", "0"));
+
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        let version = get_contract_version(&deps.storage).unwrap();
+        assert_eq!(version.version, CONTRACT_VERSION);
+
+        // admins keep the permissions they had before roles existed
+        assert!(has_role(&deps.storage, Role::DisputeArbiter, &alice));
+
+        // statistics reflect the existing state
+        let stats = query_market_stats(deps.as_ref()).unwrap();
+        assert_eq!(stats.active_orders, 1);
+        assert_eq!(stats.escrowed, Uint128::new(price * 10));
+        assert_eq!(stats.cpu.rented, Uint128::new(4));
+
+        // migrating again does not rebuild anything
+        ACTIVE_ORDERS.save(deps.as_mut().storage, &5).unwrap();
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        let stats = query_market_stats(deps.as_ref()).unwrap();
+        assert_eq!(stats.active_orders, 5);
+        ACTIVE_ORDERS.save(deps.as_mut().storage, &1).unwrap();
+
+        // and the old order can be settled
+        let res = execute(deps.as_mut(), env, mock_info(keeper.as_str(), &[]), msg).unwrap();
+        assert_eq!(res.attributes[1], ("settled", "1"));
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(BankMsg::Send {
+                to_address: alice.to_string(),
+                amount: coins(price * 10, DEFAULT_DENOM),
+            })
+        );
+        let order = ORDER_MAP.load(&deps.storage, "100".to_string()).unwrap();
+        assert_eq!(order.status, OrderStatus::Expired);
+        assert_eq!(order.group_id, None);
+    }
+
+    #[test]
+    fn credentials_handoff() {
+        let mut deps = mock_dependencies();
+        let config = CredentialsConfig::default();
+        let max_size = config.max_size as usize;
+
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![alice.to_string()],
+            mutable: false,
+        };
+        let info = mock_info(alice.as_str(), &[]);
+        instantiate(deps.as_mut(), mock_env(), info.clone(), instantiate_msg).unwrap();
+
+        let resource = mock_resource("machine", alice.clone());
+        let msg = ExecuteMsg::AddResources {
+            resources: vec![resource.clone()],
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let order_id = "1".to_string();
+        let funds = coins(resource.get_resource_price() * 500, DEFAULT_DENOM);
+        let msg = ExecuteMsg::CreateOrder {
+            resource_id: "machine".to_string(),
+            duration: 500,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap();
+
+        // provider cannot deliver before the tenant registered a key
+        let credentials = Binary::from(b"encrypted".as_slice());
+        let msg = ExecuteMsg::DeliverCredentials {
+            order_id: order_id.clone(),
+            credentials: credentials.clone(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::PubkeyNotRegistered);
+
+        // only the tenant can register a key
+        let pubkey = Binary::from(b"pubkey".as_slice());
+        let msg = ExecuteMsg::RegisterPubkey {
+            order_id: order_id.clone(),
+            pubkey: pubkey.clone(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &[]), msg).unwrap();
+
+        // only the provider can deliver, and within the size limit
+        let msg = ExecuteMsg::DeliverCredentials {
+            order_id: order_id.clone(),
+            credentials: credentials.clone(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &[]), msg.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let too_large = ExecuteMsg::DeliverCredentials {
+            order_id: order_id.clone(),
+            credentials: Binary::from(vec![0u8; max_size + 1]),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), too_large)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CredentialsTooLarge {
+                size: max_size + 1,
+                max: max_size
+            }
+        );
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), msg).unwrap();
+
+        let res = query_credentials(deps.as_ref(), order_id.clone()).unwrap();
+        assert_eq!(res.pubkey, Some(pubkey));
+        assert_eq!(res.credentials, Some(credentials));
+        assert_eq!(
+            res.deadline,
+            Some(mock_env().block.height + config.delivery_blocks)
+        );
+
+        // delivered credentials can never be flagged
+        let mut env = mock_env();
+        env.block.height += config.delivery_blocks;
+        let msg = ExecuteMsg::FlagUndeliveredCredentials { order_id };
+        let err = execute(deps.as_mut(), env, mock_info(bob.as_str(), &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::OtherError);
+    }
+
+    #[test]
+    fn undelivered_credentials_flag_order() {
+        let mut deps = mock_dependencies();
+        let delivery_blocks = CredentialsConfig::default().delivery_blocks;
+
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+        let carl = deps.api.addr_make("carl");
+
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![alice.to_string()],
+            mutable: false,
+        };
+        let info = mock_info(alice.as_str(), &[]);
+        instantiate(deps.as_mut(), mock_env(), info.clone(), instantiate_msg).unwrap();
+
+        let resource = mock_resource("machine", alice.clone());
+        let msg = ExecuteMsg::AddResources {
+            resources: vec![resource.clone()],
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let order_id = "1".to_string();
+        let funds = coins(resource.get_resource_price() * 500, DEFAULT_DENOM);
+        let msg = ExecuteMsg::CreateOrder {
+            resource_id: "machine".to_string(),
+            duration: 500,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap();
+        let msg = ExecuteMsg::RegisterPubkey {
+            order_id: order_id.clone(),
+            pubkey: Binary::from(b"pubkey".as_slice()),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &[]), msg).unwrap();
+
+        // not overdue yet
+        let msg = ExecuteMsg::FlagUndeliveredCredentials {
+            order_id: order_id.clone(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(carl.as_str(), &[]), msg.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::OtherError);
+
+        // after the deadline the provider can no longer deliver
+        let mut env = mock_env();
+        env.block.height += delivery_blocks;
+        let deliver = ExecuteMsg::DeliverCredentials {
+            order_id: order_id.clone(),
+            credentials: Binary::from(b"encrypted".as_slice()),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info(alice.as_str(), &[]), deliver)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CredentialsDeadlinePassed {
+                deadline: env.block.height
+            }
+        );
+
+        // and anyone can flag the order
+        execute(deps.as_mut(), env, mock_info(carl.as_str(), &[]), msg).unwrap();
+        let order = ORDER_MAP.load(&deps.storage, order_id).unwrap();
+        assert_eq!(order.status, OrderStatus::Exception);
+    }
+
+    #[test]
+    fn credentials_config_and_expiry() {
+        let mut deps = mock_dependencies();
+
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![alice.to_string()],
+            mutable: true,
+        };
+        let info = mock_info(alice.as_str(), &[]);
+        instantiate(deps.as_mut(), mock_env(), info.clone(), instantiate_msg).unwrap();
+
+        // only admins set the limits, which cannot be zero
+        let config = CredentialsConfig {
+            max_size: 4,
+            delivery_blocks: 20,
+        };
+        let msg = ExecuteMsg::SetCredentialsConfig {
+            config: config.clone(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &[]), msg.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let zero = ExecuteMsg::SetCredentialsConfig {
+            config: CredentialsConfig {
+                max_size: 4,
+                delivery_blocks: 0,
+            },
+        };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), zero).unwrap_err();
+        assert_eq!(err, ContractError::InvalidCredentialsConfig {});
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let res = query_credentials_config(deps.as_ref()).unwrap();
+        assert_eq!(res.config, config);
+
+        let resource = mock_resource("machine", alice.clone());
+        let msg = ExecuteMsg::AddResources {
+            resources: vec![resource.clone()],
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let order_id = "1".to_string();
+        let end_height = mock_env().block.height + 50;
+        let funds = coins(resource.get_resource_price() * 50, DEFAULT_DENOM);
+        let msg = ExecuteMsg::CreateOrder {
+            resource_id: "machine".to_string(),
+            duration: 50,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &funds), msg).unwrap();
+
+        // keys cannot be registered once the order expired
+        let register = ExecuteMsg::RegisterPubkey {
+            order_id: order_id.clone(),
+            pubkey: Binary::from(b"pubkey".as_slice()),
+        };
+        let mut env = mock_env();
+        env.block.height = end_height;
+        let err = execute(deps.as_mut(), env.clone(), mock_info(bob.as_str(), &[]), register.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::OrderExpired { end_height });
+
+        // the delivery deadline is capped at the end of the order
+        env.block.height = end_height - 19;
+        execute(deps.as_mut(), env.clone(), mock_info(bob.as_str(), &[]), register).unwrap();
+        let res = query_credentials(deps.as_ref(), order_id.clone()).unwrap();
+        assert_eq!(res.deadline, Some(end_height));
+
+        // the size limit applies to deliveries
+        let deliver = |credentials: &[u8]| ExecuteMsg::DeliverCredentials {
+            order_id: order_id.clone(),
+            credentials: Binary::from(credentials),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info(alice.as_str(), &[]), deliver(b"12345"))
+            .unwrap_err();
+        assert_eq!(err, ContractError::CredentialsTooLarge { size: 5, max: 4 });
+        execute(deps.as_mut(), env.clone(), mock_info(alice.as_str(), &[]), deliver(b"1234"))
+            .unwrap();
+
+        // nothing can be delivered once the order expired
+        env.block.height = end_height;
+        let info = mock_info(alice.as_str(), &[]);
+        let err = execute(deps.as_mut(), env.clone(), info, deliver(b"4321")).unwrap_err();
+        assert_eq!(err, ContractError::OrderExpired { end_height });
+
+        // and the credentials are deleted when the order is settled
+        let msg = ExecuteMsg::SettleExpired { limit: None };
+        execute(deps.as_mut(), env, mock_info(bob.as_str(), &[]), msg).unwrap();
+        let res = query_credentials(deps.as_ref(), order_id).unwrap();
+        assert_eq!(res.credentials, None);
+    }
+
+    #[test]
+    fn order_and_resource_hooks() {
+        let mut deps = mock_dependencies();
+
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+        let hook = deps.api.addr_make("hook").to_string();
+
+        let instantiate_msg = InstantiateMsg {
+            admins: vec![alice.to_string()],
+            mutable: true,
+        };
+        let info = mock_info(alice.as_str(), &[]);
+        instantiate(deps.as_mut(), mock_env(), info, instantiate_msg).unwrap();
+
+        // bob cannot register hooks
+        let msg = ExecuteMsg::AddOrderHook { addr: hook.clone() };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(bob.as_str(), &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // but alice can
+        let info = mock_info(alice.as_str(), &[]);
+        let msg = ExecuteMsg::AddOrderHook { addr: hook.clone() };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::AddResourceHook { addr: hook.clone() };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        // and only once
+        let msg = ExecuteMsg::AddOrderHook { addr: hook.clone() };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, HookError::HookAlreadyRegistered {}.into());
+
+        let hooks = ORDER_HOOKS.query_hooks(deps.as_ref()).unwrap();
+        assert_eq!(hooks.hooks, vec![hook.clone()]);
+
+        // adding a resource informs the resource hook
+        let resource = mock_resource("machine", alice.clone());
+        let msg = ExecuteMsg::AddResources {
+            resources: vec![resource.clone()],
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let expected = ResourceChangedHookMsg::one(ResourceDiff::new(
+            "machine",
+            None,
+            Some(Status::Unused),
+        ));
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(expected.into_cosmos_msg(&hook).unwrap())]
+        );
+
+        // creating an order informs both hooks
+        let env = mock_env();
+        let order_id = "1".to_string();
+        let cost = resource.get_resource_price() * 5;
+        let msg = ExecuteMsg::CreateOrder {
+            resource_id: "machine".to_string(),
+            duration: 5,
+        };
+        let funds = coins(cost, DEFAULT_DENOM);
+        let res = execute(deps.as_mut(), env.clone(), mock_info(bob.as_str(), &funds), msg).unwrap();
+        let order_msg = OrderChangedHookMsg::one(OrderDiff::new(
+            &order_id,
+            "machine",
+            None,
+            Some(OrderStatus::Active),
+        ));
+        let resource_msg = ResourceChangedHookMsg::one(ResourceDiff::new(
+            "machine",
+            Some(Status::Unused),
+            Some(Status::Used),
+        ));
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(order_msg.into_cosmos_msg(&hook).unwrap()),
+                SubMsg::new(resource_msg.into_cosmos_msg(&hook).unwrap()),
+            ]
+        );
+
+        // ending the order pays the owner, then informs the hooks
+        let mut env = mock_env();
+        env.block.height += 5;
+        let msg = ExecuteMsg::EndOrder {
+            order_id: order_id.clone(),
+        };
+        let res = execute(deps.as_mut(), env, mock_info(bob.as_str(), &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 3);
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(BankMsg::Send {
+                to_address: alice.to_string(),
+                amount: funds,
+            })
+        );
+        let order_msg = OrderChangedHookMsg::one(OrderDiff::new(
+            &order_id,
+            "machine",
+            Some(OrderStatus::Active),
+            Some(OrderStatus::Expired),
+        ));
+        assert_eq!(
+            res.messages[1],
+            SubMsg::new(order_msg.into_cosmos_msg(&hook).unwrap())
+        );
+
+        // once removed, the order hook is no longer called
+        let msg = ExecuteMsg::RemoveOrderHook { addr: hook.clone() };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::RemoveOrderHook { addr: hook.clone() };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, HookError::HookNotRegistered {}.into());
+
+        // hooks are fixed once the admin list is frozen
+        execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::Freeze {}).unwrap();
+        let msg = ExecuteMsg::AddOrderHook { addr: hook.clone() };
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let msg = ExecuteMsg::RemoveResourceHook { addr: hook };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
 }
@@ -0,0 +1,209 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage};
+use cw_storage_plus::{Item, Map};
+
+use crate::ContractError;
+use crate::hook::{prepare_order_hooks, OrderDiff};
+use crate::msg::{CredentialsConfigResponse, CredentialsResponse};
+use crate::order::{save_order, ORDER_MAP};
+use crate::order_type::{Order, OrderStatus};
+use crate::resource::RESOURCE_MAP;
+use crate::state::ADMIN_LIST;
+
+/// Limits of the credentials handoff, set by the admins
+#[cw_serde]
+pub struct CredentialsConfig {
+    /// maximum size of the encrypted credentials, in bytes
+    pub max_size: u32,
+    /// blocks the provider has to deliver the credentials once the tenant registered a key
+    pub delivery_blocks: u64,
+}
+
+impl Default for CredentialsConfig {
+    fn default() -> Self {
+        CredentialsConfig {
+            max_size: 2048,
+            delivery_blocks: 100,
+        }
+    }
+}
+
+pub const CREDENTIALS_CONFIG: Item<CredentialsConfig> = Item::new("credentials_config");
+
+// 订单ID -> 使用租户公钥加密的访问凭证
+pub const CREDENTIALS_MAP: Map<String, Binary> = Map::new("credentials");
+
+/// returns true if the tenant registered a public key but the provider did not
+/// deliver the credentials before the deadline
+pub fn credentials_overdue(storage: &dyn Storage, height: u64, order: &Order) -> StdResult<bool> {
+    match order.credentials_deadline {
+        Some(deadline) if height >= deadline => {
+            Ok(!CREDENTIALS_MAP.has(storage, order.id.clone()))
+        }
+        _ => Ok(false),
+    }
+}
+
+// 租户登记用于加密凭证的公钥
+pub fn register_pubkey(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    order_id: String,
+    pubkey: Binary,
+) -> Result<Response, ContractError> {
+    let mut order = ORDER_MAP.load(deps.storage, order_id.clone())?;
+
+    // 只有订单发起者可以登记公钥
+    if order.initiator != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if order.status != OrderStatus::Active {
+        return Err(ContractError::OtherError);
+    }
+
+    if env.block.height >= order.end_height {
+        return Err(ContractError::OrderExpired {
+            end_height: order.end_height,
+        });
+    }
+
+    if order.pubkey.is_some() {
+        return Err(ContractError::AlreadyExists);
+    }
+
+    // 提供者必须在截止高度前交付凭证，截止高度不晚于订单结束
+    let config = CREDENTIALS_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+    let deadline = env
+        .block
+        .height
+        .saturating_add(config.delivery_blocks)
+        .min(order.end_height);
+    order.pubkey = Some(pubkey);
+    order.credentials_deadline = Some(deadline);
+    save_order(deps.storage, &order)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_pubkey")
+        .add_attribute("order_id", order_id)
+        .add_attribute("credentials_deadline", deadline.to_string()))
+}
+
+// 资源提供者交付加密后的访问凭证
+pub fn deliver_credentials(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    order_id: String,
+    credentials: Binary,
+) -> Result<Response, ContractError> {
+    let order = ORDER_MAP.load(deps.storage, order_id.clone())?;
+
+    // 只有资源所有者可以交付凭证
+    let resource = RESOURCE_MAP.load(deps.storage, order.resource_id.clone())?;
+    if resource.get_owner() != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if order.status != OrderStatus::Active {
+        return Err(ContractError::OtherError);
+    }
+
+    if env.block.height >= order.end_height {
+        return Err(ContractError::OrderExpired {
+            end_height: order.end_height,
+        });
+    }
+
+    let deadline = order
+        .credentials_deadline
+        .ok_or(ContractError::PubkeyNotRegistered)?;
+    if env.block.height >= deadline {
+        return Err(ContractError::CredentialsDeadlinePassed { deadline });
+    }
+
+    let max_size = CREDENTIALS_CONFIG
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .max_size as usize;
+    if credentials.len() > max_size {
+        return Err(ContractError::CredentialsTooLarge {
+            size: credentials.len(),
+            max: max_size,
+        });
+    }
+
+    if CREDENTIALS_MAP.has(deps.storage, order_id.clone()) {
+        return Err(ContractError::AlreadyExists);
+    }
+    CREDENTIALS_MAP.save(deps.storage, order_id.clone(), &credentials)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "deliver_credentials")
+        .add_attribute("order_id", order_id))
+}
+
+// 任何人都可以将超时未交付凭证的订单标记为异常
+pub fn flag_undelivered_credentials(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    order_id: String,
+) -> Result<Response, ContractError> {
+    let mut order = ORDER_MAP.load(deps.storage, order_id.clone())?;
+
+    if order.status != OrderStatus::Active
+        || !credentials_overdue(deps.storage, env.block.height, &order)?
+    {
+        return Err(ContractError::OtherError);
+    }
+
+    order.status = OrderStatus::Exception;
+    save_order(deps.storage, &order)?;
+
+    let messages = prepare_order_hooks(
+        deps.storage,
+        vec![OrderDiff::new(&order_id, &order.resource_id, Some(OrderStatus::Active), Some(OrderStatus::Exception))],
+    )?;
+
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attribute("action", "flag_undelivered_credentials")
+        .add_attribute("order_id", order_id))
+}
+
+pub fn set_credentials_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    config: CredentialsConfig,
+) -> Result<Response, ContractError> {
+    let cfg = ADMIN_LIST.load(deps.storage)?;
+    if !cfg.can_modify(info.sender.as_ref()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if config.max_size == 0 || config.delivery_blocks == 0 {
+        return Err(ContractError::InvalidCredentialsConfig {});
+    }
+    CREDENTIALS_CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attribute("action", "set_credentials_config"))
+}
+
+pub fn query_credentials_config(deps: Deps) -> StdResult<CredentialsConfigResponse> {
+    let config = CREDENTIALS_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+    Ok(CredentialsConfigResponse { config })
+}
+
+pub fn query_credentials(deps: Deps, order_id: String) -> StdResult<CredentialsResponse> {
+    let order = ORDER_MAP.load(deps.storage, order_id.clone())?;
+    let credentials = CREDENTIALS_MAP.may_load(deps.storage, order_id.clone())?;
+
+    Ok(CredentialsResponse {
+        order_id,
+        pubkey: order.pubkey,
+        credentials,
+        deadline: order.credentials_deadline,
+    })
+}
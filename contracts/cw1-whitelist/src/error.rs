@@ -1,4 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Decimal, StdError};
+use cw_controllers::HookError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -6,9 +7,36 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Hook(#[from] HookError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Message not allowed by the execute policy")]
+    MessageNotAllowed {},
+
+    #[error("Threshold must be between 1 and the number of admins")]
+    InvalidThreshold {},
+
+    #[error("Pending execution expired")]
+    ExecutionExpired {},
+
+    #[error("Already approved this execution")]
+    AlreadyApproved {},
+
+    #[error("Audit log retention cannot be zero")]
+    ZeroRetention {},
+
+    #[error("Cannot migrate from different contract type: {previous_contract}")]
+    CannotMigrate { previous_contract: String },
+
+    #[error("Cannot migrate from newer version: {previous_version}")]
+    CannotMigrateVersion { previous_version: String },
+
+    #[error("Semver parsing error: {0}")]
+    SemVer(String),
+
     // 自定义的资源错误
     #[error("Insufficient resources: requested {requested}, but only {available} available.")]
     InsufficientResources { requested: u64, available: u64 },
@@ -30,4 +58,40 @@ pub enum ContractError {
 
     #[error("Already Exists")]
     AlreadyExists,
+
+    #[error("Order group must contain at least one resource")]
+    EmptyOrderGroup,
+
+    #[error("Order group {group_id} has no active orders")]
+    NoActiveOrders { group_id: u64 },
+
+    #[error("Resource {resource_id} appears more than once in the order group")]
+    DuplicateResource { resource_id: String },
+
+    #[error("Marketplace fee cannot exceed {max}")]
+    MarketplaceFeeTooHigh { max: Decimal },
+
+    #[error("Keeper fee cannot exceed {max}")]
+    KeeperFeeTooHigh { max: Decimal },
+
+    #[error("No public key registered for this order")]
+    PubkeyNotRegistered,
+
+    #[error("Credentials delivery deadline passed at height {deadline}")]
+    CredentialsDeadlinePassed { deadline: u64 },
+
+    #[error("Credentials too large: {size} bytes, maximum is {max}")]
+    CredentialsTooLarge { size: usize, max: usize },
+
+    #[error("Credentials size limit and delivery period must not be zero")]
+    InvalidCredentialsConfig {},
+
+    #[error("Order expired at height {end_height}")]
+    OrderExpired { end_height: u64 },
+}
+
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        Self::SemVer(err.to_string())
+    }
 }
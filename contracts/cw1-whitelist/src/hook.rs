@@ -0,0 +1,163 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{to_json_binary, Binary, CosmosMsg, StdResult, Storage, SubMsg, WasmMsg};
+
+use crate::order_type::OrderStatus;
+use crate::resource_type::Status;
+use crate::state::{ORDER_HOOKS, RESOURCE_HOOKS};
+
+/// OrderDiff shows the old and new status of a given order
+/// They cannot both be None.
+/// old = None, new = Some -> Created
+/// old = Some, new = Some -> Status changed
+#[cw_serde]
+pub struct OrderDiff {
+    pub order_id: String,
+    pub resource_id: String,
+    pub old: Option<OrderStatus>,
+    pub new: Option<OrderStatus>,
+}
+
+impl OrderDiff {
+    pub fn new<T: Into<String>, R: Into<String>>(
+        order_id: T,
+        resource_id: R,
+        old: Option<OrderStatus>,
+        new: Option<OrderStatus>,
+    ) -> Self {
+        OrderDiff {
+            order_id: order_id.into(),
+            resource_id: resource_id.into(),
+            old,
+            new,
+        }
+    }
+}
+
+/// OrderChangedHookMsg should be de/serialized under `OrderChangedHook()` variant in a ExecuteMsg.
+/// This contains a list of all order diffs on the given transaction.
+#[cw_serde]
+pub struct OrderChangedHookMsg {
+    pub diffs: Vec<OrderDiff>,
+}
+
+impl OrderChangedHookMsg {
+    pub fn one(diff: OrderDiff) -> Self {
+        OrderChangedHookMsg { diffs: vec![diff] }
+    }
+
+    pub fn new(diffs: Vec<OrderDiff>) -> Self {
+        OrderChangedHookMsg { diffs }
+    }
+
+    /// serializes the message
+    pub fn into_json_binary(self) -> StdResult<Binary> {
+        let msg = OrderChangedExecuteMsg::OrderChangedHook(self);
+        to_json_binary(&msg)
+    }
+
+    /// creates a cosmos_msg sending this struct to the named contract
+    pub fn into_cosmos_msg<T: Into<String>>(self, contract_addr: T) -> StdResult<CosmosMsg> {
+        let msg = self.into_json_binary()?;
+        let execute = WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            msg,
+            funds: vec![],
+        };
+        Ok(execute.into())
+    }
+}
+
+// This is just a helper to properly serialize the above message
+#[cw_serde]
+enum OrderChangedExecuteMsg {
+    OrderChangedHook(OrderChangedHookMsg),
+}
+
+/// ResourceDiff shows the old and new status of a given resource
+/// They cannot both be None.
+/// old = None, new = Some -> Added
+/// old = Some, new = Some -> Updated
+/// old = Some, new = None -> Deleted
+#[cw_serde]
+pub struct ResourceDiff {
+    pub resource_id: String,
+    pub old: Option<Status>,
+    pub new: Option<Status>,
+}
+
+impl ResourceDiff {
+    pub fn new<T: Into<String>>(resource_id: T, old: Option<Status>, new: Option<Status>) -> Self {
+        ResourceDiff {
+            resource_id: resource_id.into(),
+            old,
+            new,
+        }
+    }
+}
+
+/// ResourceChangedHookMsg should be de/serialized under `ResourceChangedHook()` variant in a ExecuteMsg.
+/// This contains a list of all resource diffs on the given transaction.
+#[cw_serde]
+pub struct ResourceChangedHookMsg {
+    pub diffs: Vec<ResourceDiff>,
+}
+
+impl ResourceChangedHookMsg {
+    pub fn one(diff: ResourceDiff) -> Self {
+        ResourceChangedHookMsg { diffs: vec![diff] }
+    }
+
+    pub fn new(diffs: Vec<ResourceDiff>) -> Self {
+        ResourceChangedHookMsg { diffs }
+    }
+
+    /// serializes the message
+    pub fn into_json_binary(self) -> StdResult<Binary> {
+        let msg = ResourceChangedExecuteMsg::ResourceChangedHook(self);
+        to_json_binary(&msg)
+    }
+
+    /// creates a cosmos_msg sending this struct to the named contract
+    pub fn into_cosmos_msg<T: Into<String>>(self, contract_addr: T) -> StdResult<CosmosMsg> {
+        let msg = self.into_json_binary()?;
+        let execute = WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            msg,
+            funds: vec![],
+        };
+        Ok(execute.into())
+    }
+}
+
+// This is just a helper to properly serialize the above message
+#[cw_serde]
+enum ResourceChangedExecuteMsg {
+    ResourceChangedHook(ResourceChangedHookMsg),
+}
+
+/// builds one `OrderChangedHook` message for every registered order hook
+pub fn prepare_order_hooks(storage: &dyn Storage, diffs: Vec<OrderDiff>) -> StdResult<Vec<SubMsg>> {
+    if diffs.is_empty() {
+        return Ok(vec![]);
+    }
+    ORDER_HOOKS.prepare_hooks(storage, |h| {
+        OrderChangedHookMsg::new(diffs.clone())
+            .into_cosmos_msg(h)
+            .map(SubMsg::new)
+    })
+}
+
+/// builds one `ResourceChangedHook` message for every registered resource hook
+pub fn prepare_resource_hooks(
+    storage: &dyn Storage,
+    diffs: Vec<ResourceDiff>,
+) -> StdResult<Vec<SubMsg>> {
+    if diffs.is_empty() {
+        return Ok(vec![]);
+    }
+    RESOURCE_HOOKS.prepare_hooks(storage, |h| {
+        ResourceChangedHookMsg::new(diffs.clone())
+            .into_cosmos_msg(h)
+            .map(SubMsg::new)
+    })
+}
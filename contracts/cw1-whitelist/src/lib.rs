@@ -18,16 +18,25 @@ For more information on this contract, please check out the
 [README](https://github.com/CosmWasm/cw-plus/blob/main/contracts/cw1-whitelist/README.md).
 */
 
+pub mod approvals;
+pub mod audit;
 pub mod contract;
 pub mod error;
+pub mod hook;
+pub mod migrations;
 #[cfg(test)]
 mod integration_tests;
 pub mod msg;
+pub mod policy;
+pub mod roles;
 pub mod state;
 
 pub mod resource_type;
 pub mod resource;
 pub mod order_type;
 pub mod order;
+pub mod order_group;
+pub mod credentials;
+pub mod stats;
 
 pub use crate::error::ContractError;
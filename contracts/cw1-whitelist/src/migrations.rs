@@ -0,0 +1,81 @@
+use cw_storage_plus::Item;
+
+// set once the storage uses the v2 layout, at instantiation or after the v1 migration
+pub const V2_LAYOUT: Item<bool> = Item::new("v2_layout");
+
+// v1 format is anything up to 2.0.0, when orders were not indexed by expiry
+// and no marketplace statistics were kept
+pub mod v1 {
+    use cosmwasm_std::{Order as IterOrder, StdResult, Storage};
+
+    use crate::order::ORDER_MAP;
+    use crate::resource::RESOURCE_MAP;
+    use crate::roles::{grant_all_roles, ROLES};
+    use crate::state::ADMIN_LIST;
+    use crate::stats::{
+        update_order_stats, update_resource_stats, ACTIVE_ORDERS, CAPACITY, ESCROWED,
+        RESOURCES_BY_REGION, RESOURCES_BY_STATUS,
+    };
+
+    // old orders are only written by tests, the live layout is `ORDER_MAP`
+    #[cfg(test)]
+    #[cosmwasm_schema::cw_serde]
+    pub struct Order {
+        pub id: String,
+        pub resource_id: String,
+        pub initiator: cosmwasm_std::Addr,
+        pub start_height: u64,
+        pub end_height: u64,
+        pub locked_funds: u128,
+        pub status: crate::order_type::OrderStatus,
+    }
+
+    #[cfg(test)]
+    pub const ORDERS: cw_storage_plus::Map<String, Order> = cw_storage_plus::Map::new("orders");
+
+    /// Re-saves every order so the expiry index gets populated, then recomputes
+    /// the marketplace statistics from scratch. Provider revenue history cannot be
+    /// recovered and starts empty.
+    pub fn rebuild_indexes_and_stats(storage: &mut dyn Storage) -> StdResult<()> {
+        let orders = ORDER_MAP
+            .range(storage, None, None, IterOrder::Ascending)
+            .map(|item| item.map(|(_, order)| order))
+            .collect::<StdResult<Vec<_>>>()?;
+        for order in orders.iter() {
+            ORDER_MAP.save(storage, order.id.clone(), order)?;
+        }
+
+        RESOURCES_BY_STATUS.clear(storage);
+        RESOURCES_BY_REGION.clear(storage);
+        CAPACITY.clear(storage);
+        ACTIVE_ORDERS.remove(storage);
+        ESCROWED.remove(storage);
+
+        let resources = RESOURCE_MAP
+            .range(storage, None, None, IterOrder::Ascending)
+            .map(|item| item.map(|(_, resource)| resource))
+            .collect::<StdResult<Vec<_>>>()?;
+        for resource in resources.iter() {
+            update_resource_stats(storage, None, Some(resource))?;
+        }
+        for order in orders.iter() {
+            update_order_stats(storage, None, Some(order))?;
+        }
+
+        Ok(())
+    }
+
+    /// Admins used to hold every permission, so they keep all roles after the
+    /// migration. Does nothing once any role was granted.
+    pub fn seed_roles(storage: &mut dyn Storage) -> StdResult<()> {
+        let seeded = ROLES
+            .keys_raw(storage, None, None, IterOrder::Ascending)
+            .next()
+            .is_some();
+        if seeded {
+            return Ok(());
+        }
+        let cfg = ADMIN_LIST.load(storage)?;
+        grant_all_roles(storage, &cfg.admins)
+    }
+}
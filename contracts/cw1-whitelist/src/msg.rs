@@ -3,10 +3,15 @@ use schemars::JsonSchema;
 use std::fmt;
 
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{CosmosMsg, Empty};
+use cosmwasm_std::{Binary, CosmosMsg, Decimal, Empty, Uint128};
 
 use crate::resource_type::{ResourceType,Resource};
-use crate::order_type::{HandleAction};
+use crate::order_type::{HandleAction, Order};
+use crate::approvals::{ApprovalConfig, PendingExecution};
+use crate::audit::{AuditConfig, ExecutionRecord};
+use crate::credentials::CredentialsConfig;
+use crate::policy::ExecutePolicy;
+use crate::roles::Role;
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -14,6 +19,9 @@ pub struct InstantiateMsg {
     pub mutable: bool,
 }
 
+#[cw_serde]
+pub struct MigrateMsg {}
+
 #[cw_serde]
 pub enum ExecuteMsg<T = Empty>
 where
@@ -23,11 +31,41 @@ where
     /// contract's address as sender. Every implementation has it's own logic to
     /// determine in
     Execute { msgs: Vec<CosmosMsg<T>> },
+    /// Approves messages proposed with `Execute` while approvals are required, relaying them
+    /// once the threshold is reached. Must be called by an admin holding the `ProxyExecutor`
+    /// role before the proposal expires
+    ApproveExecution { id: u64 },
+    /// Removes the expired ones among the next `limit` pending executions after `start_after`,
+    /// oldest first. Can be called by anyone
+    PruneExpiredExecutions {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Requires `threshold` admins to approve every `Execute`, or relays messages right away
+    /// when `config` is None. Must be called by an admin and only works if the contract is mutable
+    SetApprovalConfig { config: Option<ApprovalConfig> },
     /// Freeze will make a mutable contract immutable, must be called by an admin
     Freeze {},
     /// UpdateAdmins will change the admin set of the contract, must be called by an existing admin,
-    /// and only works if the contract is mutable
+    /// and only works if the contract is mutable. New admins get every role and removed admins
+    /// lose all of theirs
     UpdateAdmins { admins: Vec<String> },
+    /// Gives the role to the address. Must be called by an admin and only works if the
+    /// contract is mutable
+    GrantRole { role: Role, addr: String },
+    /// Takes the role away from the address. Must be called by an admin and only works if the
+    /// contract is mutable
+    RevokeRole { role: Role, addr: String },
+    /// Restricts the messages the address may relay with `Execute`, or lifts the restrictions
+    /// when `policy` is None. Must be called by an admin and only works if the contract is mutable
+    SetExecutePolicy {
+        addr: String,
+        policy: Option<ExecutePolicy>,
+    },
+    /// Records every relayed `Execute`, keeping the latest `retention` records, or stops
+    /// recording when `config` is None. Must be called by an admin and only works if the
+    /// contract is mutable
+    SetAuditConfig { config: Option<AuditConfig> },
 
     ///B
     //UseResources { ids: Vec<String>},
@@ -52,6 +90,54 @@ where
 
     /// B
     HandleException{order_id: String, action: HandleAction},
+
+    /// Reserves all the given resources at once with a single payment. Either every
+    /// order is created or none is, and they are linked by a common group id
+    CreateOrderBatch { resource_ids: Vec<String>, duration: u64 },
+    /// Extends every active order of the group by `duration` blocks. Must be called by the
+    /// initiator with enough funds to cover the extension. Orders already settled, terminated
+    /// or flagged on their own are left out of this and the other group operations
+    ExtendOrderGroup { group_id: u64, duration: u64 },
+    /// Terminates every active order of the group, paying providers for the blocks already used
+    /// and refunding the rest. Must be called by the initiator
+    CancelOrderGroup { group_id: u64 },
+    /// Settles every active order of the group once they all expired
+    EndOrderGroup { group_id: u64 },
+
+    /// Settles up to `limit` expired orders, oldest first. Can be called by anyone,
+    /// the caller receives the keeper fee out of the marketplace fee of every settled order
+    SettleExpired { limit: Option<u32> },
+    /// Sets the share of every provider payment kept by the marketplace. The fees stay in the
+    /// contract balance. Must be called by a fee manager
+    SetMarketplaceFee { fee: Decimal },
+    /// Sets the share of the marketplace fee paid to the caller of `SettleExpired`.
+    /// Must be called by a fee manager
+    SetKeeperFee { fee: Decimal },
+
+    /// Registers the public key the provider must encrypt the access credentials with.
+    /// Must be called by the order initiator
+    RegisterPubkey { order_id: String, pubkey: Binary },
+    /// Posts the encrypted access credentials for an order. Must be called by the resource owner
+    /// before the credentials deadline
+    DeliverCredentials { order_id: String, credentials: Binary },
+    /// Marks an order whose credentials were not delivered in time as an exception.
+    /// Can be called by anyone
+    FlagUndeliveredCredentials { order_id: String },
+    /// Sets the size limit of the credentials and the blocks the provider has to deliver them.
+    /// Must be called by an admin and only works if the contract is mutable
+    SetCredentialsConfig { config: CredentialsConfig },
+
+    /// Add a new hook to be informed of all order status changes. Must be called by an admin
+    /// and only works if the contract is mutable
+    AddOrderHook { addr: String },
+    /// Remove an order hook. Must be called by an admin and only works if the contract is mutable
+    RemoveOrderHook { addr: String },
+    /// Add a new hook to be informed of all resource changes. Must be called by an admin
+    /// and only works if the contract is mutable
+    AddResourceHook { addr: String },
+    /// Remove a resource hook. Must be called by an admin and only works if the contract
+    /// is mutable
+    RemoveResourceHook { addr: String },
 }
 
 #[cw_serde]
@@ -68,9 +154,72 @@ where
     /// before any further state changes, should also succeed.
     #[returns(cw1::CanExecuteResponse)]
     CanExecute { sender: String, msg: CosmosMsg<T> },
+    /// Shows the members of every role
+    #[returns(RolesResponse)]
+    Roles {},
+    /// Shows whether `Execute` requires approvals and how many
+    #[returns(ApprovalConfigResponse)]
+    ApprovalConfig {},
+    /// Shows messages waiting for approvals
+    #[returns(PendingExecution)]
+    PendingExecution { id: u64 },
+    #[returns(PendingExecutionsResponse)]
+    ListPendingExecutions {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Shows the restrictions on the messages the address may relay, if any
+    #[returns(ExecutePolicyResponse)]
+    ExecutePolicy { addr: String },
+    /// Shows whether relayed messages are recorded and how many records are kept
+    #[returns(AuditConfigResponse)]
+    AuditConfig {},
+    /// Shows the recorded executions, oldest first, optionally only those of `sender`
+    #[returns(ExecutionHistoryResponse)]
+    ExecutionHistory {
+        sender: Option<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 
     #[returns(Vec<Resource>)]
     QueryResources { ids: Vec<String>},
+
+    /// Shows aggregated resource, capacity and order statistics of the marketplace
+    #[returns(MarketStatsResponse)]
+    MarketStats {},
+    /// Shows the total paid to a provider between two heights, both inclusive
+    #[returns(ProviderRevenueResponse)]
+    ProviderRevenue {
+        provider: String,
+        start_height: Option<u64>,
+        end_height: Option<u64>,
+    },
+
+    /// Shows the share of every provider payment kept by the marketplace
+    #[returns(MarketplaceFeeResponse)]
+    MarketplaceFee {},
+    /// Shows the share of the marketplace fee paid to the keeper
+    #[returns(KeeperFeeResponse)]
+    KeeperFee {},
+
+    /// Shows a group of orders created together
+    #[returns(OrderGroupResponse)]
+    OrderGroup { group_id: u64 },
+
+    /// Shows the tenant public key and the encrypted credentials of an order
+    #[returns(CredentialsResponse)]
+    OrderCredentials { order_id: String },
+    /// Shows the limits of the credentials handoff
+    #[returns(CredentialsConfigResponse)]
+    CredentialsConfig {},
+
+    /// Shows all registered order hooks.
+    #[returns(cw_controllers::HooksResponse)]
+    OrderHooks {},
+    /// Shows all registered resource hooks.
+    #[returns(cw_controllers::HooksResponse)]
+    ResourceHooks {},
 }
 
 #[cw_serde]
@@ -79,6 +228,105 @@ pub struct AdminListResponse {
     pub mutable: bool,
 }
 
+#[cw_serde]
+pub struct StatCount {
+    pub key: String,
+    pub count: u64,
+}
+
+#[cw_serde]
+pub struct CapacityResponse {
+    pub total: Uint128,
+    /// capacity of the resources currently rented
+    pub rented: Uint128,
+    pub utilization: Decimal,
+}
+
+#[cw_serde]
+pub struct MarketStatsResponse {
+    pub resources_by_status: Vec<StatCount>,
+    pub resources_by_region: Vec<StatCount>,
+    pub cpu: CapacityResponse,
+    pub memory: CapacityResponse,
+    pub bandwidth: CapacityResponse,
+    pub active_orders: u64,
+    /// funds locked by active and exception orders
+    pub escrowed: Uint128,
+}
+
+#[cw_serde]
+pub struct ProviderRevenueResponse {
+    pub provider: String,
+    pub revenue: Uint128,
+}
+
+#[cw_serde]
+pub struct MarketplaceFeeResponse {
+    pub fee: Decimal,
+}
+
+#[cw_serde]
+pub struct KeeperFeeResponse {
+    pub fee: Decimal,
+}
+
+#[cw_serde]
+pub struct OrderGroupResponse {
+    pub id: u64,
+    pub initiator: String,
+    pub orders: Vec<Order>,
+}
+
+#[cw_serde]
+pub struct CredentialsResponse {
+    pub order_id: String,
+    pub pubkey: Option<Binary>,
+    /// encrypted with `pubkey`, None until delivered
+    pub credentials: Option<Binary>,
+    pub deadline: Option<u64>,
+}
+
+#[cw_serde]
+pub struct CredentialsConfigResponse {
+    pub config: CredentialsConfig,
+}
+
+#[cw_serde]
+pub struct RoleMembers {
+    pub role: Role,
+    pub members: Vec<String>,
+}
+
+#[cw_serde]
+pub struct RolesResponse {
+    pub roles: Vec<RoleMembers>,
+}
+
+#[cw_serde]
+pub struct ApprovalConfigResponse {
+    pub config: Option<ApprovalConfig>,
+}
+
+#[cw_serde]
+pub struct PendingExecutionsResponse {
+    pub executions: Vec<PendingExecution>,
+}
+
+#[cw_serde]
+pub struct ExecutePolicyResponse {
+    pub policy: Option<ExecutePolicy>,
+}
+
+#[cw_serde]
+pub struct AuditConfigResponse {
+    pub config: Option<AuditConfig>,
+}
+
+#[cw_serde]
+pub struct ExecutionHistoryResponse {
+    pub executions: Vec<ExecutionRecord>,
+}
+
 #[cfg(any(test, feature = "test-utils"))]
 impl AdminListResponse {
     /// Utility function for converting message to its canonical form, so two messages with
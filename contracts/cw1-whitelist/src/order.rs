@@ -1,13 +1,86 @@
-use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, Coin, BankMsg};
-use cw_storage_plus::Map;
+use cosmwasm_std::{
+    Addr, BankMsg, Coin, Decimal, Deps, DepsMut, Env, MessageInfo, Order as IterOrder, Response,
+    StdResult, Storage, Uint128, Uint64,
+};
+use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, MultiIndex};
 
 use crate::ContractError;
-use crate::state::{ADMIN_LIST};
+use crate::credentials::{credentials_overdue, CREDENTIALS_MAP};
+use crate::hook::{prepare_order_hooks, prepare_resource_hooks, OrderDiff, ResourceDiff};
+use crate::roles::{assert_role, Role};
 use crate::resource::{RESOURCE_MAP, update_status_by_resource_map};
 use crate::resource_type::{Status};
 use crate::order_type::{Order, OrderStatus, HandleAction, DEFAULT_DENOM};
 
-pub const ORDER_MAP: Map<String, Order> = Map::new("orders");
+use crate::msg::{KeeperFeeResponse, MarketplaceFeeResponse};
+use crate::stats::{record_revenue, update_order_stats};
+
+pub struct OrderIndexes<'a> {
+    // 活跃订单按到期高度排序，用于结算到期订单
+    pub expiry: MultiIndex<'a, (u8, u64), Order, String>,
+}
+
+impl<'a> IndexList<Order> for OrderIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Order>> + '_> {
+        let v: Vec<&dyn Index<Order>> = vec![&self.expiry];
+        Box::new(v.into_iter())
+    }
+}
+
+pub const ORDER_MAP: IndexedMap<String, Order, OrderIndexes> = IndexedMap::new(
+    "orders",
+    OrderIndexes {
+        expiry: MultiIndex::new(
+            |_pk, order| ((order.status == OrderStatus::Active) as u8, order.end_height),
+            "orders",
+            "orders__expiry",
+        ),
+    },
+);
+
+// 已创建的单个订单数量，用于生成订单编号
+pub const ORDER_COUNT: Item<u64> = Item::new("order_count");
+
+// 分配下一个订单编号，跳过已存在的编号（如旧版本以区块高度为编号的订单），
+// 同一区块内的多个订单不会互相覆盖
+pub fn next_order_id(storage: &mut dyn Storage) -> StdResult<String> {
+    let mut id = ORDER_COUNT.may_load(storage)?.unwrap_or_default();
+    loop {
+        id += 1;
+        if !ORDER_MAP.has(storage, id.to_string()) {
+            break;
+        }
+    }
+    ORDER_COUNT.save(storage, &id)?;
+    Ok(id.to_string())
+}
+
+// 保存订单并更新统计数据
+pub fn save_order(storage: &mut dyn Storage, order: &Order) -> StdResult<()> {
+    let old = ORDER_MAP.may_load(storage, order.id.clone())?;
+    ORDER_MAP.save(storage, order.id.clone(), order)?;
+    // 订单结束后不再保留访问凭证
+    if matches!(order.status, OrderStatus::Expired | OrderStatus::Terminated) {
+        CREDENTIALS_MAP.remove(storage, order.id.clone());
+    }
+    update_order_stats(storage, old.as_ref(), Some(order))
+}
+
+// 市场从提供者收入中收取的费用比例，留在合约余额中，管理员可通过 Execute 提取
+pub const MARKETPLACE_FEE: Item<Decimal> = Item::new("marketplace_fee");
+
+// 市场费用比例上限
+pub const MAX_MARKETPLACE_FEE: Decimal = Decimal::percent(10);
+
+// 结算到期订单的调用者可获得的奖励比例，从市场费用中扣除
+pub const KEEPER_FEE: Item<Decimal> = Item::new("keeper_fee");
+
+// 奖励比例上限
+pub const MAX_KEEPER_FEE: Decimal = Decimal::percent(50);
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
 
 pub fn create_order(
     deps: DepsMut,
@@ -20,45 +93,50 @@ pub fn create_order(
     let resource = RESOURCE_MAP.load(deps.storage, resource_id.clone())?;
 
     // 确保资源未被使用
-    if resource.check_status(Status::Unused) {
+    if !resource.check_status(Status::Unused) {
         return Err(ContractError::OtherError);
         //return Err(StdError::generic_err("Resource is not available"));
     }
 
     // 计算总费用
-    let total_cost = resource.get_resource_price() * duration as u128;
+    let total_cost = order_cost(resource.get_resource_price(), duration)?;
 
     // 检查用户是否发送了足够的资金
-    let sent_funds = info.funds.iter().find(|coin| coin.denom == DEFAULT_DENOM);
-    if let Some(Coin { amount, .. }) = sent_funds {
-        if *amount < total_cost.into() {
-            return Err(ContractError::OtherError);
-            //return Err(StdError::generic_err("Insufficient funds sent"));
-        }
-    } else {
-        return Err(ContractError::OtherError);
-        //return Err(StdError::generic_err("No funds sent"));
-    }
+    check_funds(&info, total_cost)?;
 
     // 创建订单
     let order = Order {
-        id: env.block.height.to_string(),
+        id: next_order_id(deps.storage)?,
         resource_id: resource_id.clone(),
         initiator: info.sender.clone(),
         start_height: env.block.height,
-        end_height: env.block.height + duration,
+        end_height: end_height(env.block.height, duration)?,
         locked_funds: total_cost,
         status: OrderStatus::Active,
+        pubkey: None,
+        credentials_deadline: None,
+        group_id: None,
     };
 
     // 保存订单
-    ORDER_MAP.save(deps.storage, order.id.clone(), &order)?;
+    save_order(deps.storage, &order)?;
+
+    // 通知订阅的合约
+    let mut messages = prepare_order_hooks(
+        deps.storage,
+        vec![OrderDiff::new(&order.id, &resource_id, None, Some(OrderStatus::Active))],
+    )?;
+    messages.extend(prepare_resource_hooks(
+        deps.storage,
+        vec![ResourceDiff::new(&resource_id, Some(resource.get_status()), Some(Status::Used))],
+    )?);
 
     //更新资源状态
     update_status_by_resource_map(deps, resource.get_id(), Status::Used)?;
 
     // 返回响应，确认订单创建成功
     Ok(Response::new()
+        .add_submessages(messages)
         .add_attribute("action", "create_order")
         .add_attribute("order_id", order.id)
         .add_attribute("locked_funds", total_cost.to_string())
@@ -66,13 +144,13 @@ pub fn create_order(
 }
 
 pub fn end_order(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     _info: MessageInfo,
     order_id: String,
 ) -> Result<Response, ContractError> {
     // 加载订单
-    let mut order = ORDER_MAP.load(deps.storage, order_id.clone())?;
+    let order = ORDER_MAP.load(deps.storage, order_id.clone())?;
 
     if order.status != OrderStatus::Active {
         // 活跃订单才能正常终止
@@ -85,30 +163,126 @@ pub fn end_order(
         //return Err(StdError::generic_err("Order has not yet expired"));
     }
 
+    let mut settlement = Settlement::default();
+    settle_order(deps.branch(), env.block.height, order, Decimal::zero(), &mut settlement)?;
+
+    // 返回响应，并发送资金
+    Ok(settlement
+        .into_response(deps.storage)?
+        .add_attribute("action", "end_order")
+        .add_attribute("order_id", order_id)
+    )
+}
+
+/// 订单结算或取消时产生的付款与状态变化
+#[derive(Default)]
+pub struct Settlement {
+    pub payments: Vec<BankMsg>,
+    pub order_diffs: Vec<OrderDiff>,
+    pub resource_diffs: Vec<ResourceDiff>,
+    // 从市场费用中扣除的结算奖励
+    pub keeper_reward: u128,
+}
+
+impl Settlement {
+    /// 付款消息在前，随后通知订阅的合约
+    pub fn into_response(self, storage: &dyn Storage) -> StdResult<Response> {
+        let mut messages = prepare_order_hooks(storage, self.order_diffs)?;
+        messages.extend(prepare_resource_hooks(storage, self.resource_diffs)?);
+
+        Ok(Response::new()
+            .add_messages(self.payments)
+            .add_submessages(messages))
+    }
+}
+
+// 结算一个已到期的活跃订单：扣除市场费用后支付给资源提供者并释放资源
+pub fn settle_order(
+    deps: DepsMut,
+    height: u64,
+    mut order: Order,
+    keeper_fee: Decimal,
+    settlement: &mut Settlement,
+) -> Result<(), ContractError> {
+    // 凭证未按时交付的订单不结算，转为异常等待管理员处理
+    if credentials_overdue(deps.storage, height, &order)? {
+        order.status = OrderStatus::Exception;
+        save_order(deps.storage, &order)?;
+
+        settlement.order_diffs.push(OrderDiff::new(&order.id, &order.resource_id, Some(OrderStatus::Active), Some(OrderStatus::Exception)));
+        return Ok(());
+    }
+
     // 获取资源并支付给资源提供者
     let resource = RESOURCE_MAP.load(deps.storage, order.resource_id.clone())?;
-    let payment_msg = BankMsg::Send {
-        to_address: resource.get_owner().to_string(),
-        amount: vec![Coin {
-            denom: DEFAULT_DENOM.to_string(),
-            amount: order.locked_funds.into(),
-        }],
-    };
+    pay_provider(deps.storage, height, resource.get_owner(), order.locked_funds, keeper_fee, settlement)?;
 
     // 更新订单状态为到期
     order.status = OrderStatus::Expired;
+    save_order(deps.storage, &order)?;
 
-    ORDER_MAP.save(deps.storage, order_id.clone(), &order)?;
+    settlement.order_diffs.push(OrderDiff::new(&order.id, &order.resource_id, Some(OrderStatus::Active), Some(OrderStatus::Expired)));
+    settlement.resource_diffs.push(ResourceDiff::new(resource.get_id(), Some(resource.get_status()), Some(Status::Unused)));
 
     // 更新资源状态为未使用
     update_status_by_resource_map(deps, resource.get_id(), Status::Unused)?;
 
-    // 返回响应，并发送资金
-    Ok(Response::new()
-        .add_message(payment_msg)
-        .add_attribute("action", "end_order")
-        .add_attribute("order_id", order_id)
-    )
+    Ok(())
+}
+
+// 扣除市场费用后支付给资源提供者，结算奖励按比例从市场费用中扣除，其余留在合约中
+pub fn pay_provider(
+    storage: &mut dyn Storage,
+    height: u64,
+    provider: Addr,
+    amount: u128,
+    keeper_fee: Decimal,
+    settlement: &mut Settlement,
+) -> Result<(), ContractError> {
+    let marketplace_fee = MARKETPLACE_FEE.may_load(storage)?.unwrap_or_default();
+    let fee = Uint128::new(amount).mul_floor(marketplace_fee).u128();
+    settlement.keeper_reward += Uint128::new(fee).mul_floor(keeper_fee).u128();
+
+    settlement.payments.push(payment(provider.clone(), amount - fee));
+    record_revenue(storage, &provider, height, amount - fee)?;
+    Ok(())
+}
+
+// 以默认币种向地址转账
+pub fn payment(to_address: Addr, amount: u128) -> BankMsg {
+    BankMsg::Send {
+        to_address: to_address.to_string(),
+        amount: vec![Coin {
+            denom: DEFAULT_DENOM.to_string(),
+            amount: amount.into(),
+        }],
+    }
+}
+
+// 计算资源租用给定区块数的费用，溢出时返回错误
+pub fn order_cost(price: u128, duration: u64) -> StdResult<u128> {
+    Ok(Uint128::new(price).checked_mul(Uint128::from(duration))?.u128())
+}
+
+// 计算订单的到期高度，溢出时返回错误
+pub fn end_height(start_height: u64, duration: u64) -> StdResult<u64> {
+    Ok(Uint64::new(start_height).checked_add(Uint64::new(duration))?.u64())
+}
+
+// 检查用户是否发送了足够的资金
+pub fn check_funds(info: &MessageInfo, total_cost: u128) -> Result<(), ContractError> {
+    let sent_funds = info.funds.iter().find(|coin| coin.denom == DEFAULT_DENOM);
+    if let Some(Coin { amount, .. }) = sent_funds {
+        if *amount < total_cost.into() {
+            return Err(ContractError::OtherError);
+            //return Err(StdError::generic_err("Insufficient funds sent"));
+        }
+    } else {
+        return Err(ContractError::OtherError);
+        //return Err(StdError::generic_err("No funds sent"));
+    }
+
+    Ok(())
 }
 
 pub fn handle_exception(
@@ -118,11 +292,8 @@ pub fn handle_exception(
     order_id: String,
     action: HandleAction,
 ) -> Result<Response,ContractError> {
-    // 只允许管理员操作
-    let admin_list = ADMIN_LIST.load(deps.storage)?;
-    if !admin_list.is_admin(info.sender.as_str()) {
-        return Err(ContractError::Unauthorized {});
-    }
+    // 只允许仲裁者操作
+    assert_role(deps.storage, Role::DisputeArbiter, &info.sender)?;
 
     // 加载订单
     let mut order = ORDER_MAP.load(deps.storage, order_id.clone())?;
@@ -132,9 +303,15 @@ pub fn handle_exception(
             // 如果订单当前为异常状态，恢复为正常状态
             if order.status == OrderStatus::Exception {
                 order.status = OrderStatus::Active;
-                ORDER_MAP.save(deps.storage, order_id.clone(), &order)?;
+                save_order(deps.storage, &order)?;
+
+                let messages = prepare_order_hooks(
+                    deps.storage,
+                    vec![OrderDiff::new(&order_id, &order.resource_id, Some(OrderStatus::Exception), Some(OrderStatus::Active))],
+                )?;
 
                 Ok(Response::new()
+                    .add_submessages(messages)
                     .add_attribute("action", "mark_as_normal")
                     .add_attribute("order_id", order_id))
             } else {
@@ -146,9 +323,15 @@ pub fn handle_exception(
             // 将正常订单标记为异常状态
             if order.status == OrderStatus::Active {
                 order.status = OrderStatus::Exception;
-                ORDER_MAP.save(deps.storage, order_id.clone(), &order)?;
+                save_order(deps.storage, &order)?;
+
+                let messages = prepare_order_hooks(
+                    deps.storage,
+                    vec![OrderDiff::new(&order_id, &order.resource_id, Some(OrderStatus::Active), Some(OrderStatus::Exception))],
+                )?;
 
                 Ok(Response::new()
+                    .add_submessages(messages)
                     .add_attribute("action", "mark_as_exception")
                     .add_attribute("order_id", order_id))
             } else {
@@ -161,7 +344,18 @@ pub fn handle_exception(
             if order.status == OrderStatus::Exception {
                 // 更新订单状态为终止
                 order.status = OrderStatus::Terminated;
-                ORDER_MAP.save(deps.storage, order_id.clone(), &order)?;
+                save_order(deps.storage, &order)?;
+
+                // 通知订阅的合约
+                let resource = RESOURCE_MAP.load(deps.storage, order.resource_id.clone())?;
+                let mut messages = prepare_order_hooks(
+                    deps.storage,
+                    vec![OrderDiff::new(&order_id, &order.resource_id, Some(OrderStatus::Exception), Some(OrderStatus::Terminated))],
+                )?;
+                messages.extend(prepare_resource_hooks(
+                    deps.storage,
+                    vec![ResourceDiff::new(resource.get_id(), Some(resource.get_status()), Some(Status::Unused))],
+                )?);
 
                 // 处理资源状态，设置为未使用
                 update_status_by_resource_map(deps,order.resource_id.clone(),  Status::Unused)?;
@@ -177,6 +371,7 @@ pub fn handle_exception(
 
                 Ok(Response::new()
                     .add_message(refund_msg)
+                    .add_submessages(messages)
                     .add_attribute("action", "terminate_order")
                     .add_attribute("order_id", order_id)
                     .add_attribute("refund", "true"))
@@ -186,4 +381,96 @@ pub fn handle_exception(
             }
         },
     }
-}
\ No newline at end of file
+}
+// 任何人都可以批量结算已到期的订单，并获得结算奖励
+pub fn settle_expired(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let keeper_fee = KEEPER_FEE.may_load(deps.storage)?.unwrap_or_default();
+
+    // 按到期高度遍历活跃订单
+    let expired = ORDER_MAP
+        .idx
+        .expiry
+        .sub_prefix(1)
+        .range(
+            deps.storage,
+            None,
+            Some(Bound::exclusive((env.block.height + 1, String::new()))),
+            IterOrder::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, order)| order))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let settled = expired.len();
+    let mut settlement = Settlement::default();
+    for order in expired {
+        settle_order(deps.branch(), env.block.height, order, keeper_fee, &mut settlement)?;
+    }
+
+    let reward = settlement.keeper_reward;
+    if reward > 0 {
+        settlement.payments.push(payment(info.sender, reward));
+    }
+
+    Ok(settlement
+        .into_response(deps.storage)?
+        .add_attribute("action", "settle_expired")
+        .add_attribute("settled", settled.to_string())
+        .add_attribute("keeper_reward", reward.to_string()))
+}
+
+pub fn set_keeper_fee(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    fee: Decimal,
+) -> Result<Response, ContractError> {
+    // 只允许费用管理者操作
+    assert_role(deps.storage, Role::FeeManager, &info.sender)?;
+
+    if fee > MAX_KEEPER_FEE {
+        return Err(ContractError::KeeperFeeTooHigh { max: MAX_KEEPER_FEE });
+    }
+
+    KEEPER_FEE.save(deps.storage, &fee)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_keeper_fee")
+        .add_attribute("fee", fee.to_string()))
+}
+
+pub fn set_marketplace_fee(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    fee: Decimal,
+) -> Result<Response, ContractError> {
+    // 只允许费用管理者操作
+    assert_role(deps.storage, Role::FeeManager, &info.sender)?;
+
+    if fee > MAX_MARKETPLACE_FEE {
+        return Err(ContractError::MarketplaceFeeTooHigh { max: MAX_MARKETPLACE_FEE });
+    }
+
+    MARKETPLACE_FEE.save(deps.storage, &fee)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_marketplace_fee")
+        .add_attribute("fee", fee.to_string()))
+}
+
+pub fn query_marketplace_fee(deps: Deps) -> StdResult<MarketplaceFeeResponse> {
+    let fee = MARKETPLACE_FEE.may_load(deps.storage)?.unwrap_or_default();
+    Ok(MarketplaceFeeResponse { fee })
+}
+
+pub fn query_keeper_fee(deps: Deps) -> StdResult<KeeperFeeResponse> {
+    let fee = KEEPER_FEE.may_load(deps.storage)?.unwrap_or_default();
+    Ok(KeeperFeeResponse { fee })
+}
@@ -0,0 +1,270 @@
+use cosmwasm_std::{
+    Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Storage, Uint128,
+};
+use cw_storage_plus::{Item, Map};
+
+use crate::ContractError;
+use crate::hook::{OrderDiff, ResourceDiff};
+use crate::msg::OrderGroupResponse;
+use crate::order::{
+    check_funds, end_height, order_cost, pay_provider, payment, save_order, settle_order,
+    Settlement, ORDER_MAP,
+};
+use crate::order_type::{Order, OrderGroup, OrderStatus};
+use crate::resource::{update_status_by_resource_map, RESOURCE_MAP};
+use crate::resource_type::Status;
+
+pub const ORDER_GROUP_MAP: Map<u64, OrderGroup> = Map::new("order_groups");
+pub const ORDER_GROUP_COUNT: Item<u64> = Item::new("order_group_count");
+
+pub fn next_group_id(store: &mut dyn Storage) -> StdResult<u64> {
+    let id: u64 = ORDER_GROUP_COUNT.may_load(store)?.unwrap_or_default() + 1;
+    ORDER_GROUP_COUNT.save(store, &id)?;
+    Ok(id)
+}
+
+// 加载订单组及组内所有订单
+fn load_group(storage: &dyn Storage, group_id: u64) -> StdResult<(OrderGroup, Vec<Order>)> {
+    let group = ORDER_GROUP_MAP.load(storage, group_id)?;
+    let orders = group
+        .order_ids
+        .iter()
+        .map(|id| ORDER_MAP.load(storage, id.clone()))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok((group, orders))
+}
+
+// 加载订单组及组内仍活跃的订单，已单独结算、终止或标记为异常的订单不参与组操作
+fn load_active_orders(
+    storage: &dyn Storage,
+    group_id: u64,
+) -> Result<(OrderGroup, Vec<Order>), ContractError> {
+    let (group, orders) = load_group(storage, group_id)?;
+    let orders: Vec<Order> = orders
+        .into_iter()
+        .filter(|order| order.status == OrderStatus::Active)
+        .collect();
+    if orders.is_empty() {
+        return Err(ContractError::NoActiveOrders { group_id });
+    }
+    Ok((group, orders))
+}
+
+// 批量创建订单，所有资源要么全部预订成功，要么全部失败
+pub fn create_order_batch(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    resource_ids: Vec<String>,
+    duration: u64,
+) -> Result<Response, ContractError> {
+    if resource_ids.is_empty() {
+        return Err(ContractError::EmptyOrderGroup);
+    }
+
+    // 加载资源并计算总费用
+    let mut resources = Vec::with_capacity(resource_ids.len());
+    let mut total_cost = Uint128::zero();
+    for (i, resource_id) in resource_ids.iter().enumerate() {
+        if resource_ids[..i].contains(resource_id) {
+            return Err(ContractError::DuplicateResource {
+                resource_id: resource_id.clone(),
+            });
+        }
+
+        let resource = RESOURCE_MAP.load(deps.storage, resource_id.clone())?;
+
+        // 确保资源未被使用
+        if !resource.check_status(Status::Unused) {
+            return Err(ContractError::OtherError);
+        }
+
+        let cost = order_cost(resource.get_resource_price(), duration)?;
+        total_cost = total_cost.checked_add(Uint128::new(cost)).map_err(StdError::from)?;
+        resources.push(resource);
+    }
+    let total_cost = total_cost.u128();
+    let end_height = end_height(env.block.height, duration)?;
+
+    // 检查用户是否发送了足够的资金
+    check_funds(&info, total_cost)?;
+
+    let group_id = next_group_id(deps.storage)?;
+    let mut settlement = Settlement::default();
+    let mut order_ids = Vec::with_capacity(resources.len());
+
+    for (i, resource) in resources.into_iter().enumerate() {
+        let order = Order {
+            id: format!("{}-{}", group_id, i),
+            resource_id: resource.get_id(),
+            initiator: info.sender.clone(),
+            start_height: env.block.height,
+            end_height,
+            locked_funds: order_cost(resource.get_resource_price(), duration)?,
+            status: OrderStatus::Active,
+            pubkey: None,
+            credentials_deadline: None,
+            group_id: Some(group_id),
+        };
+        save_order(deps.storage, &order)?;
+
+        settlement.order_diffs.push(OrderDiff::new(&order.id, &order.resource_id, None, Some(OrderStatus::Active)));
+        settlement.resource_diffs.push(ResourceDiff::new(resource.get_id(), Some(resource.get_status()), Some(Status::Used)));
+
+        update_status_by_resource_map(deps.branch(), resource.get_id(), Status::Used)?;
+        order_ids.push(order.id);
+    }
+
+    let group = OrderGroup {
+        id: group_id,
+        initiator: info.sender,
+        order_ids,
+    };
+    ORDER_GROUP_MAP.save(deps.storage, group_id, &group)?;
+
+    Ok(settlement
+        .into_response(deps.storage)?
+        .add_attribute("action", "create_order_batch")
+        .add_attribute("group_id", group_id.to_string())
+        .add_attribute("orders", group.order_ids.len().to_string())
+        .add_attribute("locked_funds", total_cost.to_string()))
+}
+
+// 为组内所有活跃订单续期
+pub fn extend_order_group(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    group_id: u64,
+    duration: u64,
+) -> Result<Response, ContractError> {
+    let (group, orders) = load_active_orders(deps.storage, group_id)?;
+
+    if group.initiator != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // 只有未到期的活跃订单才能续期
+    let mut total_cost = Uint128::zero();
+    let mut extended = Vec::with_capacity(orders.len());
+    for mut order in orders {
+        if env.block.height >= order.end_height {
+            return Err(ContractError::OtherError);
+        }
+
+        let resource = RESOURCE_MAP.load(deps.storage, order.resource_id.clone())?;
+        let cost = Uint128::new(order_cost(resource.get_resource_price(), duration)?);
+        total_cost = total_cost.checked_add(cost).map_err(StdError::from)?;
+
+        order.end_height = end_height(order.end_height, duration)?;
+        order.locked_funds = cost
+            .checked_add(Uint128::new(order.locked_funds))
+            .map_err(StdError::from)?
+            .u128();
+        extended.push(order);
+    }
+    let total_cost = total_cost.u128();
+
+    // 检查用户是否发送了足够的资金
+    check_funds(&info, total_cost)?;
+
+    for order in extended {
+        save_order(deps.storage, &order)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "extend_order_group")
+        .add_attribute("group_id", group_id.to_string())
+        .add_attribute("locked_funds", total_cost.to_string()))
+}
+
+// 取消组内所有活跃订单，已使用的区块扣除市场费用后支付给提供者，其余退还给发起者
+pub fn cancel_order_group(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    group_id: u64,
+) -> Result<Response, ContractError> {
+    let (group, orders) = load_active_orders(deps.storage, group_id)?;
+
+    if group.initiator != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut settlement = Settlement::default();
+    let mut refund = 0u128;
+    for mut order in orders {
+        // 按已使用的区块数计算应付给提供者的金额
+        let total_blocks = order.end_height - order.start_height;
+        let used_blocks = env.block.height.min(order.end_height) - order.start_height;
+        let earned = if total_blocks == 0 {
+            order.locked_funds
+        } else {
+            // 中间结果使用 256 位计算，不会溢出
+            Uint128::new(order.locked_funds)
+                .multiply_ratio(used_blocks, total_blocks)
+                .u128()
+        };
+        refund = Uint128::new(refund)
+            .checked_add(Uint128::new(order.locked_funds - earned))
+            .map_err(StdError::from)?
+            .u128();
+
+        let resource = RESOURCE_MAP.load(deps.storage, order.resource_id.clone())?;
+        if earned > 0 {
+            pay_provider(deps.storage, env.block.height, resource.get_owner(), earned, Decimal::zero(), &mut settlement)?;
+        }
+
+        order.status = OrderStatus::Terminated;
+        save_order(deps.storage, &order)?;
+
+        settlement.order_diffs.push(OrderDiff::new(&order.id, &order.resource_id, Some(OrderStatus::Active), Some(OrderStatus::Terminated)));
+        settlement.resource_diffs.push(ResourceDiff::new(resource.get_id(), Some(resource.get_status()), Some(Status::Unused)));
+
+        update_status_by_resource_map(deps.branch(), resource.get_id(), Status::Unused)?;
+    }
+
+    if refund > 0 {
+        settlement.payments.push(payment(group.initiator, refund));
+    }
+
+    Ok(settlement
+        .into_response(deps.storage)?
+        .add_attribute("action", "cancel_order_group")
+        .add_attribute("group_id", group_id.to_string())
+        .add_attribute("refund", refund.to_string()))
+}
+
+// 组内所有活跃订单到期后一起结算
+pub fn end_order_group(
+    mut deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    group_id: u64,
+) -> Result<Response, ContractError> {
+    let (_, orders) = load_active_orders(deps.storage, group_id)?;
+
+    let mut settlement = Settlement::default();
+    for order in orders {
+        if env.block.height < order.end_height {
+            return Err(ContractError::OtherError);
+        }
+
+        settle_order(deps.branch(), env.block.height, order, Decimal::zero(), &mut settlement)?;
+    }
+
+    Ok(settlement
+        .into_response(deps.storage)?
+        .add_attribute("action", "end_order_group")
+        .add_attribute("group_id", group_id.to_string()))
+}
+
+pub fn query_order_group(deps: Deps, group_id: u64) -> StdResult<OrderGroupResponse> {
+    let (group, orders) = load_group(deps.storage, group_id)?;
+
+    Ok(OrderGroupResponse {
+        id: group.id,
+        initiator: group.initiator.into(),
+        orders,
+    })
+}
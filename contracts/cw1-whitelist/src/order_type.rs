@@ -1,7 +1,7 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Binary};
 
 pub const DEFAULT_DENOM: &str = "uttnt";
 
@@ -35,6 +35,20 @@ pub struct Order {
     pub end_height: u64,         // 订单结束区块高度
     pub locked_funds: u128,      // 锁定的资金
     pub status: OrderStatus,     // 订单状态
+    #[serde(default)]
+    pub pubkey: Option<Binary>,  // 租户用于接收凭证的公钥
+    #[serde(default)]
+    pub credentials_deadline: Option<u64>, // 凭证交付截止高度
+    #[serde(default)]
+    pub group_id: Option<u64>,   // 批量订单的组ID
+}
+
+// 批量创建的一组订单，需一起续期、取消和结算
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema, Debug)]
+pub struct OrderGroup {
+    pub id: u64,                 // 组ID
+    pub initiator: Addr,         // 订单发起者
+    pub order_ids: Vec<String>,  // 组内订单ID
 }
 
 
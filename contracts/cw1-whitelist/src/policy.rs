@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    from_json, Addr, Binary, CosmosMsg, Deps, DepsMut, MessageInfo, Response, StdResult, Storage,
+    WasmMsg,
+};
+use cw_storage_plus::Map;
+use serde::de::IgnoredAny;
+
+use crate::msg::ExecutePolicyResponse;
+use crate::state::ADMIN_LIST;
+use crate::ContractError;
+
+/// Kind of `CosmosMsg` that can be relayed
+#[cw_serde]
+#[derive(Copy, Eq)]
+pub enum MsgType {
+    Bank,
+    Custom,
+    Staking,
+    Distribution,
+    Wasm,
+    /// any message kind not listed above, e.g. ibc, gov or stargate messages
+    Other,
+}
+
+/// Restricts the messages an executor may relay. Every field left unset allows everything
+#[cw_serde]
+#[derive(Default)]
+pub struct ExecutePolicy {
+    /// message kinds that can be relayed
+    pub msg_types: Option<Vec<MsgType>>,
+    /// contracts wasm messages can target. Instantiating new contracts is not allowed when set
+    pub contracts: Option<Vec<String>>,
+    /// top-level names of the wasm execute messages, e.g. "transfer" for `{"transfer":{..}}`.
+    /// No other wasm message (migrate, admin changes) is allowed when set
+    pub execute_msgs: Option<Vec<String>>,
+}
+
+// 执行者地址 -> 可转发消息的限制
+pub const POLICIES: Map<&Addr, ExecutePolicy> = Map::new("execute_policies");
+
+/// returns the kind of the message
+pub fn msg_type<T>(msg: &CosmosMsg<T>) -> MsgType {
+    match msg {
+        CosmosMsg::Bank(_) => MsgType::Bank,
+        CosmosMsg::Custom(_) => MsgType::Custom,
+        CosmosMsg::Staking(_) => MsgType::Staking,
+        CosmosMsg::Distribution(_) => MsgType::Distribution,
+        CosmosMsg::Wasm(_) => MsgType::Wasm,
+        _ => MsgType::Other,
+    }
+}
+
+/// returns the name of a message serialized as `{"name":{..}}`
+pub fn execute_msg_name(msg: &Binary) -> Option<String> {
+    let fields: BTreeMap<String, IgnoredAny> = from_json(msg).ok()?;
+    let mut names = fields.into_keys();
+    match (names.next(), names.next()) {
+        (Some(name), None) => Some(name),
+        _ => None,
+    }
+}
+
+impl ExecutePolicy {
+    /// returns true if the policy allows relaying the message
+    pub fn allows<T>(&self, msg: &CosmosMsg<T>) -> bool {
+        if let Some(msg_types) = &self.msg_types {
+            if !msg_types.contains(&msg_type(msg)) {
+                return false;
+            }
+        }
+
+        let CosmosMsg::Wasm(wasm) = msg else {
+            return true;
+        };
+        let target = match wasm {
+            WasmMsg::Execute { contract_addr, .. }
+            | WasmMsg::Migrate { contract_addr, .. }
+            | WasmMsg::UpdateAdmin { contract_addr, .. }
+            | WasmMsg::ClearAdmin { contract_addr } => Some(contract_addr),
+            _ => None,
+        };
+        if let Some(contracts) = &self.contracts {
+            if !target.is_some_and(|target| contracts.contains(target)) {
+                return false;
+            }
+        }
+
+        match (wasm, &self.execute_msgs) {
+            (WasmMsg::Execute { msg, .. }, Some(execute_msgs)) => {
+                execute_msg_name(msg).is_some_and(|name| execute_msgs.contains(&name))
+            }
+            (_, Some(_)) => false,
+            (_, None) => true,
+        }
+    }
+}
+
+/// returns true if the sender has no policy or its policy allows the message
+pub fn policy_allows<T>(
+    storage: &dyn Storage,
+    sender: &Addr,
+    msg: &CosmosMsg<T>,
+) -> StdResult<bool> {
+    match POLICIES.may_load(storage, sender)? {
+        Some(policy) => Ok(policy.allows(msg)),
+        None => Ok(true),
+    }
+}
+
+/// returns MessageNotAllowed if the sender's policy rejects any of the messages
+pub fn assert_policy<T>(
+    storage: &dyn Storage,
+    sender: &Addr,
+    msgs: &[CosmosMsg<T>],
+) -> Result<(), ContractError> {
+    for msg in msgs {
+        if !policy_allows(storage, sender, msg)? {
+            return Err(ContractError::MessageNotAllowed {});
+        }
+    }
+    Ok(())
+}
+
+pub fn set_execute_policy(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+    policy: Option<ExecutePolicy>,
+) -> Result<Response, ContractError> {
+    let cfg = ADMIN_LIST.load(deps.storage)?;
+    if !cfg.can_modify(info.sender.as_ref()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let addr = deps.api.addr_validate(&addr)?;
+    match policy {
+        Some(mut policy) => {
+            if let Some(contracts) = policy.contracts.as_mut() {
+                for contract in contracts.iter_mut() {
+                    *contract = deps.api.addr_validate(contract)?.into();
+                }
+            }
+            POLICIES.save(deps.storage, &addr, &policy)?;
+        }
+        None => POLICIES.remove(deps.storage, &addr),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_execute_policy")
+        .add_attribute("addr", addr))
+}
+
+pub fn query_execute_policy(deps: Deps, addr: String) -> StdResult<ExecutePolicyResponse> {
+    let addr = deps.api.addr_validate(&addr)?;
+    let policy = POLICIES.may_load(deps.storage, &addr)?;
+    Ok(ExecutePolicyResponse { policy })
+}
@@ -1,20 +1,35 @@
-use cosmwasm_std::{Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage};
 use cw_storage_plus::Map;
 
 use crate::ContractError;
+use crate::hook::{prepare_resource_hooks, ResourceDiff};
 use crate::resource_type::{Resource, ResourceType, Status};
-use crate::state::{ADMIN_LIST};
+use crate::roles::{assert_role, Role};
+use crate::stats::update_resource_stats;
 
 pub const RESOURCE_MAP: Map<String, Resource> = Map::new("resources");
 
+// 保存资源并更新统计数据
+pub fn save_resource(storage: &mut dyn Storage, resource: &Resource) -> StdResult<()> {
+    let old = RESOURCE_MAP.may_load(storage, resource.get_id())?;
+    RESOURCE_MAP.save(storage, resource.get_id(), resource)?;
+    update_resource_stats(storage, old.as_ref(), Some(resource))
+}
+
+// 删除资源并更新统计数据
+pub fn remove_resource(storage: &mut dyn Storage, id: String) -> StdResult<()> {
+    let old = RESOURCE_MAP.may_load(storage, id.clone())?;
+    RESOURCE_MAP.remove(storage, id);
+    update_resource_stats(storage, old.as_ref(), None)
+}
+
 pub fn update_status_by_resource_map(deps: DepsMut, id: String, status: Status) -> Result<Resource, ContractError> {
-    RESOURCE_MAP.update(deps.storage, id, |resource: Option<Resource>|{
-        let mut resource = resource.ok_or(ContractError::NotFound)?;
+    let mut resource = RESOURCE_MAP.may_load(deps.storage, id)?.ok_or(ContractError::NotFound)?;
 
-        resource.set_status(status);
+    resource.set_status(status);
+    save_resource(deps.storage, &resource)?;
 
-        Ok::<Resource, ContractError>(resource)
-    })
+    Ok(resource)
 }
 
 pub fn query_resources(deps: Deps, ids: Vec<String>) -> StdResult<Vec<Resource>> {
@@ -37,22 +52,23 @@ pub fn use_resources(
     info: MessageInfo,
     ids: Vec<String>,
 ) -> Result<Response, ContractError> {
-    let admin_list = ADMIN_LIST.load(deps.storage)?;
-    if !admin_list.is_admin(info.sender.as_str()) {
-        return Err(ContractError::Unauthorized {});
-    }
+    assert_role(deps.storage, Role::ResourceModerator, &info.sender)?;
 
+    let mut diffs = vec![];
     for id in ids {
-        RESOURCE_MAP.update(deps.storage, id, |resource: Option<Resource>|{
-            let mut resource = resource.ok_or(ContractError::NotFound)?;
+        let mut resource = RESOURCE_MAP.may_load(deps.storage, id.clone())?.ok_or(ContractError::NotFound)?;
+        let old = resource.get_status();
 
-            resource.use_resource()?;
+        resource.use_resource()?;
+        save_resource(deps.storage, &resource)?;
 
-            Ok::<Resource, ContractError>(resource)
-        })?;
+        diffs.push(ResourceDiff::new(id, Some(old), Some(resource.get_status())));
     }
 
-    Ok(Response::new().add_attribute("action", "use_resources"))
+    let messages = prepare_resource_hooks(deps.storage, diffs)?;
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attribute("action", "use_resources"))
 }
 
 // 更新一组资源
@@ -63,26 +79,26 @@ pub fn update_resources(
     ids: Vec<String>,
     resource_types: Vec<ResourceType>,
 ) -> Result<Response, ContractError> {
-    let admin_list = ADMIN_LIST.load(deps.storage)?;
-    if !admin_list.is_admin(info.sender.as_str()) {
-        return Err(ContractError::Unauthorized {});
-    }
+    assert_role(deps.storage, Role::ResourceModerator, &info.sender)?;
 
     if ids.len() != resource_types.len() {
         return Err(ContractError::OtherError)
     }
 
-    for (id, resource_type) in ids.into_iter().zip(resource_types.into_iter()) {
-        RESOURCE_MAP.update(deps.storage, id, |resource: Option<Resource>|{
-            let mut resource = resource.ok_or(ContractError::NotFound)?;
+    let mut diffs = vec![];
+    for (id, resource_type) in ids.into_iter().zip(resource_types) {
+        let mut resource = RESOURCE_MAP.may_load(deps.storage, id.clone())?.ok_or(ContractError::NotFound)?;
 
-            resource.update_resource(resource_type)?;
+        resource.update_resource(resource_type)?;
+        save_resource(deps.storage, &resource)?;
 
-            Ok::<Resource, ContractError>(resource)
-        })?;
+        diffs.push(ResourceDiff::new(id, Some(resource.get_status()), Some(resource.get_status())));
     }
 
-    Ok(Response::new().add_attribute("action", "update_resources"))
+    let messages = prepare_resource_hooks(deps.storage, diffs)?;
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attribute("action", "update_resources"))
 }
 
 // 添加资源
@@ -93,11 +109,9 @@ pub fn add_resources(
     resources: Vec<Resource>,
 ) -> Result<Response, ContractError> {
     // 检查消息发送者是否为管理员
-    let admin_list = ADMIN_LIST.load(deps.storage)?;
-    if !admin_list.is_admin(info.sender.as_str()) {
-        return Err(ContractError::Unauthorized {});
-    }
+    assert_role(deps.storage, Role::ResourceModerator, &info.sender)?;
 
+    let mut diffs = vec![];
     for resource in resources {
         // 检查资源是否已经存在
         if RESOURCE_MAP.may_load(deps.storage, resource.get_id())?.is_some() {
@@ -105,10 +119,14 @@ pub fn add_resources(
         }
 
         // 将资源添加到 MAP 中
-        RESOURCE_MAP.save(deps.storage, resource.get_id(), &resource)?;
+        save_resource(deps.storage, &resource)?;
+        diffs.push(ResourceDiff::new(resource.get_id(), None, Some(resource.get_status())));
     }
 
-    Ok(Response::new().add_attribute("action", "add_resources"))
+    let messages = prepare_resource_hooks(deps.storage, diffs)?;
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attribute("action", "add_resources"))
 }
 
 // 释放一组资源
@@ -119,23 +137,24 @@ pub fn release_resources(
     ids: Vec<String>,
 ) -> Result<Response, ContractError> {
     // 检查消息发送者是否为管理员
-    let admin_list = ADMIN_LIST.load(deps.storage)?;
-    if !admin_list.is_admin(info.sender.as_str()) {
-        return Err(ContractError::Unauthorized {});
-    }
+    assert_role(deps.storage, Role::ResourceModerator, &info.sender)?;
 
+    let mut diffs = vec![];
     for id in ids {
-        RESOURCE_MAP.update(deps.storage, id, |resource: Option<Resource>|{
-            let mut resource = resource.ok_or(ContractError::NotFound)?;
+        let mut resource = RESOURCE_MAP.may_load(deps.storage, id.clone())?.ok_or(ContractError::NotFound)?;
+        let old = resource.get_status();
 
-            // 释放资源
-            resource.release_resource()?;
+        // 释放资源
+        resource.release_resource()?;
+        save_resource(deps.storage, &resource)?;
 
-            Ok::<Resource, ContractError>(resource)
-        })?;
+        diffs.push(ResourceDiff::new(id, Some(old), Some(resource.get_status())));
     }
 
-    Ok(Response::new().add_attribute("action", "release_resources"))
+    let messages = prepare_resource_hooks(deps.storage, diffs)?;
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attribute("action", "release_resources"))
 }
 
 
@@ -146,11 +165,9 @@ pub fn delete_resources(
     ids: Vec<String>,
 ) -> Result<Response, ContractError> {
     // 检查消息发送者是否为管理员
-    let admin_list = ADMIN_LIST.load(deps.storage)?;
-    if !admin_list.is_admin(info.sender.as_str()) {
-        return Err(ContractError::Unauthorized {});
-    }
+    assert_role(deps.storage, Role::ResourceModerator, &info.sender)?;
 
+    let mut diffs = vec![];
     for id in ids {
         // 检查资源是否存在
         let resource = RESOURCE_MAP.may_load(deps.storage, id.clone())?;
@@ -162,8 +179,12 @@ pub fn delete_resources(
         }
 
         // 从 RESOURCE_MAP 中删除资源
-        RESOURCE_MAP.remove(deps.storage, id);
+        remove_resource(deps.storage, id.clone())?;
+        diffs.push(ResourceDiff::new(id, Some(resource.get_status()), None));
     }
 
-    Ok(Response::new().add_attribute("action", "delete_resources"))
+    let messages = prepare_resource_hooks(deps.storage, diffs)?;
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attribute("action", "delete_resources"))
 }
\ No newline at end of file
@@ -41,16 +41,8 @@ impl ResourceDetails {
         ResourceDetails{total, used: 0, unit_price}
     }
 
-    pub fn default() -> Self {
-        ResourceDetails{
-            total: 0,
-            used: 0,
-            unit_price: 0
-        }
-    }
-
     pub fn get_total(&self) -> u128 {
-        return self.total
+        self.total
     }
 
     pub fn set_total(&mut self, total: u128) {
@@ -62,7 +54,7 @@ impl ResourceDetails {
     }
 
     pub fn get_used(&self) -> u128 {
-        return self.used
+        self.used
     }
 
     pub fn add_used(&mut self, used: u128) -> Result<(), ContractError> {
@@ -0,0 +1,137 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Deps, DepsMut, Empty, MessageInfo, Order, Response, StdResult, Storage};
+use cw_storage_plus::Map;
+
+use crate::msg::{RoleMembers, RolesResponse};
+use crate::state::ADMIN_LIST;
+use crate::ContractError;
+
+/// Permissions that can be granted to an address independently of the admin list.
+/// Admins only manage the role sets, they need to hold a role to use it.
+#[cw_serde]
+#[derive(Copy, Eq)]
+pub enum Role {
+    /// can relay arbitrary messages with `Execute`
+    ProxyExecutor,
+    /// can add, update and delete marketplace resources
+    ResourceModerator,
+    /// can resolve orders flagged as exceptions
+    DisputeArbiter,
+    /// can set the marketplace fees
+    FeeManager,
+}
+
+impl Role {
+    pub const ALL: [Role; 4] = [
+        Role::ProxyExecutor,
+        Role::ResourceModerator,
+        Role::DisputeArbiter,
+        Role::FeeManager,
+    ];
+
+    fn key(&self) -> &'static str {
+        match self {
+            Role::ProxyExecutor => "proxy_executor",
+            Role::ResourceModerator => "resource_moderator",
+            Role::DisputeArbiter => "dispute_arbiter",
+            Role::FeeManager => "fee_manager",
+        }
+    }
+}
+
+// (角色, 地址) -> 该地址拥有此角色
+pub const ROLES: Map<(&str, &Addr), Empty> = Map::new("roles");
+
+pub fn has_role(storage: &dyn Storage, role: Role, addr: &Addr) -> bool {
+    ROLES.has(storage, (role.key(), addr))
+}
+
+/// returns Unauthorized unless the address holds the role
+pub fn assert_role(storage: &dyn Storage, role: Role, addr: &Addr) -> Result<(), ContractError> {
+    if has_role(storage, role, addr) {
+        Ok(())
+    } else {
+        Err(ContractError::Unauthorized {})
+    }
+}
+
+/// grants every role to each of the addresses
+pub fn grant_all_roles(storage: &mut dyn Storage, addrs: &[Addr]) -> StdResult<()> {
+    for role in Role::ALL {
+        for addr in addrs {
+            ROLES.save(storage, (role.key(), addr), &Empty {})?;
+        }
+    }
+    Ok(())
+}
+
+/// keeps the roles in line with the admin set: new admins get every role and removed
+/// admins lose all of theirs, other role holders are left alone
+pub fn sync_admin_roles(storage: &mut dyn Storage, old: &[Addr], new: &[Addr]) -> StdResult<()> {
+    for role in Role::ALL {
+        for addr in old.iter().filter(|addr| !new.contains(addr)) {
+            ROLES.remove(storage, (role.key(), addr));
+        }
+    }
+    let added: Vec<Addr> = new.iter().filter(|addr| !old.contains(addr)).cloned().collect();
+    grant_all_roles(storage, &added)
+}
+
+pub fn grant_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    role: Role,
+    addr: String,
+) -> Result<Response, ContractError> {
+    let cfg = ADMIN_LIST.load(deps.storage)?;
+    if !cfg.can_modify(info.sender.as_ref()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let addr = deps.api.addr_validate(&addr)?;
+    ROLES.save(deps.storage, (role.key(), &addr), &Empty {})?;
+
+    Ok(Response::new()
+        .add_attribute("action", "grant_role")
+        .add_attribute("role", role.key())
+        .add_attribute("addr", addr))
+}
+
+pub fn revoke_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    role: Role,
+    addr: String,
+) -> Result<Response, ContractError> {
+    let cfg = ADMIN_LIST.load(deps.storage)?;
+    if !cfg.can_modify(info.sender.as_ref()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let addr = deps.api.addr_validate(&addr)?;
+    if !ROLES.has(deps.storage, (role.key(), &addr)) {
+        return Err(ContractError::NotFound);
+    }
+    ROLES.remove(deps.storage, (role.key(), &addr));
+
+    Ok(Response::new()
+        .add_attribute("action", "revoke_role")
+        .add_attribute("role", role.key())
+        .add_attribute("addr", addr))
+}
+
+pub fn query_roles(deps: Deps) -> StdResult<RolesResponse> {
+    let roles = Role::ALL
+        .into_iter()
+        .map(|role| {
+            let members = ROLES
+                .prefix(role.key())
+                .keys(deps.storage, None, None, Order::Ascending)
+                .map(|addr| addr.map(String::from))
+                .collect::<StdResult<Vec<_>>>()?;
+            Ok(RoleMembers { role, members })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(RolesResponse { roles })
+}
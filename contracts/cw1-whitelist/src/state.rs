@@ -2,6 +2,7 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::Addr;
+use cw_controllers::Hooks;
 use cw_storage_plus::Item;
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema, Debug, Default)]
@@ -24,6 +25,10 @@ impl AdminList {
 }
 
 pub const ADMIN_LIST: Item<AdminList> = Item::new("admin_list");
+/// contracts informed of every order status change
+pub const ORDER_HOOKS: Hooks = Hooks::new("order_hooks");
+/// contracts informed of every resource being added, updated or deleted
+pub const RESOURCE_HOOKS: Hooks = Hooks::new("resource_hooks");
 
 #[cfg(test)]
 mod tests {
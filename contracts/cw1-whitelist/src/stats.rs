@@ -0,0 +1,220 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal, Deps, Order, StdResult, Storage, Uint128, Uint64};
+use cw_storage_plus::{Bound, Item, Map};
+
+use crate::msg::{CapacityResponse, MarketStatsResponse, ProviderRevenueResponse, StatCount};
+use crate::order_type::{Order as MarketOrder, OrderStatus};
+use crate::resource_type::{Region, Resource, Status};
+
+// 每种资源属性的总容量及正在出租的容量
+#[cw_serde]
+#[derive(Default)]
+pub struct Capacity {
+    pub total: Uint128,
+    pub rented: Uint128,
+}
+
+pub const RESOURCES_BY_STATUS: Map<String, u64> = Map::new("stats_resources_by_status");
+pub const RESOURCES_BY_REGION: Map<String, u64> = Map::new("stats_resources_by_region");
+pub const CAPACITY: Map<&str, Capacity> = Map::new("stats_capacity");
+pub const ACTIVE_ORDERS: Item<u64> = Item::new("stats_active_orders");
+pub const ESCROWED: Item<Uint128> = Item::new("stats_escrowed");
+// (提供者, 区块高度) -> 截至该高度（含）支付给提供者的累计金额
+pub const PROVIDER_REVENUE: Map<(&Addr, u64), Uint128> = Map::new("stats_provider_revenue");
+
+const CPU: &str = "cpu";
+const MEMORY: &str = "memory";
+const BANDWIDTH: &str = "bandwidth";
+
+fn status_key(status: &Status) -> String {
+    format!("{:?}", status)
+}
+
+fn region_key(region: &Region) -> String {
+    match region {
+        Region::Other(name) => name.clone(),
+        region => format!("{:?}", region),
+    }
+}
+
+fn add_count(
+    storage: &mut dyn Storage,
+    map: Map<String, u64>,
+    key: String,
+    add: bool,
+) -> StdResult<()> {
+    let count = Uint64::new(map.may_load(storage, key.clone())?.unwrap_or_default());
+    let count = if add {
+        count.checked_add(Uint64::one())?
+    } else {
+        count.checked_sub(Uint64::one())?
+    }
+    .u64();
+    if count == 0 {
+        map.remove(storage, key);
+        Ok(())
+    } else {
+        map.save(storage, key, &count)
+    }
+}
+
+fn add_capacity(
+    storage: &mut dyn Storage,
+    key: &str,
+    total: u128,
+    rented: bool,
+    add: bool,
+) -> StdResult<()> {
+    let mut capacity = CAPACITY.may_load(storage, key)?.unwrap_or_default();
+    let rented_total = if rented { total } else { 0 };
+    if add {
+        capacity.total = capacity.total.checked_add(Uint128::new(total))?;
+        capacity.rented = capacity.rented.checked_add(Uint128::new(rented_total))?;
+    } else {
+        capacity.total = capacity.total.checked_sub(Uint128::new(total))?;
+        capacity.rented = capacity.rented.checked_sub(Uint128::new(rented_total))?;
+    }
+    CAPACITY.save(storage, key, &capacity)
+}
+
+fn apply_resource(storage: &mut dyn Storage, resource: &Resource, add: bool) -> StdResult<()> {
+    let attr = resource.get_resource_attr();
+    let rented = resource.check_status(Status::Used);
+
+    add_count(
+        storage,
+        RESOURCES_BY_STATUS,
+        status_key(&resource.get_status()),
+        add,
+    )?;
+    add_count(storage, RESOURCES_BY_REGION, region_key(&attr.region), add)?;
+    add_capacity(storage, CPU, attr.cpu.get_total(), rented, add)?;
+    add_capacity(storage, MEMORY, attr.memory.get_total(), rented, add)?;
+    add_capacity(storage, BANDWIDTH, attr.bandwidth.get_total(), rented, add)
+}
+
+/// removes the contribution of the old resource and adds the one of the new resource
+pub fn update_resource_stats(
+    storage: &mut dyn Storage,
+    old: Option<&Resource>,
+    new: Option<&Resource>,
+) -> StdResult<()> {
+    if let Some(old) = old {
+        apply_resource(storage, old, false)?;
+    }
+    if let Some(new) = new {
+        apply_resource(storage, new, true)?;
+    }
+    Ok(())
+}
+
+// 活跃订单及异常订单的资金仍锁定在合约中
+fn holds_escrow(order: &MarketOrder) -> bool {
+    matches!(order.status, OrderStatus::Active | OrderStatus::Exception)
+}
+
+/// only active orders are counted, while both active and exception orders hold escrowed funds
+pub fn update_order_stats(
+    storage: &mut dyn Storage,
+    old: Option<&MarketOrder>,
+    new: Option<&MarketOrder>,
+) -> StdResult<()> {
+    let mut active = Uint64::new(ACTIVE_ORDERS.may_load(storage)?.unwrap_or_default());
+    let mut escrowed = ESCROWED.may_load(storage)?.unwrap_or_default();
+
+    if let Some(old) = old {
+        if old.status == OrderStatus::Active {
+            active = active.checked_sub(Uint64::one())?;
+        }
+        if holds_escrow(old) {
+            escrowed = escrowed.checked_sub(Uint128::new(old.locked_funds))?;
+        }
+    }
+    if let Some(new) = new {
+        if new.status == OrderStatus::Active {
+            active = active.checked_add(Uint64::one())?;
+        }
+        if holds_escrow(new) {
+            escrowed = escrowed.checked_add(Uint128::new(new.locked_funds))?;
+        }
+    }
+
+    ACTIVE_ORDERS.save(storage, &active.u64())?;
+    ESCROWED.save(storage, &escrowed)
+}
+
+/// returns the total paid to the provider up to the given height, or so far if unset
+fn revenue_until(storage: &dyn Storage, provider: &Addr, height: Option<u64>) -> StdResult<Uint128> {
+    let total = PROVIDER_REVENUE
+        .prefix(provider)
+        .range(storage, None, height.map(Bound::inclusive), Order::Descending)
+        .next()
+        .transpose()?
+        .map(|(_, total)| total);
+    Ok(total.unwrap_or_default())
+}
+
+/// records a payment made to a provider at the given height, which is never below the
+/// height of the previous payment
+pub fn record_revenue(
+    storage: &mut dyn Storage,
+    provider: &Addr,
+    height: u64,
+    amount: u128,
+) -> StdResult<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let total = revenue_until(storage, provider, None)?.checked_add(Uint128::new(amount))?;
+    PROVIDER_REVENUE.save(storage, (provider, height), &total)
+}
+
+fn capacity_response(storage: &dyn Storage, key: &str) -> StdResult<CapacityResponse> {
+    let capacity = CAPACITY.may_load(storage, key)?.unwrap_or_default();
+    let utilization = if capacity.total.is_zero() {
+        Decimal::zero()
+    } else {
+        Decimal::from_ratio(capacity.rented, capacity.total)
+    };
+    Ok(CapacityResponse {
+        total: capacity.total,
+        rented: capacity.rented,
+        utilization,
+    })
+}
+
+fn counts(storage: &dyn Storage, map: Map<String, u64>) -> StdResult<Vec<StatCount>> {
+    map.range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(key, count)| StatCount { key, count }))
+        .collect()
+}
+
+pub fn query_market_stats(deps: Deps) -> StdResult<MarketStatsResponse> {
+    Ok(MarketStatsResponse {
+        resources_by_status: counts(deps.storage, RESOURCES_BY_STATUS)?,
+        resources_by_region: counts(deps.storage, RESOURCES_BY_REGION)?,
+        cpu: capacity_response(deps.storage, CPU)?,
+        memory: capacity_response(deps.storage, MEMORY)?,
+        bandwidth: capacity_response(deps.storage, BANDWIDTH)?,
+        active_orders: ACTIVE_ORDERS.may_load(deps.storage)?.unwrap_or_default(),
+        escrowed: ESCROWED.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
+
+pub fn query_provider_revenue(
+    deps: Deps,
+    provider: String,
+    start_height: Option<u64>,
+    end_height: Option<u64>,
+) -> StdResult<ProviderRevenueResponse> {
+    let addr = deps.api.addr_validate(&provider)?;
+    // the totals are cumulative, so the range costs two lookups whatever the history
+    let until_end = revenue_until(deps.storage, &addr, end_height)?;
+    let before_start = match start_height {
+        Some(0) | None => Uint128::zero(),
+        Some(start) => revenue_until(deps.storage, &addr, Some(start - 1))?,
+    };
+    let revenue = until_end.saturating_sub(before_start);
+
+    Ok(ProviderRevenueResponse { provider, revenue })
+}
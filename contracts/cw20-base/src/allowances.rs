@@ -5,7 +5,13 @@ use cosmwasm_std::{
 use cw20::{AllowanceResponse, Cw20ReceiveMsg, Expiration};
 
 use crate::error::ContractError;
-use crate::state::{ALLOWANCES, ALLOWANCES_SPENDER, BALANCES, TOKEN_INFO};
+use crate::group::update_members;
+use crate::hooks::prepare_transfer_hooks;
+use crate::pause::{assert_not_frozen, assert_not_paused};
+use crate::state::{
+    add_balance, save_token_info, sub_balance, ALLOWANCES, ALLOWANCES_SPENDER, TOKEN_INFO,
+};
+use crate::vesting::assert_vested;
 
 pub fn execute_increase_allowance(
     deps: DepsMut,
@@ -131,30 +137,28 @@ pub fn execute_transfer_from(
 ) -> Result<Response, ContractError> {
     let rcpt_addr = deps.api.addr_validate(&recipient)?;
     let owner_addr = deps.api.addr_validate(&owner)?;
+    assert_not_paused(deps.storage)?;
+    assert_not_frozen(deps.storage, &[&owner_addr, &info.sender, &rcpt_addr])?;
 
     // deduct allowance before doing anything else have enough allowance
     deduct_allowance(deps.storage, &owner_addr, &info.sender, &env.block, amount)?;
 
-    BALANCES.update(
-        deps.storage,
-        &owner_addr,
-        |balance: Option<Uint128>| -> StdResult<_> {
-            Ok(balance.unwrap_or_default().checked_sub(amount)?)
-        },
-    )?;
-    BALANCES.update(
-        deps.storage,
-        &rcpt_addr,
-        |balance: Option<Uint128>| -> StdResult<_> { Ok(balance.unwrap_or_default() + amount) },
-    )?;
-
-    let res = Response::new().add_attributes(vec![
-        attr("action", "transfer_from"),
-        attr("from", owner),
-        attr("to", recipient),
-        attr("by", info.sender),
-        attr("amount", amount),
-    ]);
+    assert_vested(deps.storage, &env.block, &owner_addr, amount)?;
+    let transfer_hooks = prepare_transfer_hooks(deps.as_ref(), &owner_addr, &rcpt_addr, amount)?;
+    sub_balance(deps.storage, &owner_addr, amount, env.block.height)?;
+    add_balance(deps.storage, &rcpt_addr, amount, env.block.height)?;
+    let hooks = update_members(deps.storage, env.block.height, &[&owner_addr, &rcpt_addr])?;
+
+    let res = Response::new()
+        .add_submessages(hooks)
+        .add_submessages(transfer_hooks)
+        .add_attributes(vec![
+            attr("action", "transfer_from"),
+            attr("from", owner),
+            attr("to", recipient),
+            attr("by", info.sender),
+            attr("amount", amount),
+        ]);
     Ok(res)
 }
 
@@ -167,25 +171,25 @@ pub fn execute_burn_from(
     amount: Uint128,
 ) -> Result<Response, ContractError> {
     let owner_addr = deps.api.addr_validate(&owner)?;
+    assert_not_paused(deps.storage)?;
+    assert_not_frozen(deps.storage, &[&owner_addr, &info.sender])?;
+    assert_vested(deps.storage, &env.block, &owner_addr, amount)?;
 
     // deduct allowance before doing anything else have enough allowance
     deduct_allowance(deps.storage, &owner_addr, &info.sender, &env.block, amount)?;
 
     // lower balance
-    BALANCES.update(
-        deps.storage,
-        &owner_addr,
-        |balance: Option<Uint128>| -> StdResult<_> {
-            Ok(balance.unwrap_or_default().checked_sub(amount)?)
-        },
-    )?;
+    sub_balance(deps.storage, &owner_addr, amount, env.block.height)?;
     // reduce total_supply
-    TOKEN_INFO.update(deps.storage, |mut meta| -> StdResult<_> {
-        meta.total_supply = meta.total_supply.checked_sub(amount)?;
-        Ok(meta)
-    })?;
-
-    let res = Response::new().add_attributes(vec![
+    let mut meta = TOKEN_INFO.load(deps.storage)?;
+    meta.total_supply = meta
+        .total_supply
+        .checked_sub(amount)
+        .map_err(StdError::overflow)?;
+    save_token_info(deps.storage, &meta, env.block.height)?;
+    let hooks = update_members(deps.storage, env.block.height, &[&owner_addr])?;
+
+    let res = Response::new().add_submessages(hooks).add_attributes(vec![
         attr("action", "burn_from"),
         attr("from", owner),
         attr("by", info.sender),
@@ -205,23 +209,18 @@ pub fn execute_send_from(
 ) -> Result<Response, ContractError> {
     let rcpt_addr = deps.api.addr_validate(&contract)?;
     let owner_addr = deps.api.addr_validate(&owner)?;
+    assert_not_paused(deps.storage)?;
+    assert_not_frozen(deps.storage, &[&owner_addr, &info.sender, &rcpt_addr])?;
 
     // deduct allowance before doing anything else have enough allowance
     deduct_allowance(deps.storage, &owner_addr, &info.sender, &env.block, amount)?;
 
     // move the tokens to the contract
-    BALANCES.update(
-        deps.storage,
-        &owner_addr,
-        |balance: Option<Uint128>| -> StdResult<_> {
-            Ok(balance.unwrap_or_default().checked_sub(amount)?)
-        },
-    )?;
-    BALANCES.update(
-        deps.storage,
-        &rcpt_addr,
-        |balance: Option<Uint128>| -> StdResult<_> { Ok(balance.unwrap_or_default() + amount) },
-    )?;
+    assert_vested(deps.storage, &env.block, &owner_addr, amount)?;
+    let transfer_hooks = prepare_transfer_hooks(deps.as_ref(), &owner_addr, &rcpt_addr, amount)?;
+    sub_balance(deps.storage, &owner_addr, amount, env.block.height)?;
+    add_balance(deps.storage, &rcpt_addr, amount, env.block.height)?;
+    let hooks = update_members(deps.storage, env.block.height, &[&owner_addr, &rcpt_addr])?;
 
     let attrs = vec![
         attr("action", "send_from"),
@@ -239,7 +238,11 @@ pub fn execute_send_from(
     }
     .into_cosmos_msg(contract)?;
 
-    let res = Response::new().add_message(msg).add_attributes(attrs);
+    let res = Response::new()
+        .add_submessages(hooks)
+        .add_submessages(transfer_hooks)
+        .add_message(msg)
+        .add_attributes(attrs);
     Ok(res)
 }
 
@@ -260,8 +263,9 @@ mod tests {
     use cosmwasm_std::{coins, CosmosMsg, SubMsg, Timestamp, WasmMsg};
     use cw20::{Cw20Coin, TokenInfoResponse};
 
-    use crate::contract::{execute, instantiate, query_balance, query_token_info};
-    use crate::msg::{ExecuteMsg, InstantiateMsg};
+    use crate::contract::tests::execute;
+    use crate::contract::{instantiate, query_balance, query_token_info};
+    use crate::msg::{ExecuteMsg, ExtensionMsg, InstantiateMsg};
 
     fn get_balance<T: Into<String>>(deps: Deps, address: T) -> Uint128 {
         query_balance(deps, address.into()).unwrap().balance
@@ -283,6 +287,7 @@ mod tests {
             }],
             mint: None,
             marketing: None,
+            ..InstantiateMsg::default()
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
@@ -876,4 +881,112 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn permit_sets_allowance() {
+        use cosmwasm_std::{to_json_vec, Api, CanonicalAddr};
+        use k256::ecdsa::signature::Signer;
+        use k256::ecdsa::{Signature, SigningKey};
+        use ripemd::Ripemd160;
+        use sha2::{Digest, Sha256};
+
+        use crate::msg::{PermitMsg, PermitPayload};
+        use crate::permit::query_nonce;
+
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+        let signing_key = SigningKey::from_bytes(&[7u8; 32].into()).unwrap();
+        let pubkey = Binary::from(signing_key.verifying_key().to_sec1_bytes().to_vec());
+        let key_hash = Ripemd160::digest(Sha256::digest(&pubkey));
+        let owner = deps
+            .api
+            .addr_humanize(&CanonicalAddr::from(key_hash.as_slice()))
+            .unwrap()
+            .to_string();
+        let spender = deps.api.addr_make("addr0002").to_string();
+        let relayer = deps.api.addr_make("relayer").to_string();
+        let env = mock_env();
+        do_instantiate(deps.as_mut(), owner.clone(), Uint128::new(12340000));
+
+        let deadline = Expiration::AtHeight(env.block.height + 10);
+        let permit = |amount: u128, nonce: u64| {
+            let payload = PermitPayload {
+                chain_id: env.block.chain_id.clone(),
+                contract: env.contract.address.to_string(),
+                owner: owner.clone(),
+                spender: spender.clone(),
+                amount: Uint128::new(amount),
+                expires: None,
+                deadline,
+                nonce,
+            };
+            let signature: Signature = signing_key.sign(&to_json_vec(&payload).unwrap());
+            PermitMsg {
+                owner: owner.clone(),
+                spender: spender.clone(),
+                amount: Uint128::new(amount),
+                expires: None,
+                deadline,
+                nonce,
+                signature: signature.to_bytes().to_vec().into(),
+                pubkey: pubkey.clone(),
+            }
+        };
+
+        // anyone can relay the permit
+        let info = mock_info(&relayer, &[]);
+        let msg = ExtensionMsg::Permit(permit(5000, 0));
+        execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+        let allowance = query_allowance(deps.as_ref(), owner.clone(), spender.clone()).unwrap();
+        assert_eq!(allowance.allowance, Uint128::new(5000));
+        assert_eq!(query_nonce(deps.as_ref(), owner.clone()).unwrap().nonce, 1);
+
+        // it cannot be replayed
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidPermitNonce {});
+
+        // signature over other terms
+        let mut tampered = permit(3000, 1);
+        tampered.amount = Uint128::new(9000);
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExtensionMsg::Permit(tampered),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidPermitSignature {});
+
+        // key of another account
+        let mut other = permit(3000, 1);
+        other.owner = relayer.clone();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExtensionMsg::Permit(other),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::PermitKeyMismatch {});
+
+        // the allowance is set, not increased
+        let msg = ExtensionMsg::Permit(permit(3000, 1));
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let allowance = query_allowance(deps.as_ref(), owner.clone(), spender.clone()).unwrap();
+        assert_eq!(allowance.allowance, Uint128::new(3000));
+        assert_eq!(query_nonce(deps.as_ref(), owner.clone()).unwrap().nonce, 2);
+
+        // permits cannot be submitted after their deadline
+        let mut late = env.clone();
+        late.block.height += 10;
+        let msg = ExtensionMsg::Permit(permit(1000, 2));
+        let err = execute(deps.as_mut(), late, info.clone(), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::PermitExpired {});
+
+        // and the owner can cancel the ones they handed out
+        let cancel = ExtensionMsg::IncreaseNonce {};
+        execute(deps.as_mut(), env.clone(), mock_info(&owner, &[]), cancel).unwrap();
+        assert_eq!(query_nonce(deps.as_ref(), owner).unwrap().nonce, 3);
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidPermitNonce {});
+    }
 }
@@ -2,25 +2,48 @@
 use cosmwasm_std::entry_point;
 use cosmwasm_std::Order::Ascending;
 use cosmwasm_std::{
-    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128,
+    to_json_binary, Addr, Binary, Deps, DepsMut, Env, Event, MessageInfo, Response, StdError,
+    StdResult, Uint128,
 };
 
 use cw2::{ensure_from_older_version, set_contract_version};
 use cw20::{
-    BalanceResponse, Cw20Coin, Cw20ReceiveMsg, DownloadLogoResponse, EmbeddedLogo, Logo, LogoInfo,
-    MarketingInfoResponse, MinterResponse, TokenInfoResponse,
+    BalanceResponse, Cw20Coin, Cw20ReceiveMsg, Cw20Send, DownloadLogoResponse, EmbeddedLogo, Logo,
+    LogoInfo, MarketingInfoResponse, MinterResponse, TokenInfoResponse,
 };
 
 use crate::allowances::{
     execute_burn_from, execute_decrease_allowance, execute_increase_allowance, execute_send_from,
     execute_transfer_from, query_allowance,
 };
-use crate::enumerable::{query_all_accounts, query_owner_allowances, query_spender_allowances};
+use crate::enumerable::{
+    query_all_accounts, query_all_balances, query_holder_count, query_owner_allowances,
+    query_spender_allowances, query_top_holders,
+};
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::group::{query_list_members, query_member, query_total_weight, update_members};
+use crate::hooks::prepare_transfer_hooks;
+use crate::minters::{execute_add_minter, execute_remove_minter, query_minters, spend_minter_cap};
+use crate::msg::{
+    ContractExecuteMsg, ExecuteMsg, ExtensionMsg, InstantiateMsg, MigrateMsg, QueryMsg,
+};
+use crate::pause::{
+    assert_not_frozen, assert_not_paused, execute_set_frozen, execute_set_paused,
+    execute_update_pauser, query_frozen_accounts, query_pause_info,
+};
+use crate::permit::{execute_increase_nonce, execute_permit, query_nonce};
+use crate::rewards::{
+    execute_add_reward_denom, execute_claim_rewards, execute_deposit_rewards, execute_receive,
+    execute_remove_reward_denom, query_pending_rewards, query_reward_denoms,
+};
 use crate::state::{
-    MinterData, TokenInfo, ALLOWANCES, ALLOWANCES_SPENDER, BALANCES, LOGO, MARKETING_INFO,
-    TOKEN_INFO,
+    add_balance, save_token_info, snapshots_enabled, sub_balance, MinterData, TokenInfo, ADMIN,
+    ALLOWANCES, ALLOWANCES_SPENDER, BALANCES, BALANCE_SNAPSHOTS, GROUP_CONFIG,
+    HOLDER_INDEX_ENABLED, LOGO, MARKETING_INFO, MEMBER_HOOKS, PAUSER, SNAPSHOTS_ENABLED,
+    TOKEN_INFO, TOTAL_SUPPLY_SNAPSHOTS, TOTAL_WEIGHT, TRANSFER_CHECKS, TRANSFER_HOOKS,
+};
+use crate::vesting::{
+    assert_vested, create_vesting_accounts, execute_mint_vesting, query_vesting_schedule,
 };
 
 // version info for migration info
@@ -93,15 +116,38 @@ fn verify_logo(logo: &Logo) -> Result<(), ContractError> {
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     mut deps: DepsMut,
-    _env: Env,
+    env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     // check valid token info
     msg.validate()?;
+    if msg.enable_snapshots {
+        SNAPSHOTS_ENABLED.save(deps.storage, &true)?;
+    }
+    if msg.enable_holder_index {
+        HOLDER_INDEX_ENABLED.save(deps.storage, &true)?;
+    }
+    let admin = msg
+        .admin
+        .as_ref()
+        .map(|admin| deps.api.addr_validate(admin))
+        .transpose()?;
+    ADMIN.set(deps.branch(), admin)?;
+    let pauser = msg
+        .pauser
+        .as_ref()
+        .map(|pauser| deps.api.addr_validate(pauser))
+        .transpose()?;
+    PAUSER.set(deps.branch(), pauser)?;
+    if let Some(group) = &msg.group {
+        GROUP_CONFIG.save(deps.storage, group)?;
+        TOTAL_WEIGHT.save(deps.storage, &0, env.block.height)?;
+    }
     // create initial accounts
-    let total_supply = create_accounts(&mut deps, &msg.initial_balances)?;
+    let mut total_supply = create_accounts(&mut deps, &msg.initial_balances, env.block.height)?;
+    total_supply += create_vesting_accounts(&mut deps, &msg.initial_vesting, &env.block)?;
 
     if let Some(limit) = msg.get_cap() {
         if total_supply > limit {
@@ -125,7 +171,7 @@ pub fn instantiate(
         total_supply,
         mint,
     };
-    TOKEN_INFO.save(deps.storage, &data)?;
+    save_token_info(deps.storage, &data, env.block.height)?;
 
     if let Some(marketing) = msg.marketing {
         let logo = if let Some(logo) = marketing.logo {
@@ -158,15 +204,20 @@ pub fn instantiate(
 pub fn create_accounts(
     deps: &mut DepsMut,
     accounts: &[Cw20Coin],
+    height: u64,
 ) -> Result<Uint128, ContractError> {
     validate_accounts(accounts)?;
 
     let mut total_supply = Uint128::zero();
+    let mut holders = vec![];
     for row in accounts {
         let address = deps.api.addr_validate(&row.address)?;
-        BALANCES.save(deps.storage, &address, &row.amount)?;
+        add_balance(deps.storage, &address, row.amount, height)?;
         total_supply += row.amount;
+        holders.push(address);
     }
+    // no hooks can be registered yet
+    update_members(deps.storage, height, &holders.iter().collect::<Vec<_>>())?;
 
     Ok(total_supply)
 }
@@ -185,6 +236,19 @@ pub fn validate_accounts(accounts: &[Cw20Coin]) -> Result<(), ContractError> {
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ContractExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ContractExecuteMsg::Base(msg) => execute_base(deps, env, info, msg),
+        ContractExecuteMsg::Extension(msg) => execute_extension(deps, env, info, msg),
+    }
+}
+
+/// Executes the messages of the cw20 spec
+pub fn execute_base(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
@@ -194,6 +258,10 @@ pub fn execute(
         ExecuteMsg::Transfer { recipient, amount } => {
             execute_transfer(deps, env, info, recipient, amount)
         }
+        ExecuteMsg::BatchTransfer { transfers } => {
+            execute_batch_transfer(deps, env, info, transfers)
+        }
+        ExecuteMsg::BatchSend { sends } => execute_batch_send(deps, env, info, sends),
         ExecuteMsg::Burn { amount } => execute_burn(deps, env, info, amount),
         ExecuteMsg::Send {
             contract,
@@ -235,29 +303,91 @@ pub fn execute(
     }
 }
 
+/// Executes the messages of the extensions beyond the cw20 spec
+pub fn execute_extension(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExtensionMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExtensionMsg::MintVesting {
+            recipient,
+            schedule,
+        } => execute_mint_vesting(deps, env, info, recipient, schedule),
+        ExtensionMsg::Permit(permit) => execute_permit(deps, env, permit),
+        ExtensionMsg::IncreaseNonce {} => execute_increase_nonce(deps, info),
+        ExtensionMsg::AddMinter {
+            minter,
+            cap,
+            expires,
+        } => execute_add_minter(deps, &env.block, info, minter, cap, expires),
+        ExtensionMsg::RemoveMinter { minter } => execute_remove_minter(deps, info, minter),
+        ExtensionMsg::Pause {} => execute_set_paused(deps, info, true),
+        ExtensionMsg::Unpause {} => execute_set_paused(deps, info, false),
+        ExtensionMsg::Freeze { address } => execute_set_frozen(deps, info, address, true),
+        ExtensionMsg::Unfreeze { address } => execute_set_frozen(deps, info, address, false),
+        ExtensionMsg::UpdatePauser { pauser } => execute_update_pauser(deps, info, pauser),
+        ExtensionMsg::UpdateAdmin { admin } => {
+            let admin = admin
+                .map(|admin| deps.api.addr_validate(&admin))
+                .transpose()?;
+            Ok(ADMIN.execute_update_admin(deps, info, admin)?)
+        }
+        ExtensionMsg::AddHook { addr } => {
+            let addr = deps.api.addr_validate(&addr)?;
+            Ok(MEMBER_HOOKS.execute_add_hook(&ADMIN, deps, info, addr)?)
+        }
+        ExtensionMsg::RemoveHook { addr } => {
+            let addr = deps.api.addr_validate(&addr)?;
+            Ok(MEMBER_HOOKS.execute_remove_hook(&ADMIN, deps, info, addr)?)
+        }
+        ExtensionMsg::AddTransferHook { addr } => {
+            let addr = deps.api.addr_validate(&addr)?;
+            Ok(TRANSFER_HOOKS.execute_add_hook(&ADMIN, deps, info, addr)?)
+        }
+        ExtensionMsg::RemoveTransferHook { addr } => {
+            let addr = deps.api.addr_validate(&addr)?;
+            Ok(TRANSFER_HOOKS.execute_remove_hook(&ADMIN, deps, info, addr)?)
+        }
+        ExtensionMsg::AddTransferCheck { addr } => {
+            let addr = deps.api.addr_validate(&addr)?;
+            Ok(TRANSFER_CHECKS.execute_add_hook(&ADMIN, deps, info, addr)?)
+        }
+        ExtensionMsg::RemoveTransferCheck { addr } => {
+            let addr = deps.api.addr_validate(&addr)?;
+            Ok(TRANSFER_CHECKS.execute_remove_hook(&ADMIN, deps, info, addr)?)
+        }
+        ExtensionMsg::AddRewardDenom { denom, min_deposit } => {
+            execute_add_reward_denom(deps, info, denom, min_deposit)
+        }
+        ExtensionMsg::RemoveRewardDenom { denom } => execute_remove_reward_denom(deps, info, denom),
+        ExtensionMsg::DepositRewards {} => execute_deposit_rewards(deps, env, info),
+        ExtensionMsg::Receive(msg) => execute_receive(deps, env, info, msg),
+        ExtensionMsg::ClaimRewards { denom } => execute_claim_rewards(deps, info, denom),
+    }
+}
+
 pub fn execute_transfer(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     recipient: String,
     amount: Uint128,
 ) -> Result<Response, ContractError> {
     let rcpt_addr = deps.api.addr_validate(&recipient)?;
+    assert_not_paused(deps.storage)?;
+    assert_not_frozen(deps.storage, &[&info.sender, &rcpt_addr])?;
 
-    BALANCES.update(
-        deps.storage,
-        &info.sender,
-        |balance: Option<Uint128>| -> StdResult<_> {
-            Ok(balance.unwrap_or_default().checked_sub(amount)?)
-        },
-    )?;
-    BALANCES.update(
-        deps.storage,
-        &rcpt_addr,
-        |balance: Option<Uint128>| -> StdResult<_> { Ok(balance.unwrap_or_default() + amount) },
-    )?;
+    assert_vested(deps.storage, &env.block, &info.sender, amount)?;
+    let transfer_hooks = prepare_transfer_hooks(deps.as_ref(), &info.sender, &rcpt_addr, amount)?;
+    sub_balance(deps.storage, &info.sender, amount, env.block.height)?;
+    add_balance(deps.storage, &rcpt_addr, amount, env.block.height)?;
+    let hooks = update_members(deps.storage, env.block.height, &[&info.sender, &rcpt_addr])?;
 
     let res = Response::new()
+        .add_submessages(hooks)
+        .add_submessages(transfer_hooks)
         .add_attribute("action", "transfer")
         .add_attribute("from", info.sender)
         .add_attribute("to", recipient)
@@ -267,25 +397,27 @@ pub fn execute_transfer(
 
 pub fn execute_burn(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     amount: Uint128,
 ) -> Result<Response, ContractError> {
+    assert_not_paused(deps.storage)?;
+    assert_not_frozen(deps.storage, &[&info.sender])?;
+    assert_vested(deps.storage, &env.block, &info.sender, amount)?;
+
     // lower balance
-    BALANCES.update(
-        deps.storage,
-        &info.sender,
-        |balance: Option<Uint128>| -> StdResult<_> {
-            Ok(balance.unwrap_or_default().checked_sub(amount)?)
-        },
-    )?;
+    sub_balance(deps.storage, &info.sender, amount, env.block.height)?;
     // reduce total_supply
-    TOKEN_INFO.update(deps.storage, |mut info| -> StdResult<_> {
-        info.total_supply = info.total_supply.checked_sub(amount)?;
-        Ok(info)
-    })?;
+    let mut config = TOKEN_INFO.load(deps.storage)?;
+    config.total_supply = config
+        .total_supply
+        .checked_sub(amount)
+        .map_err(StdError::overflow)?;
+    save_token_info(deps.storage, &config, env.block.height)?;
+    let hooks = update_members(deps.storage, env.block.height, &[&info.sender])?;
 
     let res = Response::new()
+        .add_submessages(hooks)
         .add_attribute("action", "burn")
         .add_attribute("from", info.sender)
         .add_attribute("amount", amount);
@@ -294,7 +426,7 @@ pub fn execute_burn(
 
 pub fn execute_mint(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     recipient: String,
     amount: Uint128,
@@ -303,16 +435,16 @@ pub fn execute_mint(
         .may_load(deps.storage)?
         .ok_or(ContractError::Unauthorized {})?;
 
-    if config
-        .mint
-        .as_ref()
-        .ok_or(ContractError::Unauthorized {})?
-        .minter
-        != info.sender
-    {
-        return Err(ContractError::Unauthorized {});
+    // registered minters only mint as long as the token is mintable
+    let mint = config.mint.as_ref().ok_or(ContractError::Unauthorized {})?;
+    // the main minter has no cap of its own, others need to be registered
+    if mint.minter != info.sender {
+        spend_minter_cap(deps.storage, &env.block, &info.sender, amount)?;
     }
 
+    let rcpt_addr = deps.api.addr_validate(&recipient)?;
+    assert_not_frozen(deps.storage, &[&rcpt_addr])?;
+
     // update supply and enforce cap
     config.total_supply += amount;
     if let Some(limit) = config.get_cap() {
@@ -320,17 +452,14 @@ pub fn execute_mint(
             return Err(ContractError::CannotExceedCap {});
         }
     }
-    TOKEN_INFO.save(deps.storage, &config)?;
+    save_token_info(deps.storage, &config, env.block.height)?;
 
     // add amount to recipient balance
-    let rcpt_addr = deps.api.addr_validate(&recipient)?;
-    BALANCES.update(
-        deps.storage,
-        &rcpt_addr,
-        |balance: Option<Uint128>| -> StdResult<_> { Ok(balance.unwrap_or_default() + amount) },
-    )?;
+    add_balance(deps.storage, &rcpt_addr, amount, env.block.height)?;
+    let hooks = update_members(deps.storage, env.block.height, &[&rcpt_addr])?;
 
     let res = Response::new()
+        .add_submessages(hooks)
         .add_attribute("action", "mint")
         .add_attribute("to", recipient)
         .add_attribute("amount", amount);
@@ -339,29 +468,26 @@ pub fn execute_mint(
 
 pub fn execute_send(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     contract: String,
     amount: Uint128,
     msg: Binary,
 ) -> Result<Response, ContractError> {
     let rcpt_addr = deps.api.addr_validate(&contract)?;
+    assert_not_paused(deps.storage)?;
+    assert_not_frozen(deps.storage, &[&info.sender, &rcpt_addr])?;
 
     // move the tokens to the contract
-    BALANCES.update(
-        deps.storage,
-        &info.sender,
-        |balance: Option<Uint128>| -> StdResult<_> {
-            Ok(balance.unwrap_or_default().checked_sub(amount)?)
-        },
-    )?;
-    BALANCES.update(
-        deps.storage,
-        &rcpt_addr,
-        |balance: Option<Uint128>| -> StdResult<_> { Ok(balance.unwrap_or_default() + amount) },
-    )?;
+    assert_vested(deps.storage, &env.block, &info.sender, amount)?;
+    let transfer_hooks = prepare_transfer_hooks(deps.as_ref(), &info.sender, &rcpt_addr, amount)?;
+    sub_balance(deps.storage, &info.sender, amount, env.block.height)?;
+    add_balance(deps.storage, &rcpt_addr, amount, env.block.height)?;
+    let hooks = update_members(deps.storage, env.block.height, &[&info.sender, &rcpt_addr])?;
 
     let res = Response::new()
+        .add_submessages(hooks)
+        .add_submessages(transfer_hooks)
         .add_attribute("action", "send")
         .add_attribute("from", &info.sender)
         .add_attribute("to", &contract)
@@ -377,6 +503,99 @@ pub fn execute_send(
     Ok(res)
 }
 
+/// Moves the tokens out of the sender's balance at once, returning the response with the hook
+/// messages and an event per recipient
+fn move_batch(
+    deps: DepsMut,
+    env: &Env,
+    sender: &Addr,
+    event: &str,
+    batch: &[(Addr, Uint128)],
+) -> Result<Response, ContractError> {
+    assert_not_paused(deps.storage)?;
+    if batch.is_empty() {
+        return Err(ContractError::EmptyBatch {});
+    }
+    let mut accounts = vec![sender];
+    accounts.extend(batch.iter().map(|(rcpt, _)| rcpt));
+    assert_not_frozen(deps.storage, &accounts)?;
+
+    let total = batch
+        .iter()
+        .try_fold(Uint128::zero(), |acc, (_, amount)| acc.checked_add(*amount))
+        .map_err(StdError::overflow)?;
+    assert_vested(deps.storage, &env.block, sender, total)?;
+    let mut transfer_hooks = vec![];
+    for (rcpt, amount) in batch {
+        transfer_hooks.extend(prepare_transfer_hooks(
+            deps.as_ref(),
+            sender,
+            rcpt,
+            *amount,
+        )?);
+    }
+
+    sub_balance(deps.storage, sender, total, env.block.height)?;
+    let mut events = vec![];
+    for (rcpt, amount) in batch {
+        add_balance(deps.storage, rcpt, *amount, env.block.height)?;
+        events.push(
+            Event::new(event)
+                .add_attribute("to", rcpt)
+                .add_attribute("amount", *amount),
+        );
+    }
+    let hooks = update_members(deps.storage, env.block.height, &accounts)?;
+
+    let res = Response::new()
+        .add_submessages(hooks)
+        .add_submessages(transfer_hooks)
+        .add_attribute("from", sender)
+        .add_attribute("amount", total)
+        .add_events(events);
+    Ok(res)
+}
+
+pub fn execute_batch_transfer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    transfers: Vec<Cw20Coin>,
+) -> Result<Response, ContractError> {
+    let batch = transfers
+        .into_iter()
+        .map(|t| Ok((deps.api.addr_validate(&t.address)?, t.amount)))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let res = move_batch(deps, &env, &info.sender, "transfer", &batch)?;
+    Ok(res.add_attribute("action", "batch_transfer"))
+}
+
+pub fn execute_batch_send(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    sends: Vec<Cw20Send>,
+) -> Result<Response, ContractError> {
+    let batch = sends
+        .iter()
+        .map(|s| Ok((deps.api.addr_validate(&s.contract)?, s.amount)))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut res = move_batch(deps, &env, &info.sender, "send", &batch)?;
+    for send in sends {
+        res = res.add_message(
+            Cw20ReceiveMsg {
+                sender: info.sender.to_string(),
+                amount: send.amount,
+                msg: send.msg,
+            }
+            .into_cosmos_msg(send.contract)?,
+        );
+    }
+    Ok(res.add_attribute("action", "batch_send"))
+}
+
 pub fn execute_update_minter(
     deps: DepsMut,
     _env: Env,
@@ -504,14 +723,24 @@ pub fn execute_upload_logo(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Balance { address } => to_json_binary(&query_balance(deps, address)?),
         QueryMsg::TokenInfo {} => to_json_binary(&query_token_info(deps)?),
+        QueryMsg::BalanceAt { address, at_height } => {
+            to_json_binary(&query_balance_at_height(deps, address, at_height)?)
+        }
+        QueryMsg::TokenInfoAt { at_height } => {
+            to_json_binary(&query_token_info_at_height(deps, at_height)?)
+        }
         QueryMsg::Minter {} => to_json_binary(&query_minter(deps)?),
+        QueryMsg::Minters { start_after, limit } => {
+            to_json_binary(&query_minters(deps, start_after, limit)?)
+        }
         QueryMsg::Allowance { owner, spender } => {
             to_json_binary(&query_allowance(deps, owner, spender)?)
         }
+        QueryMsg::Nonce { owner } => to_json_binary(&query_nonce(deps, owner)?),
         QueryMsg::AllAllowances {
             owner,
             start_after,
@@ -530,8 +759,40 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::AllAccounts { start_after, limit } => {
             to_json_binary(&query_all_accounts(deps, start_after, limit)?)
         }
+        QueryMsg::AllBalances { start_after, limit } => {
+            to_json_binary(&query_all_balances(deps, start_after, limit)?)
+        }
+        QueryMsg::TopHolders { start_after, limit } => {
+            to_json_binary(&query_top_holders(deps, start_after, limit)?)
+        }
+        QueryMsg::HolderCount {} => to_json_binary(&query_holder_count(deps)?),
         QueryMsg::MarketingInfo {} => to_json_binary(&query_marketing_info(deps)?),
         QueryMsg::DownloadLogo {} => to_json_binary(&query_download_logo(deps)?),
+        QueryMsg::Member {
+            addr,
+            at_height: height,
+        } => to_json_binary(&query_member(deps, addr, height)?),
+        QueryMsg::TotalWeight { at_height: height } => {
+            to_json_binary(&query_total_weight(deps, height)?)
+        }
+        QueryMsg::ListMembers { start_after, limit } => {
+            to_json_binary(&query_list_members(deps, start_after, limit)?)
+        }
+        QueryMsg::VestingSchedule { address } => {
+            to_json_binary(&query_vesting_schedule(deps, env, address)?)
+        }
+        QueryMsg::PauseInfo {} => to_json_binary(&query_pause_info(deps)?),
+        QueryMsg::FrozenAccounts { start_after, limit } => {
+            to_json_binary(&query_frozen_accounts(deps, start_after, limit)?)
+        }
+        QueryMsg::Admin {} => to_json_binary(&ADMIN.query_admin(deps)?),
+        QueryMsg::Hooks {} => to_json_binary(&MEMBER_HOOKS.query_hooks(deps)?),
+        QueryMsg::TransferHooks {} => to_json_binary(&TRANSFER_HOOKS.query_hooks(deps)?),
+        QueryMsg::TransferChecks {} => to_json_binary(&TRANSFER_CHECKS.query_hooks(deps)?),
+        QueryMsg::PendingRewards { address } => {
+            to_json_binary(&query_pending_rewards(deps, address)?)
+        }
+        QueryMsg::RewardDenoms {} => to_json_binary(&query_reward_denoms(deps)?),
     }
 }
 
@@ -554,6 +815,40 @@ pub fn query_token_info(deps: Deps) -> StdResult<TokenInfoResponse> {
     Ok(res)
 }
 
+fn assert_snapshots_enabled(deps: Deps) -> StdResult<()> {
+    if !snapshots_enabled(deps.storage)? {
+        return Err(StdError::generic_err(
+            "Snapshots are not enabled for this token",
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the balance at the beginning of the given block, only available in snapshot mode
+pub fn query_balance_at_height(
+    deps: Deps,
+    address: String,
+    height: u64,
+) -> StdResult<BalanceResponse> {
+    assert_snapshots_enabled(deps)?;
+    let address = deps.api.addr_validate(&address)?;
+    let balance = BALANCE_SNAPSHOTS
+        .may_load_at_height(deps.storage, &address, height)?
+        .unwrap_or_default();
+    Ok(BalanceResponse { balance })
+}
+
+/// Returns the token info with the total supply at the beginning of the given block,
+/// only available in snapshot mode
+pub fn query_token_info_at_height(deps: Deps, height: u64) -> StdResult<TokenInfoResponse> {
+    assert_snapshots_enabled(deps)?;
+    let mut res = query_token_info(deps)?;
+    res.total_supply = TOTAL_SUPPLY_SNAPSHOTS
+        .may_load_at_height(deps.storage, height)?
+        .unwrap_or_default();
+    Ok(res)
+}
+
 pub fn query_minter(deps: Deps) -> StdResult<Option<MinterResponse>> {
     let meta = TOKEN_INFO.load(deps.storage)?;
     let minter = match meta.mint {
@@ -603,14 +898,36 @@ pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, C
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use cosmwasm_std::testing::{
         mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info,
     };
-    use cosmwasm_std::{coins, from_json, Addr, CosmosMsg, StdError, SubMsg, WasmMsg};
+    use cosmwasm_std::{
+        coins, from_json, Addr, BankMsg, ContractResult, CosmosMsg, StdError, SubMsg, SystemError,
+        SystemResult, WasmMsg, WasmQuery,
+    };
+    use cw20::{Cw20ExecuteMsg, Denom, Expiration};
+    use cw_controllers::HooksResponse;
 
     use super::*;
-    use crate::msg::InstantiateMarketingInfo;
+    use crate::hooks::{CanTransferResponse, TransferCheckQueryMsg, TransferHookMsg};
+    use crate::msg::{
+        GroupConfig, InstantiateMarketingInfo, MinterInfo, PauseInfoResponse, ReceiveMsg,
+        RewardAmount, RewardDenom, VestingAccount, VestingClock, VestingCurve, VestingSchedule,
+        VestingScheduleResponse, VestingStep,
+    };
+    use crate::rewards::MAX_REWARD_DENOMS;
+    use cw4::{Member, MemberChangedHookMsg, MemberDiff};
+
+    /// Executes both the cw20 and the extension messages, to keep the tests short
+    pub(crate) fn execute(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: impl Into<ContractExecuteMsg>,
+    ) -> Result<Response, ContractError> {
+        super::execute(deps, env, info, msg.into())
+    }
 
     fn get_balance<T: Into<String>>(deps: Deps, address: T) -> Uint128 {
         query_balance(deps, address.into()).unwrap().balance
@@ -657,6 +974,7 @@ mod tests {
             }],
             mint: mint.clone(),
             marketing: None,
+            ..InstantiateMsg::default()
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
@@ -698,6 +1016,7 @@ mod tests {
                 }],
                 mint: None,
                 marketing: None,
+                ..InstantiateMsg::default()
             };
             let info = mock_info("creator", &[]);
             let env = mock_env();
@@ -736,6 +1055,7 @@ mod tests {
                     cap: Some(limit),
                 }),
                 marketing: None,
+                ..InstantiateMsg::default()
             };
             let info = mock_info("creator", &[]);
             let env = mock_env();
@@ -781,6 +1101,7 @@ mod tests {
                     cap: Some(limit),
                 }),
                 marketing: None,
+                ..InstantiateMsg::default()
             };
             let info = mock_info("creator", &[]);
             let env = mock_env();
@@ -812,6 +1133,7 @@ mod tests {
                         marketing: Some(marketing.to_string()),
                         logo: Some(Logo::Url("url".to_owned())),
                     }),
+                    ..InstantiateMsg::default()
                 };
 
                 let info = mock_info("creator", &[]);
@@ -851,6 +1173,7 @@ mod tests {
                         marketing: Some("m".to_owned()),
                         logo: Some(Logo::Url("url".to_owned())),
                     }),
+                    ..InstantiateMsg::default()
                 };
 
                 let info = mock_info("creator", &[]);
@@ -1061,6 +1384,7 @@ mod tests {
             ],
             mint: None,
             marketing: None,
+            ..InstantiateMsg::default()
         };
         let err =
             instantiate(deps.as_mut(), env.clone(), info.clone(), instantiate_msg).unwrap_err();
@@ -1083,6 +1407,7 @@ mod tests {
             ],
             mint: None,
             marketing: None,
+            ..InstantiateMsg::default()
         };
         let res = instantiate(deps.as_mut(), env, info, instantiate_msg).unwrap();
         assert_eq!(0, res.messages.len());
@@ -1312,6 +1637,1361 @@ mod tests {
         );
     }
 
+    #[test]
+    fn snapshots() {
+        let mut deps = mock_dependencies();
+        let addr1 = deps.api.addr_make("addr0001").to_string();
+        let addr2 = deps.api.addr_make("addr0002").to_string();
+        let minter = deps.api.addr_make("minter").to_string();
+
+        // history is only available in snapshot mode
+        do_instantiate(deps.as_mut(), &addr1, Uint128::new(100));
+        let err = query_balance_at_height(deps.as_ref(), addr1.clone(), 12345).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Snapshots are not enabled for this token")
+        );
+
+        let mut deps = mock_dependencies();
+        let instantiate_msg = InstantiateMsg {
+            name: "Auto Gen".to_string(),
+            symbol: "AUTO".to_string(),
+            decimals: 3,
+            initial_balances: vec![Cw20Coin {
+                address: addr1.clone(),
+                amount: Uint128::new(100),
+            }],
+            mint: Some(MinterResponse {
+                minter: minter.clone(),
+                cap: None,
+            }),
+            marketing: None,
+            enable_snapshots: true,
+            ..InstantiateMsg::default()
+        };
+        let mut env = mock_env();
+        let start = env.block.height;
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        env.block.height += 1;
+        let msg = ExecuteMsg::Transfer {
+            recipient: addr2.clone(),
+            amount: Uint128::new(30),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(&addr1, &[]), msg).unwrap();
+
+        env.block.height += 1;
+        let msg = ExecuteMsg::Mint {
+            recipient: addr2.clone(),
+            amount: Uint128::new(50),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(&minter, &[]), msg).unwrap();
+        let msg = ExecuteMsg::Burn {
+            amount: Uint128::new(20),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(&addr2, &[]), msg).unwrap();
+
+        // values at the beginning of each block
+        let balance_at = |deps: Deps, addr: &str, height: u64| {
+            let msg = QueryMsg::BalanceAt {
+                address: addr.to_string(),
+                at_height: height,
+            };
+            from_json::<BalanceResponse>(query(deps, mock_env(), msg).unwrap())
+                .unwrap()
+                .balance
+                .u128()
+        };
+        let supply_at = |deps: Deps, height: u64| {
+            let msg = QueryMsg::TokenInfoAt { at_height: height };
+            from_json::<TokenInfoResponse>(query(deps, mock_env(), msg).unwrap())
+                .unwrap()
+                .total_supply
+                .u128()
+        };
+        assert_eq!(balance_at(deps.as_ref(), &addr1, start), 0);
+        assert_eq!(balance_at(deps.as_ref(), &addr1, start + 1), 100);
+        assert_eq!(balance_at(deps.as_ref(), &addr1, start + 2), 70);
+        assert_eq!(balance_at(deps.as_ref(), &addr2, start + 2), 30);
+        assert_eq!(balance_at(deps.as_ref(), &addr2, start + 3), 60);
+        assert_eq!(supply_at(deps.as_ref(), start + 2), 100);
+        assert_eq!(supply_at(deps.as_ref(), start + 3), 130);
+
+        // current values are unaffected
+        assert_eq!(get_balance(deps.as_ref(), &addr2), Uint128::new(60));
+        assert_eq!(
+            query_token_info(deps.as_ref()).unwrap().total_supply,
+            Uint128::new(130)
+        );
+    }
+
+    #[test]
+    fn group_weights_and_hooks() {
+        let mut deps = mock_dependencies();
+        let addr1 = deps.api.addr_make("addr0001").to_string();
+        let addr2 = deps.api.addr_make("addr0002").to_string();
+        let minter = deps.api.addr_make("minter").to_string();
+        let admin = deps.api.addr_make("admin").to_string();
+        let hook = deps.api.addr_make("hook").to_string();
+
+        let instantiate_msg = InstantiateMsg {
+            name: "Auto Gen".to_string(),
+            symbol: "AUTO".to_string(),
+            decimals: 3,
+            initial_balances: vec![Cw20Coin {
+                address: addr1.clone(),
+                amount: Uint128::new(1000),
+            }],
+            mint: Some(MinterResponse {
+                minter: minter.clone(),
+                cap: None,
+            }),
+            marketing: None,
+            admin: Some(admin.clone()),
+            group: Some(GroupConfig {
+                tokens_per_weight: Uint128::new(100),
+                min_bond: Uint128::new(200),
+            }),
+            ..InstantiateMsg::default()
+        };
+        let mut env = mock_env();
+        let start = env.block.height;
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        let weight_of = |deps: Deps, addr: &str, height: Option<u64>| {
+            query_member(deps, addr.to_string(), height).unwrap().weight
+        };
+        assert_eq!(weight_of(deps.as_ref(), &addr1, None), Some(10));
+        assert_eq!(query_total_weight(deps.as_ref(), None).unwrap().weight, 10);
+
+        // only the admin registers hooks
+        let msg = ExtensionMsg::AddHook { addr: hook.clone() };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Hook(_)));
+        execute(deps.as_mut(), env.clone(), mock_info(&admin, &[]), msg).unwrap();
+
+        // below min_bond the recipient does not join
+        env.block.height += 1;
+        let msg = ExecuteMsg::Transfer {
+            recipient: addr2.clone(),
+            amount: Uint128::new(150),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info(&addr1, &[]), msg).unwrap();
+        let diffs = vec![MemberDiff::new(&addr1, Some(10), Some(8))];
+        let expected = MemberChangedHookMsg { diffs }
+            .into_cosmos_msg(hook.clone())
+            .unwrap();
+        assert_eq!(res.messages, vec![SubMsg::new(expected)]);
+        assert_eq!(weight_of(deps.as_ref(), &addr2, None), None);
+
+        // minting crosses it
+        env.block.height += 1;
+        let msg = ExecuteMsg::Mint {
+            recipient: addr2.clone(),
+            amount: Uint128::new(100),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info(&minter, &[]), msg).unwrap();
+        let diffs = vec![MemberDiff::new(&addr2, None, Some(2))];
+        let expected = MemberChangedHookMsg { diffs }
+            .into_cosmos_msg(hook.clone())
+            .unwrap();
+        assert_eq!(res.messages, vec![SubMsg::new(expected)]);
+
+        // a burn that keeps the weight sends no hook
+        let msg = ExecuteMsg::Burn {
+            amount: Uint128::new(50),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info(&addr1, &[]), msg).unwrap();
+        assert_eq!(res.messages, vec![]);
+        // and one dropping below min_bond removes the member
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr2, &[]),
+            ExecuteMsg::Burn {
+                amount: Uint128::new(100),
+            },
+        )
+        .unwrap();
+        let diffs = vec![MemberDiff::new(&addr2, Some(2), None)];
+        let expected = MemberChangedHookMsg { diffs }
+            .into_cosmos_msg(hook)
+            .unwrap();
+        assert_eq!(res.messages, vec![SubMsg::new(expected)]);
+
+        // weights at the beginning of each block
+        assert_eq!(weight_of(deps.as_ref(), &addr1, Some(start + 1)), Some(10));
+        assert_eq!(weight_of(deps.as_ref(), &addr1, Some(start + 2)), Some(8));
+        assert_eq!(weight_of(deps.as_ref(), &addr1, None), Some(8));
+        assert_eq!(weight_of(deps.as_ref(), &addr2, None), None);
+        assert_eq!(
+            query_total_weight(deps.as_ref(), Some(start + 2))
+                .unwrap()
+                .weight,
+            8
+        );
+        assert_eq!(query_total_weight(deps.as_ref(), None).unwrap().weight, 8);
+
+        let members = query_list_members(deps.as_ref(), None, None)
+            .unwrap()
+            .members;
+        assert_eq!(
+            members,
+            vec![Member {
+                addr: addr1,
+                weight: 8
+            }]
+        );
+    }
+
+    #[test]
+    fn group_weight_is_capped_at_u64() {
+        let mut deps = mock_dependencies();
+        let addr1 = deps.api.addr_make("addr0001").to_string();
+
+        let instantiate_msg = InstantiateMsg {
+            name: "Auto Gen".to_string(),
+            symbol: "AUTO".to_string(),
+            decimals: 3,
+            initial_balances: vec![Cw20Coin {
+                address: addr1.clone(),
+                amount: Uint128::from(u64::MAX) + Uint128::new(10),
+            }],
+            group: Some(GroupConfig {
+                tokens_per_weight: Uint128::one(),
+                min_bond: Uint128::one(),
+            }),
+            ..InstantiateMsg::default()
+        };
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            instantiate_msg,
+        )
+        .unwrap();
+        let member = query_member(deps.as_ref(), addr1.clone(), None).unwrap();
+        assert_eq!(member.weight, Some(u64::MAX));
+        let total = query_total_weight(deps.as_ref(), None).unwrap();
+        assert_eq!(total.weight, u64::MAX);
+
+        // transfers keep working
+        let addr2 = deps.api.addr_make("addr0002").to_string();
+        let msg = ExecuteMsg::Transfer {
+            recipient: addr2.clone(),
+            amount: Uint128::new(2),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(&addr1, &[]), msg).unwrap();
+        let member = query_member(deps.as_ref(), addr1, None).unwrap();
+        assert_eq!(member.weight, Some(u64::MAX));
+        let member = query_member(deps.as_ref(), addr2, None).unwrap();
+        assert_eq!(member.weight, Some(2));
+    }
+
+    #[test]
+    fn vesting_schedules() {
+        let mut deps = mock_dependencies();
+        let addr1 = deps.api.addr_make("addr0001").to_string();
+        let addr2 = deps.api.addr_make("addr0002").to_string();
+        let minter = deps.api.addr_make("minter").to_string();
+        let spender = deps.api.addr_make("spender").to_string();
+
+        let mut env = mock_env();
+        let start = env.block.height;
+        // 100 liquid and 1000 vesting linearly over 100 blocks after a 25 block cliff
+        let linear = VestingSchedule {
+            amount: Uint128::new(1000),
+            clock: VestingClock::Height,
+            curve: VestingCurve::Linear {
+                start,
+                cliff: start + 25,
+                end: start + 100,
+            },
+        };
+        let instantiate_msg = InstantiateMsg {
+            name: "Auto Gen".to_string(),
+            symbol: "AUTO".to_string(),
+            decimals: 3,
+            initial_balances: vec![Cw20Coin {
+                address: addr1.clone(),
+                amount: Uint128::new(100),
+            }],
+            mint: Some(MinterResponse {
+                minter: minter.clone(),
+                cap: None,
+            }),
+            initial_vesting: vec![VestingAccount {
+                address: addr1.clone(),
+                schedule: linear.clone(),
+            }],
+            ..InstantiateMsg::default()
+        };
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            instantiate_msg,
+        )
+        .unwrap();
+        assert_eq!(get_balance(deps.as_ref(), &addr1), Uint128::new(1100));
+        assert_eq!(
+            query_token_info(deps.as_ref()).unwrap().total_supply,
+            Uint128::new(1100)
+        );
+
+        // only the liquid part can move
+        let transfer = |amount: u128| ExecuteMsg::Transfer {
+            recipient: addr2.clone(),
+            amount: Uint128::new(amount),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            transfer(101),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::UnvestedTokens {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            transfer(100),
+        )
+        .unwrap();
+
+        // the cliff releases what accrued so far, also for allowances
+        env.block.height = start + 30;
+        let msg = ExecuteMsg::IncreaseAllowance {
+            spender: spender.clone(),
+            amount: Uint128::new(1000),
+            expires: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(&addr1, &[]), msg).unwrap();
+        let transfer_from = |amount: u128| ExecuteMsg::TransferFrom {
+            owner: addr1.clone(),
+            recipient: addr2.clone(),
+            amount: Uint128::new(amount),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&spender, &[]),
+            transfer_from(301),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::UnvestedTokens {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&spender, &[]),
+            transfer_from(300),
+        )
+        .unwrap();
+
+        // nor can locked tokens be burned
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            ExecuteMsg::Burn {
+                amount: Uint128::new(1),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::UnvestedTokens {});
+        let msg = ExecuteMsg::BurnFrom {
+            owner: addr1.clone(),
+            amount: Uint128::new(1),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info(&spender, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::UnvestedTokens {});
+
+        let res = query_vesting_schedule(deps.as_ref(), env.clone(), addr1.clone()).unwrap();
+        assert_eq!(
+            res,
+            VestingScheduleResponse {
+                schedules: vec![linear],
+                vested: Uint128::new(300),
+                locked: Uint128::new(700),
+            }
+        );
+
+        // the minter hands out tokens released in steps
+        let steps = VestingSchedule {
+            amount: Uint128::new(500),
+            clock: VestingClock::Time,
+            curve: VestingCurve::Steps(vec![
+                VestingStep {
+                    at: env.block.time.seconds() + 60,
+                    amount: Uint128::new(200),
+                },
+                VestingStep {
+                    at: env.block.time.seconds() + 120,
+                    amount: Uint128::new(300),
+                },
+            ]),
+        };
+        let msg = ExtensionMsg::MintVesting {
+            recipient: addr2.clone(),
+            schedule: steps.clone(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), mock_info(&minter, &[]), msg).unwrap();
+        assert_eq!(get_balance(deps.as_ref(), &addr2), Uint128::new(900));
+
+        let invalid = ExtensionMsg::MintVesting {
+            recipient: addr2.clone(),
+            schedule: VestingSchedule {
+                amount: Uint128::new(600),
+                ..steps
+            },
+        };
+        let err =
+            execute(deps.as_mut(), env.clone(), mock_info(&minter, &[]), invalid).unwrap_err();
+        assert_eq!(err, ContractError::InvalidVestingSchedule {});
+
+        env.block.time = env.block.time.plus_seconds(60);
+        let send = |amount: u128| ExecuteMsg::Send {
+            contract: addr1.clone(),
+            amount: Uint128::new(amount),
+            msg: Binary::default(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr2, &[]),
+            send(601),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::UnvestedTokens {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr2, &[]),
+            send(600),
+        )
+        .unwrap();
+
+        // everything moves once fully vested
+        env.block.height = start + 100;
+        env.block.time = env.block.time.plus_seconds(60);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr2, &[]),
+            send(300),
+        )
+        .unwrap();
+        let res = query_vesting_schedule(deps.as_ref(), env.clone(), addr1.clone()).unwrap();
+        assert_eq!(res.vested, Uint128::new(1000));
+        assert_eq!(res.locked, Uint128::zero());
+        // fully vested schedules are dropped once the holder moves tokens
+        let res = query_vesting_schedule(deps.as_ref(), env, addr2.clone()).unwrap();
+        assert_eq!(res.schedules, vec![]);
+    }
+
+    #[test]
+    fn pause_and_freeze() {
+        let mut deps = mock_dependencies();
+        let addr1 = deps.api.addr_make("addr0001").to_string();
+        let addr2 = deps.api.addr_make("addr0002").to_string();
+        let minter = deps.api.addr_make("minter").to_string();
+        let pauser = deps.api.addr_make("pauser").to_string();
+        let spender = deps.api.addr_make("spender").to_string();
+
+        let instantiate_msg = InstantiateMsg {
+            name: "Auto Gen".to_string(),
+            symbol: "AUTO".to_string(),
+            decimals: 3,
+            initial_balances: vec![Cw20Coin {
+                address: addr1.clone(),
+                amount: Uint128::new(1000),
+            }],
+            mint: Some(MinterResponse {
+                minter: minter.clone(),
+                cap: None,
+            }),
+            pauser: Some(pauser.clone()),
+            ..InstantiateMsg::default()
+        };
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            instantiate_msg,
+        )
+        .unwrap();
+        let msg = ExecuteMsg::IncreaseAllowance {
+            spender: spender.clone(),
+            amount: Uint128::new(500),
+            expires: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(&addr1, &[]), msg).unwrap();
+
+        // only the pauser may pause
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            ExtensionMsg::Pause {},
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Admin(_)));
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&pauser, &[]),
+            ExtensionMsg::Pause {},
+        )
+        .unwrap();
+        assert_eq!(
+            query_pause_info(deps.as_ref()).unwrap(),
+            PauseInfoResponse {
+                paused: true,
+                pauser: Some(pauser.clone()),
+            }
+        );
+
+        let transfer = ExecuteMsg::Transfer {
+            recipient: addr2.clone(),
+            amount: Uint128::new(100),
+        };
+        let transfer_from = ExecuteMsg::TransferFrom {
+            owner: addr1.clone(),
+            recipient: addr2.clone(),
+            amount: Uint128::new(100),
+        };
+        let burn = ExecuteMsg::Burn {
+            amount: Uint128::new(100),
+        };
+        for (sender, msg) in [
+            (&addr1, transfer.clone()),
+            (&spender, transfer_from.clone()),
+            (&addr1, burn.clone()),
+        ] {
+            let err = execute(deps.as_mut(), env.clone(), mock_info(sender, &[]), msg).unwrap_err();
+            assert_eq!(err, ContractError::Paused {});
+        }
+        // minting goes on
+        let mint = ExecuteMsg::Mint {
+            recipient: addr2.clone(),
+            amount: Uint128::new(100),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&minter, &[]),
+            mint.clone(),
+        )
+        .unwrap();
+
+        // the pauser cannot walk away from a paused token
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&pauser, &[]),
+            ExtensionMsg::UpdatePauser { pauser: None },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::CannotRemovePauser {});
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&pauser, &[]),
+            ExtensionMsg::Unpause {},
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            transfer.clone(),
+        )
+        .unwrap();
+
+        // a frozen account can neither send nor receive
+        let msg = ExtensionMsg::Freeze {
+            address: addr2.clone(),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(&pauser, &[]), msg).unwrap();
+        let frozen = ContractError::Frozen {
+            address: addr2.clone(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            transfer.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, frozen);
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&spender, &[]),
+            transfer_from.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, frozen);
+        let err = execute(deps.as_mut(), env.clone(), mock_info(&minter, &[]), mint).unwrap_err();
+        assert_eq!(err, frozen);
+        let msg = ExecuteMsg::Send {
+            contract: addr1.clone(),
+            amount: Uint128::new(100),
+            msg: Binary::default(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info(&addr2, &[]), msg).unwrap_err();
+        assert_eq!(err, frozen);
+        let err = execute(deps.as_mut(), env.clone(), mock_info(&addr2, &[]), burn).unwrap_err();
+        assert_eq!(err, frozen);
+
+        assert_eq!(
+            query_frozen_accounts(deps.as_ref(), None, None)
+                .unwrap()
+                .accounts,
+            vec![addr2.clone()]
+        );
+        let msg = ExtensionMsg::Unfreeze {
+            address: addr2.clone(),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(&pauser, &[]), msg).unwrap();
+        execute(deps.as_mut(), env, mock_info(&spender, &[]), transfer_from).unwrap();
+        assert_eq!(get_balance(deps.as_ref(), &addr2), Uint128::new(300));
+        assert!(query_frozen_accounts(deps.as_ref(), None, None)
+            .unwrap()
+            .accounts
+            .is_empty());
+    }
+
+    #[test]
+    fn transfer_hooks_and_checks() {
+        let mut deps = mock_dependencies();
+        let addr1 = deps.api.addr_make("addr0001").to_string();
+        let addr2 = deps.api.addr_make("addr0002").to_string();
+        let admin = deps.api.addr_make("admin").to_string();
+        let hook = deps.api.addr_make("hook").to_string();
+        let check = deps.api.addr_make("check").to_string();
+
+        // the check rejects transfers of more than 100 tokens
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { msg, .. } => {
+                let TransferCheckQueryMsg::CanTransfer(transfer) = from_json(msg).unwrap();
+                let res = CanTransferResponse {
+                    allowed: transfer.amount <= Uint128::new(100),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&res).unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "wasm".to_string(),
+            }),
+        });
+
+        let instantiate_msg = InstantiateMsg {
+            name: "Auto Gen".to_string(),
+            symbol: "AUTO".to_string(),
+            decimals: 3,
+            initial_balances: vec![Cw20Coin {
+                address: addr1.clone(),
+                amount: Uint128::new(1000),
+            }],
+            admin: Some(admin.clone()),
+            ..InstantiateMsg::default()
+        };
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // only the admin manages them
+        let msg = ExtensionMsg::AddTransferHook { addr: hook.clone() };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Hook(_)));
+        execute(deps.as_mut(), env.clone(), mock_info(&admin, &[]), msg).unwrap();
+        let msg = ExtensionMsg::AddTransferCheck {
+            addr: check.clone(),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(&admin, &[]), msg).unwrap();
+        let hooks: HooksResponse =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::TransferHooks {}).unwrap())
+                .unwrap();
+        assert_eq!(hooks.hooks, vec![hook.clone()]);
+        let checks: HooksResponse =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::TransferChecks {}).unwrap())
+                .unwrap();
+        assert_eq!(checks.hooks, vec![check.clone()]);
+
+        let transfer = |amount: u128| ExecuteMsg::Transfer {
+            recipient: addr2.clone(),
+            amount: Uint128::new(amount),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            transfer(101),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::TransferRejected { hook: check });
+
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            transfer(100),
+        )
+        .unwrap();
+        let expected = TransferHookMsg {
+            from: addr1.clone(),
+            to: addr2.clone(),
+            amount: Uint128::new(100),
+        }
+        .into_cosmos_msg(hook.clone())
+        .unwrap();
+        assert_eq!(res.messages, vec![SubMsg::new(expected)]);
+
+        // once removed, the hook is no longer called
+        let msg = ExtensionMsg::RemoveTransferHook { addr: hook };
+        execute(deps.as_mut(), env.clone(), mock_info(&admin, &[]), msg).unwrap();
+        let res = execute(deps.as_mut(), env, mock_info(&addr1, &[]), transfer(50)).unwrap();
+        assert_eq!(res.messages, vec![]);
+        assert_eq!(get_balance(deps.as_ref(), &addr2), Uint128::new(150));
+    }
+
+    #[test]
+    fn batch_transfer_and_send() {
+        let mut deps = mock_dependencies();
+        let addr1 = deps.api.addr_make("addr0001").to_string();
+        let addr2 = deps.api.addr_make("addr0002").to_string();
+        let addr3 = deps.api.addr_make("addr0003").to_string();
+        let contract = deps.api.addr_make("contract0000").to_string();
+        do_instantiate(deps.as_mut(), &addr1, Uint128::new(1000));
+        let env = mock_env();
+        let info = mock_info(addr1.as_ref(), &[]);
+
+        let msg = ExecuteMsg::BatchTransfer { transfers: vec![] };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::EmptyBatch {});
+
+        // fails as a whole if the sender cannot cover the total
+        let msg = ExecuteMsg::BatchTransfer {
+            transfers: vec![
+                Cw20Coin {
+                    address: addr2.clone(),
+                    amount: Uint128::new(600),
+                },
+                Cw20Coin {
+                    address: addr3.clone(),
+                    amount: Uint128::new(401),
+                },
+            ],
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::Overflow { .. })));
+
+        let msg = ExecuteMsg::BatchTransfer {
+            transfers: vec![
+                Cw20Coin {
+                    address: addr2.clone(),
+                    amount: Uint128::new(100),
+                },
+                Cw20Coin {
+                    address: addr3.clone(),
+                    amount: Uint128::new(200),
+                },
+            ],
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        assert_eq!(res.attributes[0], ("from", addr1.as_str()));
+        assert_eq!(res.attributes[1], ("amount", "300"));
+        assert_eq!(res.attributes[2], ("action", "batch_transfer"));
+        assert_eq!(
+            res.events,
+            vec![
+                Event::new("transfer")
+                    .add_attribute("to", &addr2)
+                    .add_attribute("amount", "100"),
+                Event::new("transfer")
+                    .add_attribute("to", &addr3)
+                    .add_attribute("amount", "200"),
+            ]
+        );
+        assert_eq!(get_balance(deps.as_ref(), &addr1), Uint128::new(700));
+        assert_eq!(get_balance(deps.as_ref(), &addr2), Uint128::new(100));
+        assert_eq!(get_balance(deps.as_ref(), &addr3), Uint128::new(200));
+
+        // every contract receives its own message
+        let send_msg = Binary::from(r#"{"some":123}"#.as_bytes());
+        let msg = ExecuteMsg::BatchSend {
+            sends: vec![
+                Cw20Send {
+                    contract: contract.clone(),
+                    amount: Uint128::new(50),
+                    msg: send_msg.clone(),
+                },
+                Cw20Send {
+                    contract: addr2.clone(),
+                    amount: Uint128::new(150),
+                    msg: Binary::default(),
+                },
+            ],
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let receive = |contract: &str, amount: u128, msg: Binary| {
+            SubMsg::new(
+                Cw20ReceiveMsg {
+                    sender: addr1.clone(),
+                    amount: Uint128::new(amount),
+                    msg,
+                }
+                .into_cosmos_msg(contract)
+                .unwrap(),
+            )
+        };
+        assert_eq!(
+            res.messages,
+            vec![
+                receive(&contract, 50, send_msg),
+                receive(&addr2, 150, Binary::default())
+            ]
+        );
+        assert_eq!(res.events.len(), 2);
+        assert_eq!(get_balance(deps.as_ref(), &addr1), Uint128::new(500));
+        assert_eq!(get_balance(deps.as_ref(), &contract), Uint128::new(50));
+        assert_eq!(get_balance(deps.as_ref(), &addr2), Uint128::new(250));
+        assert_eq!(
+            query_token_info(deps.as_ref()).unwrap().total_supply,
+            Uint128::new(1000)
+        );
+    }
+
+    #[test]
+    fn registered_minters() {
+        let mut deps = mock_dependencies();
+        let addr1 = deps.api.addr_make("addr0001").to_string();
+        let minter = deps.api.addr_make("minter").to_string();
+        let bridge = deps.api.addr_make("bridge").to_string();
+        let treasury = deps.api.addr_make("treasury").to_string();
+        let admin = deps.api.addr_make("admin").to_string();
+
+        let instantiate_msg = InstantiateMsg {
+            name: "Auto Gen".to_string(),
+            symbol: "AUTO".to_string(),
+            decimals: 3,
+            mint: Some(MinterResponse {
+                minter: minter.clone(),
+                cap: Some(Uint128::new(1000)),
+            }),
+            admin: Some(admin.clone()),
+            ..InstantiateMsg::default()
+        };
+        let mut env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            instantiate_msg,
+        )
+        .unwrap();
+
+        // only the admin registers minters
+        let add_bridge = ExtensionMsg::AddMinter {
+            minter: bridge.clone(),
+            cap: Some(Uint128::new(300)),
+            expires: None,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&minter, &[]),
+            add_bridge.clone(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Admin(_)));
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&admin, &[]),
+            add_bridge,
+        )
+        .unwrap();
+        let msg = ExtensionMsg::AddMinter {
+            minter: treasury.clone(),
+            cap: None,
+            expires: Some(Expiration::AtHeight(env.block.height + 10)),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(&admin, &[]), msg).unwrap();
+
+        let mint = |amount: u128| ExecuteMsg::Mint {
+            recipient: addr1.clone(),
+            amount: Uint128::new(amount),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info(&addr1, &[]), mint(1)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // each minter has its own budget
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&bridge, &[]),
+            mint(300),
+        )
+        .unwrap();
+        let err =
+            execute(deps.as_mut(), env.clone(), mock_info(&bridge, &[]), mint(1)).unwrap_err();
+        assert_eq!(err, ContractError::CannotExceedMinterCap {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&treasury, &[]),
+            mint(500),
+        )
+        .unwrap();
+
+        // within the cap of the token
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&treasury, &[]),
+            mint(201),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::CannotExceedCap {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&minter, &[]),
+            mint(100),
+        )
+        .unwrap();
+
+        let minters = query_minters(deps.as_ref(), None, None).unwrap().minters;
+        assert_eq!(minters.len(), 2);
+        assert_eq!(
+            minters.iter().find(|m| m.minter == bridge).unwrap(),
+            &MinterInfo {
+                minter: bridge.clone(),
+                cap: Some(Uint128::new(300)),
+                minted: Uint128::new(300),
+                expires: Expiration::Never {},
+            }
+        );
+
+        // expired and removed minters cannot mint
+        env.block.height += 10;
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&treasury, &[]),
+            mint(1),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::MinterExpired {});
+        let msg = ExtensionMsg::RemoveMinter {
+            minter: bridge.clone(),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(&admin, &[]), msg).unwrap();
+        let err =
+            execute(deps.as_mut(), env.clone(), mock_info(&bridge, &[]), mint(1)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        assert_eq!(
+            query_token_info(deps.as_ref()).unwrap().total_supply,
+            Uint128::new(900)
+        );
+
+        // removing the main minter ends minting for everyone
+        let add_bridge = ExtensionMsg::AddMinter {
+            minter: bridge.clone(),
+            cap: None,
+            expires: None,
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&admin, &[]),
+            add_bridge.clone(),
+        )
+        .unwrap();
+        let msg = ExecuteMsg::UpdateMinter { new_minter: None };
+        execute(deps.as_mut(), env.clone(), mock_info(&minter, &[]), msg).unwrap();
+        let err =
+            execute(deps.as_mut(), env.clone(), mock_info(&bridge, &[]), mint(1)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), env, mock_info(&admin, &[]), add_bridge).unwrap_err();
+        assert_eq!(err, ContractError::MintingDisabled {});
+    }
+
+    #[test]
+    fn reward_distribution() {
+        let mut deps = mock_dependencies();
+        let addr1 = deps.api.addr_make("addr0001").to_string();
+        let addr2 = deps.api.addr_make("addr0002").to_string();
+        let reward_token = deps.api.addr_make("reward_token");
+        let admin = deps.api.addr_make("admin").to_string();
+        let instantiate_msg = InstantiateMsg {
+            name: "Auto Gen".to_string(),
+            symbol: "AUTO".to_string(),
+            decimals: 3,
+            initial_balances: vec![Cw20Coin {
+                address: addr1.clone(),
+                amount: Uint128::new(400),
+            }],
+            admin: Some(admin.clone()),
+            ..InstantiateMsg::default()
+        };
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            instantiate_msg,
+        )
+        .unwrap();
+        let transfer = |amount: u128| ExecuteMsg::Transfer {
+            recipient: addr2.clone(),
+            amount: Uint128::new(amount),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            transfer(100),
+        )
+        .unwrap();
+
+        // only denoms whitelisted by the admin are accepted
+        let native = Denom::Native("uatom".to_string());
+        let cw20 = Denom::Cw20(reward_token.clone());
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("payer", &coins(400, "uatom")),
+            ExtensionMsg::DepositRewards {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::UnsupportedRewardDenom {});
+        let add_denom = |denom: &Denom, min_deposit: u128| ExtensionMsg::AddRewardDenom {
+            denom: denom.clone(),
+            min_deposit: Uint128::new(min_deposit),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("payer", &[]),
+            add_denom(&native, 100),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Admin(_)));
+        for denom in [&native, &cw20] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(&admin, &[]),
+                add_denom(denom, 10),
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            query_reward_denoms(deps.as_ref()).unwrap().denoms,
+            vec![
+                RewardDenom {
+                    denom: native.clone(),
+                    min_deposit: Uint128::new(10),
+                },
+                RewardDenom {
+                    denom: cw20.clone(),
+                    min_deposit: Uint128::new(10),
+                },
+            ]
+        );
+
+        // deposits must reach the minimum
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("payer", &coins(9, "uatom")),
+            ExtensionMsg::DepositRewards {},
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::RewardBelowMinimum {
+                min: Uint128::new(10)
+            }
+        );
+
+        // native rewards are shared by the balances at deposit time
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("payer", &[]),
+            ExtensionMsg::DepositRewards {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoRewards {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("payer", &coins(400, "uatom")),
+            ExtensionMsg::DepositRewards {},
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            transfer(200),
+        )
+        .unwrap();
+
+        // so are cw20 rewards, after the transfer
+        let msg = ExtensionMsg::Receive(Cw20ReceiveMsg {
+            sender: "payer".to_string(),
+            amount: Uint128::new(40),
+            msg: to_json_binary(&ReceiveMsg::DepositRewards {}).unwrap(),
+        });
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(reward_token.as_str(), &[]),
+            msg,
+        )
+        .unwrap();
+
+        let pending = |deps: Deps, address: &str| {
+            query_pending_rewards(deps, address.to_string())
+                .unwrap()
+                .rewards
+        };
+        let reward = |denom: Denom, amount: u128| RewardAmount {
+            denom,
+            amount: Uint128::new(amount),
+        };
+        assert_eq!(
+            pending(deps.as_ref(), &addr1),
+            vec![reward(native.clone(), 300), reward(cw20.clone(), 10)]
+        );
+        assert_eq!(
+            pending(deps.as_ref(), &addr2),
+            vec![reward(native.clone(), 100), reward(cw20.clone(), 30)]
+        );
+
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            ExtensionMsg::ClaimRewards { denom: None },
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: addr1.clone(),
+                    amount: coins(300, "uatom"),
+                }),
+                SubMsg::new(WasmMsg::Execute {
+                    contract_addr: reward_token.to_string(),
+                    msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                        recipient: addr1.clone(),
+                        amount: Uint128::new(10),
+                    })
+                    .unwrap(),
+                    funds: vec![],
+                }),
+            ]
+        );
+        assert_eq!(
+            pending(deps.as_ref(), &addr1),
+            vec![reward(native.clone(), 0), reward(cw20.clone(), 0)]
+        );
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr1, &[]),
+            ExtensionMsg::ClaimRewards { denom: None },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoRewards {});
+
+        // a single denom can be claimed on its own, e.g. if another one cannot be paid out
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr2, &[]),
+            ExtensionMsg::ClaimRewards {
+                denom: Some(native.clone()),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: addr2.clone(),
+                amount: coins(100, "uatom"),
+            })]
+        );
+        assert_eq!(
+            pending(deps.as_ref(), &addr2),
+            vec![reward(native.clone(), 0), reward(cw20.clone(), 30)]
+        );
+
+        // removed denoms are no longer accepted, but what was deposited can still be claimed
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&admin, &[]),
+            ExtensionMsg::RemoveRewardDenom {
+                denom: cw20.clone(),
+            },
+        )
+        .unwrap();
+        let msg = ExtensionMsg::Receive(Cw20ReceiveMsg {
+            sender: "payer".to_string(),
+            amount: Uint128::new(40),
+            msg: to_json_binary(&ReceiveMsg::DepositRewards {}).unwrap(),
+        });
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(reward_token.as_str(), &[]),
+            msg,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::UnsupportedRewardDenom {});
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&addr2, &[]),
+            ExtensionMsg::ClaimRewards { denom: Some(cw20) },
+        )
+        .unwrap();
+
+        // the number of reward denoms is bounded, counting those removed
+        for i in 2..MAX_REWARD_DENOMS {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(&admin, &[]),
+                add_denom(&Denom::Native(format!("denom{}", i)), 0),
+            )
+            .unwrap();
+        }
+        let err = execute(
+            deps.as_mut(),
+            env,
+            mock_info(&admin, &[]),
+            add_denom(&Denom::Native("denom".to_string()), 0),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TooManyRewardDenoms {
+                max: MAX_REWARD_DENOMS
+            }
+        );
+    }
+
+    #[test]
+    fn reward_remainder_is_carried_over() {
+        let mut deps = mock_dependencies();
+        let addr1 = deps.api.addr_make("addr0001").to_string();
+        let admin = deps.api.addr_make("admin").to_string();
+        let mut env = mock_env();
+        env.contract.address = deps.api.addr_make("token");
+        // tokens held by the token contract itself earn nothing
+        let instantiate_msg = InstantiateMsg {
+            name: "Auto Gen".to_string(),
+            symbol: "AUTO".to_string(),
+            decimals: 18,
+            initial_balances: vec![
+                Cw20Coin {
+                    address: addr1.clone(),
+                    amount: Uint128::new(2_000_000_000_000_000_000),
+                },
+                Cw20Coin {
+                    address: env.contract.address.to_string(),
+                    amount: Uint128::new(2_000_000_000_000_000_000),
+                },
+            ],
+            admin: Some(admin.clone()),
+            ..InstantiateMsg::default()
+        };
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            instantiate_msg,
+        )
+        .unwrap();
+        let msg = ExtensionMsg::AddRewardDenom {
+            denom: Denom::Native("uatom".to_string()),
+            min_deposit: Uint128::zero(),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(&admin, &[]), msg).unwrap();
+
+        let pending = |deps: Deps| {
+            query_pending_rewards(deps, addr1.clone()).unwrap().rewards[0]
+                .amount
+                .u128()
+        };
+        // 1 uatom over 2 * 10^18 tokens rounds to zero per token
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("payer", &coins(1, "uatom")),
+            ExtensionMsg::DepositRewards {},
+        )
+        .unwrap();
+        assert_eq!(pending(deps.as_ref()), 0);
+        // and is paid along the next deposit
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("payer", &coins(3, "uatom")),
+            ExtensionMsg::DepositRewards {},
+        )
+        .unwrap();
+        assert_eq!(pending(deps.as_ref()), 4);
+    }
+
     mod migration {
         use super::*;
 
@@ -1352,6 +3032,7 @@ mod tests {
                         }],
                         mint: None,
                         marketing: None,
+                        ..InstantiateMsg::default()
                     },
                     &[],
                     "TOKEN",
@@ -1457,6 +3138,7 @@ mod tests {
                     marketing: Some(marketing.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info(creator.as_str(), &[]);
@@ -1513,6 +3195,7 @@ mod tests {
                     marketing: Some(creator.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info(creator.as_str(), &[]);
@@ -1568,6 +3251,7 @@ mod tests {
                     marketing: Some(creator.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info(creator.as_str(), &[]);
@@ -1623,6 +3307,7 @@ mod tests {
                     marketing: Some(creator.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info(creator.as_str(), &[]);
@@ -1678,6 +3363,7 @@ mod tests {
                     marketing: Some(creator.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info(creator.as_str(), &[]);
@@ -1734,6 +3420,7 @@ mod tests {
                     marketing: Some(creator.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info(creator.as_str(), &[]);
@@ -1789,6 +3476,7 @@ mod tests {
                     marketing: Some(creator.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info(creator.as_str(), &[]);
@@ -1847,6 +3535,7 @@ mod tests {
                     marketing: Some(creator.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info(creator.as_str(), &[]);
@@ -1902,6 +3591,7 @@ mod tests {
                     marketing: Some(creator.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info(creator.as_str(), &[]);
@@ -1953,6 +3643,7 @@ mod tests {
                     marketing: Some(creator.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info(creator.as_str(), &[]);
@@ -2006,6 +3697,7 @@ mod tests {
                     marketing: Some(creator.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info(creator.as_str(), &[]);
@@ -2060,6 +3752,7 @@ mod tests {
                     marketing: Some(creator.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info(creator.as_str(), &[]);
@@ -2112,6 +3805,7 @@ mod tests {
                     marketing: Some(creator.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info(creator.as_str(), &[]);
@@ -2171,6 +3865,7 @@ mod tests {
                     marketing: Some(creator.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info(creator.as_str(), &[]);
@@ -2223,6 +3918,7 @@ mod tests {
                     marketing: Some(creator.to_string()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                ..InstantiateMsg::default()
             };
 
             let info = mock_info("creator", &[]);
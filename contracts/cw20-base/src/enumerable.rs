@@ -1,10 +1,13 @@
-use cosmwasm_std::{Deps, Order, StdResult};
+use cosmwasm_std::{Deps, Order, StdError, StdResult};
 use cw20::{
     AllAccountsResponse, AllAllowancesResponse, AllSpenderAllowancesResponse, AllowanceInfo,
-    SpenderAllowanceInfo,
+    Cw20Coin, SpenderAllowanceInfo,
 };
 
-use crate::state::{ALLOWANCES, ALLOWANCES_SPENDER, BALANCES};
+use crate::msg::{AllBalancesResponse, HolderCountResponse, TopHoldersResponse};
+use crate::state::{
+    holder_index_enabled, ALLOWANCES, ALLOWANCES_SPENDER, BALANCES, HOLDERS, HOLDER_COUNT,
+};
 use cw_storage_plus::Bound;
 
 // settings for pagination
@@ -78,6 +81,75 @@ pub fn query_all_accounts(
     Ok(AllAccountsResponse { accounts })
 }
 
+pub fn query_all_balances(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AllBalancesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|s| Bound::ExclusiveRaw(s.into()));
+
+    let balances = BALANCES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|(addr, amount)| Cw20Coin {
+                address: addr.into(),
+                amount,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(AllBalancesResponse { balances })
+}
+
+fn assert_holder_index_enabled(deps: Deps) -> StdResult<()> {
+    if !holder_index_enabled(deps.storage)? {
+        return Err(StdError::generic_err(
+            "Holder index is not enabled for this token",
+        ));
+    }
+    Ok(())
+}
+
+pub fn query_top_holders(
+    deps: Deps,
+    start_after: Option<Cw20Coin>,
+    limit: Option<u32>,
+) -> StdResult<TopHoldersResponse> {
+    assert_holder_index_enabled(deps)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    // continue below the last holder of the previous page, even if its balance changed since
+    let end = match start_after {
+        Some(last) => {
+            let addr = deps.api.addr_validate(&last.address)?;
+            Some(Bound::exclusive((last.amount.u128(), addr)))
+        }
+        None => None,
+    };
+
+    let holders = HOLDERS
+        .idx
+        .balance
+        .range(deps.storage, None, end, Order::Descending)
+        .take(limit)
+        .map(|item| {
+            item.map(|(addr, amount)| Cw20Coin {
+                address: addr.into(),
+                amount,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(TopHoldersResponse { holders })
+}
+
+pub fn query_holder_count(deps: Deps) -> StdResult<HolderCountResponse> {
+    assert_holder_index_enabled(deps)?;
+    let count = HOLDER_COUNT.may_load(deps.storage)?.unwrap_or_default();
+    Ok(HolderCountResponse { count })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -86,7 +158,8 @@ mod tests {
     use cosmwasm_std::{coins, from_json, DepsMut, Uint128};
     use cw20::{Cw20Coin, Expiration, TokenInfoResponse};
 
-    use crate::contract::{execute, instantiate, query, query_token_info};
+    use crate::contract::tests::execute;
+    use crate::contract::{instantiate, query, query_token_info};
     use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
 
     // this will set up the instantiation for other tests
@@ -101,6 +174,7 @@ mod tests {
             }],
             mint: None,
             marketing: None,
+            ..InstantiateMsg::default()
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
@@ -323,4 +397,98 @@ mod tests {
                 .unwrap();
         assert_eq!(accounts.accounts, expected_order[3..].to_vec());
     }
+
+    #[test]
+    fn top_holders_and_count() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+        let acct1 = deps.api.addr_make("acct1").to_string();
+        let acct2 = deps.api.addr_make("zebra").to_string();
+        let acct3 = deps.api.addr_make("nice").to_string();
+        let acct4 = deps.api.addr_make("aaardvark").to_string();
+
+        // only available with the index
+        do_instantiate(deps.as_mut(), &acct1, Uint128::new(1000));
+        let err = query_holder_count(deps.as_ref()).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Holder index is not enabled for this token")
+        );
+
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+        let instantiate_msg = InstantiateMsg {
+            name: "Auto Gen".to_string(),
+            symbol: "AUTO".to_string(),
+            decimals: 3,
+            initial_balances: vec![Cw20Coin {
+                address: acct1.clone(),
+                amount: Uint128::new(1000),
+            }],
+            enable_holder_index: true,
+            ..InstantiateMsg::default()
+        };
+        let info = mock_info("creator", &[]);
+        let env = mock_env();
+        instantiate(deps.as_mut(), env.clone(), info, instantiate_msg).unwrap();
+
+        let info = mock_info(acct1.as_ref(), &[]);
+        for (recipient, amount) in [(&acct2, 300), (&acct3, 100), (&acct4, 200)] {
+            let msg = ExecuteMsg::Transfer {
+                recipient: recipient.clone(),
+                amount: Uint128::new(amount),
+            };
+            execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        }
+        // emptied accounts are no longer holders
+        let msg = ExecuteMsg::Transfer {
+            recipient: acct2.clone(),
+            amount: Uint128::new(100),
+        };
+        execute(deps.as_mut(), env, mock_info(acct3.as_ref(), &[]), msg).unwrap();
+        assert_eq!(query_holder_count(deps.as_ref()).unwrap().count, 3);
+
+        let coin = |address: &String, amount: u128| Cw20Coin {
+            address: address.clone(),
+            amount: Uint128::new(amount),
+        };
+        let top = query_top_holders(deps.as_ref(), None, Some(2)).unwrap();
+        assert_eq!(top.holders, vec![coin(&acct2, 400), coin(&acct1, 400)]);
+        let last = top.holders[1].clone();
+        let top = query_top_holders(deps.as_ref(), Some(last.clone()), None).unwrap();
+        assert_eq!(top.holders, vec![coin(&acct4, 200)]);
+
+        // the cursor still works after the last holder of the page was emptied
+        let msg = ExecuteMsg::Transfer {
+            recipient: acct2.clone(),
+            amount: Uint128::new(400),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(acct1.as_ref(), &[]),
+            msg,
+        )
+        .unwrap();
+        let top = query_top_holders(deps.as_ref(), Some(last), None).unwrap();
+        assert_eq!(top.holders, vec![coin(&acct4, 200)]);
+
+        // all balances can be listed, including emptied ones
+        let balances = query_all_balances(deps.as_ref(), None, None)
+            .unwrap()
+            .balances;
+        let balance_of = |addr: &String| {
+            balances
+                .iter()
+                .find(|b| &b.address == addr)
+                .unwrap()
+                .amount
+                .u128()
+        };
+        assert_eq!(balances.len(), 4);
+        assert_eq!(balance_of(&acct2), 800);
+        assert_eq!(balance_of(&acct3), 0);
+        let page = query_all_balances(deps.as_ref(), Some(balances[1].address.clone()), None)
+            .unwrap()
+            .balances;
+        assert_eq!(page, balances[2..]);
+    }
 }
@@ -1,4 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
+use cw_controllers::{AdminError, HookError};
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -6,6 +7,12 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Hook(#[from] HookError),
+
+    #[error("{0}")]
+    Admin(#[from] AdminError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -26,6 +33,15 @@ pub enum ContractError {
     #[error("Minting cannot exceed the cap")]
     CannotExceedCap {},
 
+    #[error("Minting cannot exceed the cap of the minter")]
+    CannotExceedMinterCap {},
+
+    #[error("Minter is expired")]
+    MinterExpired {},
+
+    #[error("Token is not mintable")]
+    MintingDisabled {},
+
     #[error("Logo binary data exceeds 5KB limit")]
     LogoTooBig {},
 
@@ -40,4 +56,52 @@ pub enum ContractError {
 
     #[error("Duplicate initial balance addresses")]
     DuplicateInitialBalanceAddresses {},
+
+    #[error("Batch must not be empty")]
+    EmptyBatch {},
+
+    #[error("Invalid vesting schedule")]
+    InvalidVestingSchedule {},
+
+    #[error("Cannot move unvested tokens")]
+    UnvestedTokens {},
+
+    #[error("Token transfers are paused")]
+    Paused {},
+
+    #[error("Cannot remove the pauser while paused")]
+    CannotRemovePauser {},
+
+    #[error("Account {address} is frozen")]
+    Frozen { address: String },
+
+    #[error("Transfer rejected by {hook}")]
+    TransferRejected { hook: String },
+
+    #[error("Public key does not belong to the owner")]
+    PermitKeyMismatch {},
+
+    #[error("Permit deadline passed")]
+    PermitExpired {},
+
+    #[error("Permit nonce does not match")]
+    InvalidPermitNonce {},
+
+    #[error("Invalid permit signature")]
+    InvalidPermitSignature {},
+
+    #[error("No rewards")]
+    NoRewards {},
+
+    #[error("Cannot deposit rewards without any tokens in circulation")]
+    NoSupply {},
+
+    #[error("Denom is not accepted as rewards")]
+    UnsupportedRewardDenom {},
+
+    #[error("Reward deposit must be at least {min}")]
+    RewardBelowMinimum { min: Uint128 },
+
+    #[error("Cannot accept more than {max} reward denoms")]
+    TooManyRewardDenoms { max: usize },
 }
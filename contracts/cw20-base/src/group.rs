@@ -0,0 +1,111 @@
+use cosmwasm_std::{Addr, Deps, Order, StdResult, Storage, SubMsg, Uint128, Uint64};
+use cw4::{
+    Member, MemberChangedHookMsg, MemberDiff, MemberListResponse, MemberResponse,
+    TotalWeightResponse,
+};
+use cw_storage_plus::Bound;
+
+use crate::state::{GroupConfig, BALANCES, GROUP_CONFIG, MEMBERS, MEMBER_HOOKS, TOTAL_WEIGHT};
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+/// The weight of a holder, capped at `u64::MAX` so huge balances cannot block transfers.
+fn calc_weight(balance: Uint128, cfg: &GroupConfig) -> StdResult<Option<u64>> {
+    if balance < cfg.min_bond {
+        Ok(None)
+    } else {
+        let w = balance.checked_div(cfg.tokens_per_weight)?;
+        Ok(Some(u64::try_from(w.u128()).unwrap_or(u64::MAX)))
+    }
+}
+
+/// Updates the weight of the holders after their balances changed, returning the messages
+/// for the member hooks. Does nothing unless the token is used as a group.
+pub fn update_members(
+    storage: &mut dyn Storage,
+    height: u64,
+    holders: &[&Addr],
+) -> StdResult<Vec<SubMsg>> {
+    let Some(cfg) = GROUP_CONFIG.may_load(storage)? else {
+        return Ok(vec![]);
+    };
+
+    let mut total = Uint64::new(TOTAL_WEIGHT.may_load(storage)?.unwrap_or_default());
+    let mut diffs = vec![];
+    for holder in holders {
+        let balance = BALANCES.may_load(storage, holder)?.unwrap_or_default();
+        let new = calc_weight(balance, &cfg)?;
+        let old = MEMBERS.may_load(storage, holder)?;
+
+        // short-circuit if no change
+        if new == old {
+            continue;
+        }
+        match new.as_ref() {
+            Some(w) => MEMBERS.save(storage, holder, w, height),
+            None => MEMBERS.remove(storage, holder, height),
+        }?;
+        // the total saturates like the weights, instead of failing the transfer
+        total = total
+            .saturating_sub(old.unwrap_or_default().into())
+            .saturating_add(new.unwrap_or_default().into());
+        diffs.push(MemberDiff::new(*holder, old, new));
+    }
+
+    if diffs.is_empty() {
+        return Ok(vec![]);
+    }
+    TOTAL_WEIGHT.save(storage, &total.u64(), height)?;
+
+    // alert the hooks
+    MEMBER_HOOKS.prepare_hooks(storage, |h| {
+        MemberChangedHookMsg::new(diffs.clone())
+            .into_cosmos_msg(h)
+            .map(SubMsg::new)
+    })
+}
+
+pub fn query_total_weight(deps: Deps, height: Option<u64>) -> StdResult<TotalWeightResponse> {
+    let weight = match height {
+        Some(h) => TOTAL_WEIGHT.may_load_at_height(deps.storage, h),
+        None => TOTAL_WEIGHT.may_load(deps.storage),
+    }?
+    .unwrap_or_default();
+    Ok(TotalWeightResponse { weight })
+}
+
+pub fn query_member(deps: Deps, addr: String, height: Option<u64>) -> StdResult<MemberResponse> {
+    let addr = deps.api.addr_validate(&addr)?;
+    let weight = match height {
+        Some(h) => MEMBERS.may_load_at_height(deps.storage, &addr, h),
+        None => MEMBERS.may_load(deps.storage, &addr),
+    }?;
+    Ok(MemberResponse { weight })
+}
+
+pub fn query_list_members(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<MemberListResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let addr = start_after
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    let start = addr.as_ref().map(Bound::exclusive);
+
+    let members = MEMBERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|(addr, weight)| Member {
+                addr: addr.into(),
+                weight,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(MemberListResponse { members })
+}
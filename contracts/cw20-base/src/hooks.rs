@@ -0,0 +1,84 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, CosmosMsg, Deps, StdResult, SubMsg, Uint128, WasmMsg,
+};
+
+use crate::error::ContractError;
+use crate::state::{TRANSFER_CHECKS, TRANSFER_HOOKS};
+
+/// TransferHookMsg is sent to the transfer hooks under the `TransferHook()` variant of their
+/// ExecuteMsg after every transfer, and to the transfer checks under the `CanTransfer()`
+/// variant of their QueryMsg before it
+#[cw_serde]
+pub struct TransferHookMsg {
+    pub from: String,
+    pub to: String,
+    pub amount: Uint128,
+}
+
+impl TransferHookMsg {
+    pub fn new(from: &Addr, to: &Addr, amount: Uint128) -> Self {
+        TransferHookMsg {
+            from: from.into(),
+            to: to.into(),
+            amount,
+        }
+    }
+
+    /// serializes the message
+    pub fn into_json_binary(self) -> StdResult<Binary> {
+        let msg = TransferHookExecuteMsg::TransferHook(self);
+        to_json_binary(&msg)
+    }
+
+    /// creates a cosmos_msg sending this struct to the named contract
+    pub fn into_cosmos_msg<T: Into<String>>(self, contract_addr: T) -> StdResult<CosmosMsg> {
+        let msg = self.into_json_binary()?;
+        let execute = WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            msg,
+            funds: vec![],
+        };
+        Ok(execute.into())
+    }
+}
+
+// This is just a helper to properly serialize the above message
+#[cw_serde]
+enum TransferHookExecuteMsg {
+    TransferHook(TransferHookMsg),
+}
+
+/// Query the transfer checks must answer
+#[cw_serde]
+pub enum TransferCheckQueryMsg {
+    CanTransfer(TransferHookMsg),
+}
+
+#[cw_serde]
+pub struct CanTransferResponse {
+    pub allowed: bool,
+}
+
+/// Asks every transfer check whether the transfer may happen, and returns the messages
+/// informing the transfer hooks once it did
+pub fn prepare_transfer_hooks(
+    deps: Deps,
+    from: &Addr,
+    to: &Addr,
+    amount: Uint128,
+) -> Result<Vec<SubMsg>, ContractError> {
+    let msg = TransferHookMsg::new(from, to, amount);
+
+    for check in TRANSFER_CHECKS.query_hooks(deps)?.hooks {
+        let query = TransferCheckQueryMsg::CanTransfer(msg.clone());
+        let res: CanTransferResponse = deps.querier.query_wasm_smart(&check, &query)?;
+        if !res.allowed {
+            return Err(ContractError::TransferRejected { hook: check });
+        }
+    }
+
+    Ok(TRANSFER_HOOKS.prepare_hooks(deps.storage, |h| {
+        msg.clone().into_cosmos_msg(h).map(SubMsg::new)
+    })?)
+}
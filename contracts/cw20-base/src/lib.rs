@@ -8,7 +8,14 @@ Implements:
 
 - [x] CW20 Base
 - [x] Mintable extension
+- [x] Multiple minters
 - [x] Allowances extension
+- [x] cw4 group queries, weighted by balance
+- [x] Vesting schedules
+- [x] Pausing and freezing accounts
+- [x] Transfer hooks
+- [x] Signed permits
+- [x] Reward distribution to holders
 
 For more information on this contract, please check out the
 [README](https://github.com/CosmWasm/cw-plus/blob/main/contracts/cw20-base/README.md).
@@ -18,7 +25,14 @@ pub mod allowances;
 pub mod contract;
 pub mod enumerable;
 mod error;
+pub mod group;
+pub mod hooks;
+pub mod minters;
 pub mod msg;
+pub mod pause;
+pub mod permit;
+pub mod rewards;
 pub mod state;
+pub mod vesting;
 
 pub use crate::error::ContractError;
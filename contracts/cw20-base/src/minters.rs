@@ -0,0 +1,115 @@
+use cosmwasm_std::{
+    Addr, BlockInfo, Deps, DepsMut, MessageInfo, Order, Response, StdError, StdResult, Storage,
+    Uint128,
+};
+use cw20::Expiration;
+use cw_storage_plus::Bound;
+
+use crate::error::ContractError;
+use crate::msg::{MinterInfo, MintersResponse};
+use crate::state::{RegisteredMinter, ADMIN, MINTERS, TOKEN_INFO};
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+/// Charges the amount against the budget of a registered minter. Returns Unauthorized if the
+/// sender is not one
+pub fn spend_minter_cap(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    minter: &Addr,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    let mut registered = MINTERS
+        .may_load(storage, minter)?
+        .ok_or(ContractError::Unauthorized {})?;
+    if registered.expires.is_expired(block) {
+        return Err(ContractError::MinterExpired {});
+    }
+
+    registered.minted = registered
+        .minted
+        .checked_add(amount)
+        .map_err(StdError::from)?;
+    if registered.cap.is_some_and(|cap| registered.minted > cap) {
+        return Err(ContractError::CannotExceedMinterCap {});
+    }
+    MINTERS.save(storage, minter, &registered)?;
+    Ok(())
+}
+
+/// Registers a minter, or updates its cap and expiration. What it already minted still counts
+/// against the new cap
+pub fn execute_add_minter(
+    deps: DepsMut,
+    block: &BlockInfo,
+    info: MessageInfo,
+    minter: String,
+    cap: Option<Uint128>,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    // registered minters mint within the mintable extension, not instead of it
+    if TOKEN_INFO.load(deps.storage)?.mint.is_none() {
+        return Err(ContractError::MintingDisabled {});
+    }
+    let minter_addr = deps.api.addr_validate(&minter)?;
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(block) {
+        return Err(ContractError::InvalidExpiration {});
+    }
+
+    let minted = MINTERS
+        .may_load(deps.storage, &minter_addr)?
+        .map(|registered| registered.minted)
+        .unwrap_or_default();
+    let registered = RegisteredMinter {
+        cap,
+        minted,
+        expires,
+    };
+    MINTERS.save(deps.storage, &minter_addr, &registered)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_minter")
+        .add_attribute("minter", minter_addr))
+}
+
+pub fn execute_remove_minter(
+    deps: DepsMut,
+    info: MessageInfo,
+    minter: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let minter_addr = deps.api.addr_validate(&minter)?;
+    MINTERS.remove(deps.storage, &minter_addr);
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_minter")
+        .add_attribute("minter", minter_addr))
+}
+
+pub fn query_minters(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<MintersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|s| Bound::ExclusiveRaw(s.into()));
+
+    let minters = MINTERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|(minter, registered)| MinterInfo {
+                minter: minter.into(),
+                cap: registered.cap,
+                minted: registered.minted,
+                expires: registered.expires,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(MintersResponse { minters })
+}
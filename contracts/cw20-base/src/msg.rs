@@ -1,11 +1,15 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{StdError, StdResult, Uint128};
-use cw20::{Cw20Coin, Logo, MinterResponse};
+use cosmwasm_std::{Binary, StdError, StdResult, Uint128};
+use cw20::{Cw20Coin, Cw20ReceiveMsg, Denom, Expiration, Logo, MinterResponse};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 pub use cw20::Cw20ExecuteMsg as ExecuteMsg;
 
+pub use crate::state::{
+    GroupConfig, RewardDenom, VestingClock, VestingCurve, VestingSchedule, VestingStep,
+};
+
 #[cw_serde]
 pub struct InstantiateMarketingInfo {
     pub project: Option<String>,
@@ -14,8 +18,15 @@ pub struct InstantiateMarketingInfo {
     pub logo: Option<Logo>,
 }
 
+/// Balance created at instantiation, locked until it vests
+#[cw_serde]
+pub struct VestingAccount {
+    pub address: String,
+    pub schedule: VestingSchedule,
+}
+
 #[cw_serde]
-#[cfg_attr(test, derive(Default))]
+#[derive(Default)]
 pub struct InstantiateMsg {
     pub name: String,
     pub symbol: String,
@@ -23,6 +34,25 @@ pub struct InstantiateMsg {
     pub initial_balances: Vec<Cw20Coin>,
     pub mint: Option<MinterResponse>,
     pub marketing: Option<InstantiateMarketingInfo>,
+    /// Keeps the history of balances and total supply, so they can be queried at past heights
+    #[serde(default)]
+    pub enable_snapshots: bool,
+    /// Indexes the holders by balance, enabling `TopHolders` and `HolderCount`
+    #[serde(default)]
+    pub enable_holder_index: bool,
+    /// Manages the minters, the member hooks, the transfer hooks and checks and the
+    /// reward denoms
+    #[serde(default)]
+    pub admin: Option<String>,
+    /// Makes the holders a cw4 group, see `GroupConfig`
+    #[serde(default)]
+    pub group: Option<GroupConfig>,
+    /// Locked balances, in addition to `initial_balances`
+    #[serde(default)]
+    pub initial_vesting: Vec<VestingAccount>,
+    /// May pause transfers and freeze accounts
+    #[serde(default)]
+    pub pauser: Option<String>,
 }
 
 impl InstantiateMsg {
@@ -45,6 +75,13 @@ impl InstantiateMsg {
         if self.decimals > 18 {
             return Err(StdError::generic_err("Decimals must not exceed 18"));
         }
+        if self
+            .group
+            .as_ref()
+            .is_some_and(|group| group.tokens_per_weight.is_zero())
+        {
+            return Err(StdError::generic_err("Tokens per weight must not be zero"));
+        }
         Ok(())
     }
 
@@ -70,6 +107,104 @@ impl InstantiateMsg {
     }
 }
 
+/// Messages of the cw20-base extensions that are not part of the cw20 spec
+#[cw_serde]
+pub enum ExtensionMsg {
+    /// Only with "approval" extension. Sets the spender's allowance on the owner's account
+    /// to amount, as signed off-chain by the owner. Can be submitted by anyone.
+    Permit(PermitMsg),
+    /// Only with "approval" extension. Skips the sender's current permit nonce, cancelling
+    /// the permits they signed but were not submitted yet.
+    IncreaseNonce {},
+    /// Only with the "mintable" extension. If authorized, mints the amount of the schedule
+    /// to the recipient, who cannot move it before it vests.
+    MintVesting {
+        recipient: String,
+        schedule: VestingSchedule,
+    },
+    /// Only the admin, and only with the "mintable" extension. Allows the minter to mint up to
+    /// cap tokens until it expires, within the cap of the token. Updates the cap and expiration
+    /// of a registered minter.
+    AddMinter {
+        minter: String,
+        cap: Option<Uint128>,
+        expires: Option<Expiration>,
+    },
+    /// Only the admin. Unregisters a minter added with `AddMinter`.
+    RemoveMinter { minter: String },
+    /// Only the pauser. Halts all transfers, sends and burns until `Unpause`.
+    Pause {},
+    /// Only the pauser. Resumes transfers, sends and burns.
+    Unpause {},
+    /// Only the pauser. Prevents the account from moving, receiving or burning tokens.
+    Freeze { address: String },
+    /// Only the pauser. Removes the account from the freeze list.
+    Unfreeze { address: String },
+    /// Only the pauser. Hands the role over, or removes it forever with None, which is only
+    /// possible while the token is not paused.
+    UpdatePauser { pauser: Option<String> },
+    /// Only the admin. Changes or removes the admin managing the minters, the member hooks,
+    /// the transfer hooks and checks and the reward denoms.
+    UpdateAdmin { admin: Option<String> },
+    /// Only with the "group" extension. Add a contract to be informed of weight changes,
+    /// same as in cw4. Only the admin may call it.
+    AddHook { addr: String },
+    /// Only with the "group" extension. Remove a member hook. Only the admin may call it.
+    RemoveHook { addr: String },
+    /// Only the admin. Add a contract to be informed after every transfer with a
+    /// `TransferHook(TransferHookMsg)` execute message.
+    AddTransferHook { addr: String },
+    /// Only the admin. Remove a transfer hook.
+    RemoveTransferHook { addr: String },
+    /// Only the admin. Add a contract queried with `CanTransfer(TransferHookMsg)`
+    /// before every transfer, which may reject it.
+    AddTransferCheck { addr: String },
+    /// Only the admin. Remove a transfer check.
+    RemoveTransferCheck { addr: String },
+    /// Only the admin. Accept the denom as rewards, in deposits of at least `min_deposit`.
+    /// Also updates the minimum of an accepted denom.
+    AddRewardDenom { denom: Denom, min_deposit: Uint128 },
+    /// Only the admin. Stop accepting the denom as rewards. What was deposited
+    /// so far can still be claimed.
+    RemoveRewardDenom { denom: Denom },
+    /// Distributes the native tokens sent along pro-rata to the current holders.
+    /// They must be accepted reward denoms.
+    DepositRewards {},
+    /// Distributes the cw20 tokens received pro-rata to the current holders.
+    /// The sending contract must be an accepted reward denom, and the embedded
+    /// message must be a `ReceiveMsg`.
+    Receive(Cw20ReceiveMsg),
+    /// Pays out the rewards accrued by the sender in `denom`, or in every denom if unset.
+    ClaimRewards { denom: Option<Denom> },
+}
+
+/// Everything the contract executes, the cw20 messages as well as those of the extensions.
+/// Both serialize the same as on their own.
+#[cw_serde]
+#[serde(untagged)]
+pub enum ContractExecuteMsg {
+    Base(ExecuteMsg),
+    Extension(ExtensionMsg),
+}
+
+impl From<ExecuteMsg> for ContractExecuteMsg {
+    fn from(msg: ExecuteMsg) -> Self {
+        ContractExecuteMsg::Base(msg)
+    }
+}
+
+impl From<ExtensionMsg> for ContractExecuteMsg {
+    fn from(msg: ExtensionMsg) -> Self {
+        ContractExecuteMsg::Extension(msg)
+    }
+}
+
+/// Messages accepted along cw20 tokens sent to this contract
+#[cw_serde]
+pub enum ReceiveMsg {
+    DepositRewards {},
+}
+
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
@@ -79,14 +214,33 @@ pub enum QueryMsg {
     /// Returns metadata on the contract - name, decimals, supply, etc.
     #[returns(cw20::TokenInfoResponse)]
     TokenInfo {},
+    /// Only in snapshot mode.
+    /// Returns the balance of the given address at the beginning of the block `at_height`.
+    #[returns(cw20::BalanceResponse)]
+    BalanceAt { address: String, at_height: u64 },
+    /// Only in snapshot mode.
+    /// Returns the token info with the total supply at the beginning of the block `at_height`.
+    #[returns(cw20::TokenInfoResponse)]
+    TokenInfoAt { at_height: u64 },
     /// Only with "mintable" extension.
     /// Returns who can mint and the hard cap on maximum tokens after minting.
     #[returns(cw20::MinterResponse)]
     Minter {},
+    /// Only with "mintable" extension.
+    /// Returns the minters added by the admin, with their caps. Supports pagination.
+    #[returns(MintersResponse)]
+    Minters {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     /// Only with "allowance" extension.
     /// Returns how much spender can use from owner account, 0 if unset.
     #[returns(cw20::AllowanceResponse)]
     Allowance { owner: String, spender: String },
+    /// Only with "allowance" extension.
+    /// Returns the nonce the next permit of the owner must use.
+    #[returns(NonceResponse)]
+    Nonce { owner: String },
     /// Only with "enumerable" extension (and "allowances")
     /// Returns all allowances this owner has approved. Supports pagination.
     #[returns(cw20::AllAllowancesResponse)]
@@ -110,6 +264,24 @@ pub enum QueryMsg {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    /// Returns all accounts that have balances, along with them. Supports pagination.
+    #[returns(AllBalancesResponse)]
+    AllBalances {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Only with the holder index.
+    /// Returns the holders with the highest balances first. Supports pagination, continuing
+    /// after the last holder and balance of the previous page.
+    #[returns(TopHoldersResponse)]
+    TopHolders {
+        start_after: Option<Cw20Coin>,
+        limit: Option<u32>,
+    },
+    /// Only with the holder index.
+    /// Returns the number of accounts with a non-zero balance.
+    #[returns(HolderCountResponse)]
+    HolderCount {},
     /// Only with "marketing" extension
     /// Returns more metadata on the contract to display in the client:
     /// - description, logo, project url, etc.
@@ -120,6 +292,152 @@ pub enum QueryMsg {
     /// contract.
     #[returns(cw20::DownloadLogoResponse)]
     DownloadLogo {},
+    /// Only with "group" extension
+    /// Returns the weight of the holder, None if not a member.
+    #[returns(cw4::MemberResponse)]
+    Member {
+        addr: String,
+        at_height: Option<u64>,
+    },
+    /// Only with "group" extension
+    /// Returns the total weight of all members.
+    #[returns(cw4::TotalWeightResponse)]
+    TotalWeight { at_height: Option<u64> },
+    /// Only with "group" extension
+    /// Returns all members with their weight. Supports pagination.
+    #[returns(cw4::MemberListResponse)]
+    ListMembers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the vesting schedules of the address, with the amounts vested and still locked
+    /// over all of them.
+    #[returns(VestingScheduleResponse)]
+    VestingSchedule { address: String },
+    /// Returns whether the token is paused and who may pause it
+    #[returns(PauseInfoResponse)]
+    PauseInfo {},
+    /// Returns the accounts on the freeze list. Supports pagination.
+    #[returns(FrozenAccountsResponse)]
+    FrozenAccounts {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the admin managing the minters, hooks, transfer checks and reward denoms
+    #[returns(cw_controllers::AdminResponse)]
+    Admin {},
+    /// Returns the contracts informed of weight changes
+    #[returns(cw_controllers::HooksResponse)]
+    Hooks {},
+    /// Returns the contracts informed after every transfer
+    #[returns(cw_controllers::HooksResponse)]
+    TransferHooks {},
+    /// Returns the contracts queried before every transfer
+    #[returns(cw_controllers::HooksResponse)]
+    TransferChecks {},
+    /// Returns the rewards the address can claim, in every denom deposited so far
+    #[returns(PendingRewardsResponse)]
+    PendingRewards { address: String },
+    /// Returns the denoms accepted as rewards, with their minimum deposit
+    #[returns(RewardDenomsResponse)]
+    RewardDenoms {},
+}
+
+#[cw_serde]
+pub struct AllBalancesResponse {
+    pub balances: Vec<Cw20Coin>,
+}
+
+#[cw_serde]
+pub struct TopHoldersResponse {
+    pub holders: Vec<Cw20Coin>,
+}
+
+#[cw_serde]
+pub struct HolderCountResponse {
+    pub count: u64,
+}
+
+#[cw_serde]
+pub struct MinterInfo {
+    pub minter: String,
+    pub cap: Option<Uint128>,
+    pub minted: Uint128,
+    pub expires: Expiration,
+}
+
+#[cw_serde]
+pub struct MintersResponse {
+    pub minters: Vec<MinterInfo>,
+}
+
+#[cw_serde]
+pub struct PermitMsg {
+    pub owner: String,
+    pub spender: String,
+    pub amount: Uint128,
+    pub expires: Option<Expiration>,
+    /// the permit cannot be submitted anymore once this passed
+    pub deadline: Expiration,
+    /// must match the `Nonce` of the owner
+    pub nonce: u64,
+    /// secp256k1 signature of the sha256 hash of the JSON serialized `PermitPayload`
+    pub signature: Binary,
+    /// compressed secp256k1 key of the owner's account
+    pub pubkey: Binary,
+}
+
+/// What the owner signs for a `Permit`. Binding it to the chain and the token contract
+/// prevents replays on other deployments
+#[cw_serde]
+pub struct PermitPayload {
+    pub chain_id: String,
+    pub contract: String,
+    pub owner: String,
+    pub spender: String,
+    pub amount: Uint128,
+    pub expires: Option<Expiration>,
+    pub deadline: Expiration,
+    pub nonce: u64,
+}
+
+#[cw_serde]
+pub struct NonceResponse {
+    pub nonce: u64,
+}
+
+#[cw_serde]
+pub struct VestingScheduleResponse {
+    pub schedules: Vec<VestingSchedule>,
+    pub vested: Uint128,
+    pub locked: Uint128,
+}
+
+#[cw_serde]
+pub struct PauseInfoResponse {
+    pub paused: bool,
+    pub pauser: Option<String>,
+}
+
+#[cw_serde]
+pub struct FrozenAccountsResponse {
+    pub accounts: Vec<String>,
+}
+
+#[cw_serde]
+pub struct RewardAmount {
+    pub denom: Denom,
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct PendingRewardsResponse {
+    pub rewards: Vec<RewardAmount>,
+}
+
+#[cw_serde]
+pub struct RewardDenomsResponse {
+    pub denoms: Vec<RewardDenom>,
 }
 
 #[derive(Serialize, Deserialize, JsonSchema)]
@@ -172,4 +490,27 @@ mod tests {
             assert!(!msg.has_valid_symbol());
         });
     }
+
+    #[test]
+    fn execute_msgs_serialize_flat() {
+        use cosmwasm_std::{from_json, to_json_binary};
+
+        let transfer = ContractExecuteMsg::from(ExecuteMsg::Transfer {
+            recipient: "addr".to_string(),
+            amount: Uint128::new(5),
+        });
+        let json = to_json_binary(&transfer).unwrap();
+        assert_eq!(
+            json.as_slice(),
+            br#"{"transfer":{"recipient":"addr","amount":"5"}}"#
+        );
+        assert_eq!(from_json::<ContractExecuteMsg>(&json).unwrap(), transfer);
+
+        let pause = ContractExecuteMsg::from(ExtensionMsg::Pause {});
+        let json = to_json_binary(&pause).unwrap();
+        assert_eq!(json.as_slice(), br#"{"pause":{}}"#);
+        assert_eq!(from_json::<ContractExecuteMsg>(&json).unwrap(), pause);
+
+        from_json::<ContractExecuteMsg>(br#"{"unknown":{}}"#).unwrap_err();
+    }
 }
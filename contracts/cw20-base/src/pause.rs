@@ -0,0 +1,100 @@
+use cosmwasm_std::{Addr, Deps, DepsMut, Empty, MessageInfo, Order, Response, StdResult, Storage};
+use cw_storage_plus::Bound;
+
+use crate::error::ContractError;
+use crate::msg::{FrozenAccountsResponse, PauseInfoResponse};
+use crate::state::{FROZEN, PAUSED, PAUSER};
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+/// Returns Paused while the pauser halted transfers, sends and burns
+pub fn assert_not_paused(storage: &dyn Storage) -> Result<(), ContractError> {
+    if PAUSED.may_load(storage)?.unwrap_or_default() {
+        return Err(ContractError::Paused {});
+    }
+    Ok(())
+}
+
+/// Returns Frozen for the first of the accounts on the freeze list
+pub fn assert_not_frozen(storage: &dyn Storage, accounts: &[&Addr]) -> Result<(), ContractError> {
+    for account in accounts {
+        if FROZEN.has(storage, account) {
+            return Err(ContractError::Frozen {
+                address: account.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+pub fn execute_set_paused(
+    deps: DepsMut,
+    info: MessageInfo,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    PAUSER.assert_admin(deps.as_ref(), &info.sender)?;
+    PAUSED.save(deps.storage, &paused)?;
+
+    let action = if paused { "pause" } else { "unpause" };
+    Ok(Response::new().add_attribute("action", action))
+}
+
+pub fn execute_update_pauser(
+    deps: DepsMut,
+    info: MessageInfo,
+    pauser: Option<String>,
+) -> Result<Response, ContractError> {
+    let pauser = pauser
+        .map(|pauser| deps.api.addr_validate(&pauser))
+        .transpose()?;
+    // nobody could unpause the token anymore
+    if pauser.is_none() && PAUSED.may_load(deps.storage)?.unwrap_or_default() {
+        return Err(ContractError::CannotRemovePauser {});
+    }
+    Ok(PAUSER.execute_update_admin(deps, info, pauser)?)
+}
+
+pub fn execute_set_frozen(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+    frozen: bool,
+) -> Result<Response, ContractError> {
+    PAUSER.assert_admin(deps.as_ref(), &info.sender)?;
+    let addr = deps.api.addr_validate(&address)?;
+    if frozen {
+        FROZEN.save(deps.storage, &addr, &Empty {})?;
+    } else {
+        FROZEN.remove(deps.storage, &addr);
+    }
+
+    let action = if frozen { "freeze" } else { "unfreeze" };
+    Ok(Response::new()
+        .add_attribute("action", action)
+        .add_attribute("address", addr))
+}
+
+pub fn query_pause_info(deps: Deps) -> StdResult<PauseInfoResponse> {
+    let paused = PAUSED.may_load(deps.storage)?.unwrap_or_default();
+    let pauser = PAUSER.get(deps)?.map(Into::into);
+    Ok(PauseInfoResponse { paused, pauser })
+}
+
+pub fn query_frozen_accounts(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<FrozenAccountsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|s| Bound::ExclusiveRaw(s.into()));
+
+    let accounts = FROZEN
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(Into::into))
+        .collect::<StdResult<_>>()?;
+
+    Ok(FrozenAccountsResponse { accounts })
+}
@@ -0,0 +1,110 @@
+use cosmwasm_std::{
+    attr, to_json_vec, CanonicalAddr, Deps, DepsMut, Env, MessageInfo, Response, StdError,
+    StdResult,
+};
+use cw20::AllowanceResponse;
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+use crate::error::ContractError;
+use crate::msg::{NonceResponse, PermitMsg, PermitPayload};
+use crate::state::{ALLOWANCES, ALLOWANCES_SPENDER, PERMIT_NONCES};
+
+/// Sets the allowance of the spender to the amount, as signed off-chain by the owner.
+/// Anyone may submit the permit, but every nonce can only be used once
+pub fn execute_permit(
+    deps: DepsMut,
+    env: Env,
+    permit: PermitMsg,
+) -> Result<Response, ContractError> {
+    let owner_addr = deps.api.addr_validate(&permit.owner)?;
+    let spender_addr = deps.api.addr_validate(&permit.spender)?;
+    if spender_addr == owner_addr {
+        return Err(ContractError::CannotSetOwnAccount {});
+    }
+    if permit.deadline.is_expired(&env.block) {
+        return Err(ContractError::PermitExpired {});
+    }
+    let expires = permit.expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::InvalidExpiration {});
+    }
+
+    // cosmos accounts are derived from their secp256k1 key as ripemd160(sha256(pubkey))
+    let key_hash = Ripemd160::digest(Sha256::digest(&permit.pubkey));
+    if deps.api.addr_canonicalize(owner_addr.as_str())? != CanonicalAddr::from(key_hash.as_slice())
+    {
+        return Err(ContractError::PermitKeyMismatch {});
+    }
+
+    let nonce = PERMIT_NONCES
+        .may_load(deps.storage, &owner_addr)?
+        .unwrap_or_default();
+    if permit.nonce != nonce {
+        return Err(ContractError::InvalidPermitNonce {});
+    }
+
+    let payload = PermitPayload {
+        chain_id: env.block.chain_id,
+        contract: env.contract.address.into(),
+        owner: permit.owner.clone(),
+        spender: permit.spender.clone(),
+        amount: permit.amount,
+        expires: permit.expires,
+        deadline: permit.deadline,
+        nonce,
+    };
+    let hash = Sha256::digest(to_json_vec(&payload)?);
+    let valid = deps
+        .api
+        .secp256k1_verify(&hash, &permit.signature, &permit.pubkey)
+        .map_err(StdError::from)?;
+    if !valid {
+        return Err(ContractError::InvalidPermitSignature {});
+    }
+    PERMIT_NONCES.save(deps.storage, &owner_addr, &(nonce + 1))?;
+
+    let key = (&owner_addr, &spender_addr);
+    if permit.amount.is_zero() {
+        ALLOWANCES.remove(deps.storage, key);
+        ALLOWANCES_SPENDER.remove(deps.storage, (key.1, key.0));
+    } else {
+        let allowance = AllowanceResponse {
+            allowance: permit.amount,
+            expires,
+        };
+        ALLOWANCES.save(deps.storage, key, &allowance)?;
+        ALLOWANCES_SPENDER.save(deps.storage, (key.1, key.0), &allowance)?;
+    }
+
+    let res = Response::new().add_attributes(vec![
+        attr("action", "permit"),
+        attr("owner", permit.owner),
+        attr("spender", permit.spender),
+        attr("amount", permit.amount),
+        attr("nonce", nonce.to_string()),
+    ]);
+    Ok(res)
+}
+
+/// Cancels the outstanding permits of the sender by moving on to the next nonce
+pub fn execute_increase_nonce(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let nonce = PERMIT_NONCES.update(deps.storage, &info.sender, |nonce| -> StdResult<_> {
+        Ok(nonce.unwrap_or_default() + 1)
+    })?;
+
+    let res = Response::new().add_attributes(vec![
+        attr("action", "increase_nonce"),
+        attr("owner", info.sender),
+        attr("nonce", nonce.to_string()),
+    ]);
+    Ok(res)
+}
+
+pub fn query_nonce(deps: Deps, owner: String) -> StdResult<NonceResponse> {
+    let owner_addr = deps.api.addr_validate(&owner)?;
+    let nonce = PERMIT_NONCES
+        .may_load(deps.storage, &owner_addr)?
+        .unwrap_or_default();
+    Ok(NonceResponse { nonce })
+}
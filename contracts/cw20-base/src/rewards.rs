@@ -0,0 +1,275 @@
+use cosmwasm_std::{
+    coins, from_json, to_json_binary, Addr, BankMsg, CosmosMsg, Decimal, Deps, DepsMut, Env,
+    MessageInfo, Response, StdError, StdResult, Storage, Uint128, WasmMsg,
+};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, Denom};
+
+use crate::error::ContractError;
+use crate::msg::{PendingRewardsResponse, ReceiveMsg, RewardAmount, RewardDenomsResponse};
+use crate::state::{
+    accrue_rewards, HolderReward, RewardDenom, RewardIndex, ADMIN, BALANCES, HOLDER_REWARDS,
+    REWARD_DENOMS, REWARD_INDEXES, TOKEN_INFO,
+};
+
+/// Every holder keeps one entry per denom ever deposited, so their number is bounded
+pub const MAX_REWARD_DENOMS: usize = 10;
+
+fn validate_denom(deps: Deps, denom: Denom) -> StdResult<Denom> {
+    match denom {
+        Denom::Native(denom) => Ok(Denom::Native(denom)),
+        Denom::Cw20(addr) => Ok(Denom::Cw20(deps.api.addr_validate(addr.as_str())?)),
+    }
+}
+
+/// Only the admin. Accepts the denom as rewards, or changes its minimum deposit
+pub fn execute_add_reward_denom(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: Denom,
+    min_deposit: Uint128,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let denom = validate_denom(deps.as_ref(), denom)?;
+
+    let mut denoms = REWARD_DENOMS.may_load(deps.storage)?.unwrap_or_default();
+    match denoms.iter_mut().find(|reward| reward.denom == denom) {
+        Some(reward) => reward.min_deposit = min_deposit,
+        None => {
+            // denoms removed from the whitelist keep their index, so count them as well
+            let indexes = REWARD_INDEXES.may_load(deps.storage)?.unwrap_or_default();
+            let indexed_only = indexes
+                .iter()
+                .filter(|index| !denoms.iter().any(|reward| reward.denom == index.denom))
+                .count();
+            if denoms.len() + indexed_only >= MAX_REWARD_DENOMS
+                && !indexes.iter().any(|index| index.denom == denom)
+            {
+                return Err(ContractError::TooManyRewardDenoms {
+                    max: MAX_REWARD_DENOMS,
+                });
+            }
+            denoms.push(RewardDenom {
+                denom: denom.clone(),
+                min_deposit,
+            });
+        }
+    }
+    REWARD_DENOMS.save(deps.storage, &denoms)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_reward_denom")
+        .add_attribute("denom", denom_string(&denom))
+        .add_attribute("min_deposit", min_deposit))
+}
+
+/// Only the admin. Stops accepting the denom, rewards deposited so far can still be claimed
+pub fn execute_remove_reward_denom(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: Denom,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut denoms = REWARD_DENOMS.may_load(deps.storage)?.unwrap_or_default();
+    denoms.retain(|reward| reward.denom != denom);
+    REWARD_DENOMS.save(deps.storage, &denoms)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_reward_denom")
+        .add_attribute("denom", denom_string(&denom)))
+}
+
+fn denom_string(denom: &Denom) -> String {
+    match denom {
+        Denom::Native(denom) => denom.clone(),
+        Denom::Cw20(contract) => contract.to_string(),
+    }
+}
+
+/// Spreads the amount over every token in circulation, except those held by this
+/// contract which nobody can claim. Holders settle their share on their next balance
+/// change or claim
+fn deposit_reward(
+    storage: &mut dyn Storage,
+    contract: &Addr,
+    denom: Denom,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    if amount.is_zero() {
+        return Err(ContractError::NoRewards {});
+    }
+    let min_deposit = REWARD_DENOMS
+        .may_load(storage)?
+        .unwrap_or_default()
+        .into_iter()
+        .find(|reward| reward.denom == denom)
+        .ok_or(ContractError::UnsupportedRewardDenom {})?
+        .min_deposit;
+    if amount < min_deposit {
+        return Err(ContractError::RewardBelowMinimum { min: min_deposit });
+    }
+    let own_balance = BALANCES.may_load(storage, contract)?.unwrap_or_default();
+    let supply = TOKEN_INFO
+        .load(storage)?
+        .total_supply
+        .checked_sub(own_balance)
+        .map_err(StdError::from)?;
+    if supply.is_zero() {
+        return Err(ContractError::NoSupply {});
+    }
+
+    let mut indexes = REWARD_INDEXES.may_load(storage)?.unwrap_or_default();
+    let pos = match indexes.iter().position(|index| index.denom == denom) {
+        Some(pos) => pos,
+        None => {
+            indexes.push(RewardIndex {
+                denom,
+                per_token: Decimal::zero(),
+                undistributed: Uint128::zero(),
+            });
+            indexes.len() - 1
+        }
+    };
+    let index = &mut indexes[pos];
+    // what rounds down is carried over to the next deposit rather than stuck
+    let amount = amount
+        .checked_add(index.undistributed)
+        .map_err(StdError::from)?;
+    let per_token = Decimal::checked_from_ratio(amount, supply)
+        .map_err(|e| StdError::generic_err(e.to_string()))?;
+    let distributed = supply
+        .checked_mul_floor(per_token)
+        .map_err(|e| StdError::generic_err(e.to_string()))?;
+    index.per_token = index
+        .per_token
+        .checked_add(per_token)
+        .map_err(StdError::from)?;
+    index.undistributed = amount.checked_sub(distributed).map_err(StdError::from)?;
+    REWARD_INDEXES.save(storage, &indexes)?;
+    Ok(())
+}
+
+pub fn execute_deposit_rewards(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    if info.funds.is_empty() {
+        return Err(ContractError::NoRewards {});
+    }
+    let mut res = Response::new().add_attribute("action", "deposit_rewards");
+    for coin in info.funds {
+        deposit_reward(
+            deps.storage,
+            &env.contract.address,
+            Denom::Native(coin.denom.clone()),
+            coin.amount,
+        )?;
+        res = res.add_attribute("rewards", coin.to_string());
+    }
+    Ok(res.add_attribute("from", info.sender))
+}
+
+/// Called by the cw20 contract of the rewards, on `Send`
+pub fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    match from_json(&wrapper.msg)? {
+        ReceiveMsg::DepositRewards {} => {
+            deposit_reward(
+                deps.storage,
+                &env.contract.address,
+                Denom::Cw20(info.sender.clone()),
+                wrapper.amount,
+            )?;
+            Ok(Response::new()
+                .add_attribute("action", "deposit_rewards")
+                .add_attribute("rewards", format!("{}{}", wrapper.amount, info.sender))
+                .add_attribute("from", wrapper.sender))
+        }
+    }
+}
+
+/// Pays out the rewards accrued by the sender in the given denom, or in all of them
+pub fn execute_claim_rewards(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: Option<Denom>,
+) -> Result<Response, ContractError> {
+    let mut rewards = pending_rewards(deps.as_ref(), &info.sender)?;
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    let mut res = Response::new().add_attribute("action", "claim_rewards");
+    for reward in rewards.iter_mut() {
+        if reward.pending.is_zero() {
+            continue;
+        }
+        if let Some(denom) = &denom {
+            if &reward.denom != denom {
+                continue;
+            }
+        }
+        match &reward.denom {
+            Denom::Native(denom) => {
+                msgs.push(
+                    BankMsg::Send {
+                        to_address: info.sender.to_string(),
+                        amount: coins(reward.pending.u128(), denom),
+                    }
+                    .into(),
+                );
+                res = res.add_attribute("rewards", format!("{}{}", reward.pending, denom));
+            }
+            Denom::Cw20(contract) => {
+                msgs.push(
+                    WasmMsg::Execute {
+                        contract_addr: contract.to_string(),
+                        msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                            recipient: info.sender.to_string(),
+                            amount: reward.pending,
+                        })?,
+                        funds: vec![],
+                    }
+                    .into(),
+                );
+                res = res.add_attribute("rewards", format!("{}{}", reward.pending, contract));
+            }
+        }
+        reward.pending = Uint128::zero();
+    }
+    if msgs.is_empty() {
+        return Err(ContractError::NoRewards {});
+    }
+    HOLDER_REWARDS.save(deps.storage, &info.sender, &rewards)?;
+
+    Ok(res.add_attribute("to", info.sender).add_messages(msgs))
+}
+
+/// Rewards of the holder, settled up to the current indexes
+fn pending_rewards(deps: Deps, holder: &Addr) -> StdResult<Vec<HolderReward>> {
+    let indexes = REWARD_INDEXES.may_load(deps.storage)?.unwrap_or_default();
+    let balance = BALANCES.may_load(deps.storage, holder)?.unwrap_or_default();
+    let rewards = HOLDER_REWARDS
+        .may_load(deps.storage, holder)?
+        .unwrap_or_default();
+    accrue_rewards(&indexes, rewards, balance)
+}
+
+pub fn query_pending_rewards(deps: Deps, address: String) -> StdResult<PendingRewardsResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let rewards = pending_rewards(deps, &address)?
+        .into_iter()
+        .map(|reward| RewardAmount {
+            denom: reward.denom,
+            amount: reward.pending,
+        })
+        .collect();
+    Ok(PendingRewardsResponse { rewards })
+}
+
+pub fn query_reward_denoms(deps: Deps) -> StdResult<RewardDenomsResponse> {
+    let denoms = REWARD_DENOMS.may_load(deps.storage)?.unwrap_or_default();
+    Ok(RewardDenomsResponse { denoms })
+}
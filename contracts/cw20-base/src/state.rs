@@ -1,8 +1,15 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, BlockInfo, Decimal, Empty, StdError, StdResult, Storage, Uint128};
+use cw4::{
+    MEMBERS_CHANGELOG, MEMBERS_CHECKPOINTS, MEMBERS_KEY, TOTAL_KEY, TOTAL_KEY_CHANGELOG,
+    TOTAL_KEY_CHECKPOINTS,
+};
+use cw_controllers::{Admin, Hooks};
+use cw_storage_plus::{
+    Index, IndexList, IndexedMap, Item, Map, MultiIndex, SnapshotItem, SnapshotMap, Strategy,
+};
 
-use cw20::{AllowanceResponse, Logo, MarketingInfoResponse};
+use cw20::{AllowanceResponse, Denom, Expiration, Logo, MarketingInfoResponse};
 
 #[cw_serde]
 pub struct TokenInfo {
@@ -20,17 +27,343 @@ pub struct MinterData {
     pub cap: Option<Uint128>,
 }
 
+/// Minter added by the admin, on top of the one in `TokenInfo`
+#[cw_serde]
+pub struct RegisteredMinter {
+    /// cap is how many tokens this minter can issue in total, within the token cap
+    pub cap: Option<Uint128>,
+    pub minted: Uint128,
+    pub expires: Expiration,
+}
+
+/// Turns the token holders into a cw4 group, weighted by their balance
+#[cw_serde]
+pub struct GroupConfig {
+    /// balance needed for each unit of weight
+    pub tokens_per_weight: Uint128,
+    /// holders with a lower balance are not members
+    pub min_bond: Uint128,
+}
+
+/// Rewards paid so far for every token held, in one denom
+#[cw_serde]
+pub struct RewardIndex {
+    pub denom: Denom,
+    pub per_token: Decimal,
+    /// deposited but lost to rounding, added to the next deposit
+    #[serde(default)]
+    pub undistributed: Uint128,
+}
+
+/// Denom accepted as rewards, whitelisted by the admin
+#[cw_serde]
+pub struct RewardDenom {
+    pub denom: Denom,
+    /// smallest amount accepted in a single deposit
+    pub min_deposit: Uint128,
+}
+
+/// Rewards of a holder in one denom, accrued up to `per_token`
+#[cw_serde]
+pub struct HolderReward {
+    pub denom: Denom,
+    pub per_token: Decimal,
+    pub pending: Uint128,
+}
+
 impl TokenInfo {
     pub fn get_cap(&self) -> Option<Uint128> {
         self.mint.as_ref().and_then(|v| v.cap)
     }
 }
 
+/// What the points of a vesting curve are measured in
+#[cw_serde]
+#[derive(Copy)]
+pub enum VestingClock {
+    Height,
+    /// seconds since the unix epoch
+    Time,
+}
+
+#[cw_serde]
+pub struct VestingStep {
+    pub at: u64,
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub enum VestingCurve {
+    /// Nothing vests before `cliff`, then the amount vests linearly from `start` to `end`,
+    /// releasing what accrued before the cliff at once
+    Linear { start: u64, cliff: u64, end: u64 },
+    /// Each step releases its amount once reached
+    Steps(Vec<VestingStep>),
+}
+
+/// Locks `amount` tokens of the holder until they vest
+#[cw_serde]
+pub struct VestingSchedule {
+    pub amount: Uint128,
+    pub clock: VestingClock,
+    pub curve: VestingCurve,
+}
+
+impl VestingSchedule {
+    /// returns true if the curve is well formed and releases exactly `amount`
+    pub fn is_valid(&self) -> bool {
+        if self.amount.is_zero() {
+            return false;
+        }
+        match &self.curve {
+            VestingCurve::Linear { start, cliff, end } => {
+                start <= cliff && cliff <= end && start < end
+            }
+            VestingCurve::Steps(steps) => {
+                let total = steps
+                    .iter()
+                    .try_fold(Uint128::zero(), |acc, step| acc.checked_add(step.amount));
+                !steps.is_empty() && total == Ok(self.amount)
+            }
+        }
+    }
+
+    /// amount released at the given block
+    pub fn vested(&self, block: &BlockInfo) -> Uint128 {
+        let now = match self.clock {
+            VestingClock::Height => block.height,
+            VestingClock::Time => block.time.seconds(),
+        };
+        match &self.curve {
+            VestingCurve::Linear { start, cliff, end } => {
+                if now < *cliff {
+                    Uint128::zero()
+                } else if now >= *end {
+                    self.amount
+                } else {
+                    self.amount.multiply_ratio(now - start, end - start)
+                }
+            }
+            VestingCurve::Steps(steps) => steps
+                .iter()
+                .filter(|step| step.at <= now)
+                .map(|step| step.amount)
+                .sum(),
+        }
+    }
+
+    /// amount still locked at the given block
+    pub fn locked(&self, block: &BlockInfo) -> Uint128 {
+        self.amount - self.vested(block)
+    }
+}
+
 pub const TOKEN_INFO: Item<TokenInfo> = Item::new("token_info");
+pub const ADMIN: Admin = Admin::new("admin");
+pub const MINTERS: Map<&Addr, RegisteredMinter> = Map::new("minters");
+/// May pause the token and freeze accounts
+pub const PAUSER: Admin = Admin::new("pauser");
+pub const PAUSED: Item<bool> = Item::new("paused");
+pub const FROZEN: Map<&Addr, Empty> = Map::new("frozen");
 pub const MARKETING_INFO: Item<MarketingInfoResponse> = Item::new("marketing_info");
 pub const LOGO: Item<Logo> = Item::new("logo");
 pub const BALANCES: Map<&Addr, Uint128> = Map::new("balance");
+/// Schedules locking part of the balance, see `vesting::assert_vested`
+pub const VESTING: Map<&Addr, Vec<VestingSchedule>> = Map::new("vesting");
+/// Same storage as `BALANCES`, along with the history of every balance. Only written to
+/// in snapshot mode
+pub const BALANCE_SNAPSHOTS: SnapshotMap<&Addr, Uint128> = SnapshotMap::new(
+    "balance",
+    "balance__checkpoints",
+    "balance__changelog",
+    Strategy::EveryBlock,
+);
+/// History of the total supply, only kept in snapshot mode
+pub const TOTAL_SUPPLY_SNAPSHOTS: SnapshotItem<Uint128> = SnapshotItem::new(
+    "total_supply",
+    "total_supply__checkpoints",
+    "total_supply__changelog",
+    Strategy::EveryBlock,
+);
+/// Set at instantiation when balances and total supply history is kept
+pub const SNAPSHOTS_ENABLED: Item<bool> = Item::new("snapshots_enabled");
+
+pub struct HolderIndexes<'a> {
+    pub balance: MultiIndex<'a, u128, Uint128, Addr>,
+}
+
+impl<'a> IndexList<Uint128> for HolderIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Uint128>> + '_> {
+        let v: Vec<&dyn Index<Uint128>> = vec![&self.balance];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Non-zero balances indexed by amount. Only kept when the holder index is enabled
+pub const HOLDERS: IndexedMap<&Addr, Uint128, HolderIndexes> = IndexedMap::new(
+    "holders",
+    HolderIndexes {
+        balance: MultiIndex::new(|_pk, balance| balance.u128(), "holders", "holders__balance"),
+    },
+);
+pub const HOLDER_COUNT: Item<u64> = Item::new("holder_count");
+/// Set at instantiation when holders are indexed by balance
+pub const HOLDER_INDEX_ENABLED: Item<bool> = Item::new("holder_index_enabled");
+
+/// Set at instantiation when the holders form a cw4 group
+pub const GROUP_CONFIG: Item<GroupConfig> = Item::new("group_config");
+/// Stored under the cw4 keys, so the token can be used as a `Cw4Contract`
+pub const MEMBERS: SnapshotMap<&Addr, u64> = SnapshotMap::new(
+    MEMBERS_KEY,
+    MEMBERS_CHECKPOINTS,
+    MEMBERS_CHANGELOG,
+    Strategy::EveryBlock,
+);
+pub const TOTAL_WEIGHT: SnapshotItem<u64> = SnapshotItem::new(
+    TOTAL_KEY,
+    TOTAL_KEY_CHECKPOINTS,
+    TOTAL_KEY_CHANGELOG,
+    Strategy::EveryBlock,
+);
+pub const MEMBER_HOOKS: Hooks = Hooks::new("cw4-hooks");
+
+/// Denoms that may be deposited as rewards, see `rewards::MAX_REWARD_DENOMS`
+pub const REWARD_DENOMS: Item<Vec<RewardDenom>> = Item::new("reward_denoms");
+/// One entry per denom ever deposited as rewards
+pub const REWARD_INDEXES: Item<Vec<RewardIndex>> = Item::new("reward_indexes");
+/// Settled by `save_balance` before every balance change
+pub const HOLDER_REWARDS: Map<&Addr, Vec<HolderReward>> = Map::new("holder_rewards");
+
+/// Informed after every transfer, see `hooks::TransferHookMsg`
+pub const TRANSFER_HOOKS: Hooks = Hooks::new("transfer_hooks");
+/// Queried before every transfer, any of them may reject it
+pub const TRANSFER_CHECKS: Hooks = Hooks::new("transfer_checks");
+
 pub const ALLOWANCES: Map<(&Addr, &Addr), AllowanceResponse> = Map::new("allowance");
 // TODO: After https://github.com/CosmWasm/cw-plus/issues/670 is implemented, replace this with a `MultiIndex` over `ALLOWANCES`
 pub const ALLOWANCES_SPENDER: Map<(&Addr, &Addr), AllowanceResponse> =
     Map::new("allowance_spender");
+/// Nonce expected in the next permit of each owner
+pub const PERMIT_NONCES: Map<&Addr, u64> = Map::new("permit_nonces");
+
+pub fn snapshots_enabled(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(SNAPSHOTS_ENABLED.may_load(storage)?.unwrap_or_default())
+}
+
+pub fn holder_index_enabled(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(HOLDER_INDEX_ENABLED.may_load(storage)?.unwrap_or_default())
+}
+
+fn save_balance(
+    storage: &mut dyn Storage,
+    address: &Addr,
+    balance: Uint128,
+    height: u64,
+) -> StdResult<()> {
+    settle_rewards(storage, address)?;
+    if holder_index_enabled(storage)? {
+        index_holder(storage, address, balance)?;
+    }
+    if snapshots_enabled(storage)? {
+        BALANCE_SNAPSHOTS.save(storage, address, &balance, height)
+    } else {
+        BALANCES.save(storage, address, &balance)
+    }
+}
+
+fn index_holder(storage: &mut dyn Storage, address: &Addr, balance: Uint128) -> StdResult<()> {
+    let was_holder = HOLDERS.has(storage, address);
+    let count = HOLDER_COUNT.may_load(storage)?.unwrap_or_default();
+    if balance.is_zero() {
+        if was_holder {
+            HOLDERS.remove(storage, address)?;
+            HOLDER_COUNT.save(storage, &(count - 1))?;
+        }
+    } else {
+        HOLDERS.save(storage, address, &balance)?;
+        if !was_holder {
+            HOLDER_COUNT.save(storage, &(count + 1))?;
+        }
+    }
+    Ok(())
+}
+
+/// Accrues the rewards of the holder on its current balance, before it changes
+fn settle_rewards(storage: &mut dyn Storage, address: &Addr) -> StdResult<()> {
+    let indexes = match REWARD_INDEXES.may_load(storage)? {
+        Some(indexes) => indexes,
+        None => return Ok(()),
+    };
+    let balance = BALANCES.may_load(storage, address)?.unwrap_or_default();
+    let rewards = HOLDER_REWARDS
+        .may_load(storage, address)?
+        .unwrap_or_default();
+    let rewards = accrue_rewards(&indexes, rewards, balance)?;
+    HOLDER_REWARDS.save(storage, address, &rewards)
+}
+
+/// Brings the rewards of a holder with the given balance up to date with the indexes.
+/// Denoms missing from `rewards` were deposited after the holder last settled, so
+/// accrue from zero
+pub fn accrue_rewards(
+    indexes: &[RewardIndex],
+    mut rewards: Vec<HolderReward>,
+    balance: Uint128,
+) -> StdResult<Vec<HolderReward>> {
+    for index in indexes {
+        let reward = match rewards.iter().position(|r| r.denom == index.denom) {
+            Some(pos) => &mut rewards[pos],
+            None => {
+                rewards.push(HolderReward {
+                    denom: index.denom.clone(),
+                    per_token: Decimal::zero(),
+                    pending: Uint128::zero(),
+                });
+                rewards.last_mut().unwrap()
+            }
+        };
+        let accrued = balance
+            .checked_mul_floor(index.per_token - reward.per_token)
+            .map_err(|e| StdError::generic_err(e.to_string()))?;
+        reward.pending = reward.pending.checked_add(accrued)?;
+        reward.per_token = index.per_token;
+    }
+    Ok(rewards)
+}
+
+/// Adds the amount to the balance of the address, returning the new balance.
+/// Every balance change goes through this function or `sub_balance`
+pub fn add_balance(
+    storage: &mut dyn Storage,
+    address: &Addr,
+    amount: Uint128,
+    height: u64,
+) -> StdResult<Uint128> {
+    let balance = BALANCES.may_load(storage, address)?.unwrap_or_default() + amount;
+    save_balance(storage, address, balance, height)?;
+    Ok(balance)
+}
+
+/// Removes the amount from the balance of the address, returning the new balance
+pub fn sub_balance(
+    storage: &mut dyn Storage,
+    address: &Addr,
+    amount: Uint128,
+    height: u64,
+) -> StdResult<Uint128> {
+    let balance = BALANCES
+        .may_load(storage, address)?
+        .unwrap_or_default()
+        .checked_sub(amount)?;
+    save_balance(storage, address, balance, height)?;
+    Ok(balance)
+}
+
+/// Stores the token info, recording the total supply in snapshot mode
+pub fn save_token_info(storage: &mut dyn Storage, info: &TokenInfo, height: u64) -> StdResult<()> {
+    TOKEN_INFO.save(storage, info)?;
+    if snapshots_enabled(storage)? {
+        TOTAL_SUPPLY_SNAPSHOTS.save(storage, &info.total_supply, height)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use cosmwasm_std::{
+    Addr, BlockInfo, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage, Uint128,
+};
+
+use crate::contract::execute_mint;
+use crate::error::ContractError;
+use crate::group::update_members;
+use crate::msg::{VestingAccount, VestingScheduleResponse};
+use crate::state::{add_balance, VestingSchedule, BALANCES, VESTING};
+
+/// Schedules of the holder still locking tokens. Fully vested ones are dropped from
+/// storage, so the list only holds what is still relevant
+fn load_schedules(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    holder: &Addr,
+) -> StdResult<Vec<VestingSchedule>> {
+    let mut schedules = VESTING.may_load(storage, holder)?.unwrap_or_default();
+    let count = schedules.len();
+    schedules.retain(|s| !s.locked(block).is_zero());
+    if schedules.len() != count {
+        save_schedules(storage, holder, &schedules)?;
+    }
+    Ok(schedules)
+}
+
+fn save_schedules(
+    storage: &mut dyn Storage,
+    holder: &Addr,
+    schedules: &[VestingSchedule],
+) -> StdResult<()> {
+    if schedules.is_empty() {
+        VESTING.remove(storage, holder);
+        Ok(())
+    } else {
+        VESTING.save(storage, holder, &schedules.to_vec())
+    }
+}
+
+/// Locks part of the holder's balance under the schedule
+pub fn add_vesting(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    holder: &Addr,
+    schedule: VestingSchedule,
+) -> Result<(), ContractError> {
+    if !schedule.is_valid() {
+        return Err(ContractError::InvalidVestingSchedule {});
+    }
+    let mut schedules = load_schedules(storage, block, holder)?;
+    schedules.push(schedule);
+    save_schedules(storage, holder, &schedules)?;
+    Ok(())
+}
+
+/// Amount of the holder's balance that is not vested yet
+pub fn locked_amount(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    holder: &Addr,
+) -> StdResult<Uint128> {
+    let schedules = VESTING.may_load(storage, holder)?.unwrap_or_default();
+    Ok(schedules.iter().map(|s| s.locked(block)).sum())
+}
+
+/// Returns UnvestedTokens if moving the amount out of the holder's balance would touch
+/// the locked tokens. A balance too low for the amount is left to `sub_balance`
+pub fn assert_vested(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    holder: &Addr,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    let balance = BALANCES.may_load(storage, holder)?.unwrap_or_default();
+    if let Ok(remaining) = balance.checked_sub(amount) {
+        let locked: Uint128 = load_schedules(storage, block, holder)?
+            .iter()
+            .map(|s| s.locked(block))
+            .sum();
+        if remaining < locked {
+            return Err(ContractError::UnvestedTokens {});
+        }
+    }
+    Ok(())
+}
+
+/// Creates the locked balances given at instantiation, returning their total
+pub fn create_vesting_accounts(
+    deps: &mut DepsMut,
+    accounts: &[VestingAccount],
+    block: &BlockInfo,
+) -> Result<Uint128, ContractError> {
+    let height = block.height;
+    let mut total_supply = Uint128::zero();
+    let mut holders = vec![];
+    for account in accounts {
+        let address = deps.api.addr_validate(&account.address)?;
+        add_balance(deps.storage, &address, account.schedule.amount, height)?;
+        add_vesting(deps.storage, block, &address, account.schedule.clone())?;
+        total_supply += account.schedule.amount;
+        holders.push(address);
+    }
+    // no hooks can be registered yet
+    update_members(deps.storage, height, &holders.iter().collect::<Vec<_>>())?;
+
+    Ok(total_supply)
+}
+
+/// Mints the amount of the schedule to the recipient, locked until it vests
+pub fn execute_mint_vesting(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+    schedule: VestingSchedule,
+) -> Result<Response, ContractError> {
+    if !schedule.is_valid() {
+        return Err(ContractError::InvalidVestingSchedule {});
+    }
+    let amount = schedule.amount;
+    let res = execute_mint(deps.branch(), env.clone(), info, recipient.clone(), amount)?;
+
+    let rcpt_addr = deps.api.addr_validate(&recipient)?;
+    add_vesting(deps.storage, &env.block, &rcpt_addr, schedule)?;
+
+    Ok(res.add_attribute("locked", amount))
+}
+
+pub fn query_vesting_schedule(
+    deps: Deps,
+    env: Env,
+    address: String,
+) -> StdResult<VestingScheduleResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let schedules = VESTING
+        .may_load(deps.storage, &address)?
+        .unwrap_or_default();
+    let locked = schedules.iter().map(|s| s.locked(&env.block)).sum();
+    let vested = schedules.iter().map(|s| s.vested(&env.block)).sum();
+    Ok(VestingScheduleResponse {
+        schedules,
+        vested,
+        locked,
+    })
+}
@@ -82,6 +82,7 @@ fn cw3_controls_cw20() {
             cap: None,
         }),
         marketing: None,
+        ..cw20_base::msg::InstantiateMsg::default()
     };
     let cw20_addr = router
         .instantiate_contract(
@@ -2190,6 +2190,7 @@ mod tests {
                     ],
                     mint: None,
                     marketing: None,
+                    ..cw20_base::msg::InstantiateMsg::default()
                 },
                 &[],
                 "Token",
@@ -16,7 +16,7 @@ pub use crate::coin::{Cw20Coin, Cw20CoinVerified};
 pub use crate::denom::{Denom, UncheckedDenom};
 pub use crate::helpers::Cw20Contract;
 pub use crate::logo::{EmbeddedLogo, Logo, LogoInfo};
-pub use crate::msg::Cw20ExecuteMsg;
+pub use crate::msg::{Cw20ExecuteMsg, Cw20Send};
 pub use crate::query::{
     AllAccountsResponse, AllAllowancesResponse, AllSpenderAllowancesResponse, AllowanceInfo,
     AllowanceResponse, BalanceResponse, Cw20QueryMsg, DownloadLogoResponse, MarketingInfoResponse,
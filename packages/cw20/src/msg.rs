@@ -1,3 +1,4 @@
+use crate::coin::Cw20Coin;
 use crate::logo::Logo;
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Binary, Uint128};
@@ -17,6 +18,12 @@ pub enum Cw20ExecuteMsg {
         amount: Uint128,
         msg: Binary,
     },
+    /// Moves tokens to several accounts at once, like a `Transfer` to each of them.
+    /// Either all transfers succeed or none does.
+    BatchTransfer { transfers: Vec<Cw20Coin> },
+    /// Sends tokens to several contracts at once, like a `Send` to each of them.
+    /// Either all sends succeed or none does.
+    BatchSend { sends: Vec<Cw20Send> },
     /// Only with "approval" extension. Allows spender to access an additional amount tokens
     /// from the owner's (env.sender) account. If expires is Some(), overwrites current allowance
     /// expiration with this one.
@@ -71,3 +78,11 @@ pub enum Cw20ExecuteMsg {
     /// If set as the "marketing" role on the contract, upload a new URL, SVG, or PNG for the token
     UploadLogo(Logo),
 }
+
+/// One of the sends of a `BatchSend`
+#[cw_serde]
+pub struct Cw20Send {
+    pub contract: String,
+    pub amount: Uint128,
+    pub msg: Binary,
+}